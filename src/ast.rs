@@ -0,0 +1,787 @@
+use crate::*;
+use std::fmt;
+
+/// a typed syntax tree built on top of the lexer's token string.
+///
+/// the span is the part of the input the node was parsed from, nodes that were built rather than parsed have none.
+/// comparing two trees ignores their spans.
+#[derive(Clone, Debug)]
+pub struct Ast {
+    pub kind: AstKind,
+    pub span: Option<Span>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AstKind {
+    Number(Number),
+    Identity(String),
+    UnaryOperation {
+        operator: Operator,
+        operand: Box<Ast>,
+    },
+    BinaryOperation {
+        operator: Operator,
+        left: Box<Ast>,
+        right: Box<Ast>,
+    },
+    Call {
+        identity: String,
+        args: Vec<Ast>,
+    },
+    Grouping(Box<Ast>),
+    /// an anonymous function like "x -> x^2" or "(x, y) -> x * y", it can only be passed to a function.
+    Lambda {
+        arg_names: Vec<String>,
+        body: Box<Ast>,
+    },
+}
+
+impl PartialEq for Ast {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl From<AstKind> for Ast {
+    fn from(kind: AstKind) -> Self {
+        Self { kind, span: None }
+    }
+}
+
+impl Ast {
+    pub fn new(kind: AstKind, span: Option<Span>) -> Self {
+        Self { kind, span }
+    }
+
+    pub fn number(num: f64) -> Self {
+        AstKind::Number(num.into()).into()
+    }
+
+    pub fn identity(identity: &str) -> Self {
+        AstKind::Identity(identity.to_string()).into()
+    }
+
+    pub fn unary_operation(operator: Operator, operand: Ast) -> Self {
+        AstKind::UnaryOperation {
+            operator,
+            operand: Box::new(operand),
+        }
+        .into()
+    }
+
+    pub fn binary_operation(operator: Operator, left: Ast, right: Ast) -> Self {
+        AstKind::BinaryOperation {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+        .into()
+    }
+
+    pub fn call(identity: &str, args: Vec<Ast>) -> Self {
+        AstKind::Call {
+            identity: identity.to_string(),
+            args,
+        }
+        .into()
+    }
+
+    pub fn grouping(inner: Ast) -> Self {
+        AstKind::Grouping(Box::new(inner)).into()
+    }
+
+    pub fn lambda(arg_names: Vec<String>, body: Ast) -> Self {
+        AstKind::Lambda {
+            arg_names,
+            body: Box::new(body),
+        }
+        .into()
+    }
+
+    pub fn parse_str(input: &str) -> Result<Self, MathError> {
+        parse_spanned_ast(&parse_spanned_str(input)?)
+    }
+
+    pub fn parse_str_with_options(input: &str, options: &ParseOptions) -> Result<Self, MathError> {
+        parse_spanned_ast(&parse_spanned_str_with_options(input, options)?)
+    }
+
+    pub fn get_num(&self) -> Option<f64> {
+        if let AstKind::Number(num) = &self.kind {
+            Some(num.value)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_identity(&self) -> Option<&String> {
+        if let AstKind::Identity(identity) = &self.kind {
+            Some(identity)
+        } else {
+            None
+        }
+    }
+
+    /// this will collect every identity in the tree along with its node, including invoked functions.
+    /// the arguments of a lambda are not included inside its body, they are bound by the lambda.
+    pub fn identity_nodes(&self) -> Vec<(&String, &Ast)> {
+        match &self.kind {
+            AstKind::Number(_) => vec![],
+            AstKind::Identity(identity) => vec![(identity, self)],
+            AstKind::UnaryOperation { operand, .. } => operand.identity_nodes(),
+            AstKind::BinaryOperation { left, right, .. } => left
+                .identity_nodes()
+                .into_iter()
+                .chain(right.identity_nodes())
+                .collect(),
+            AstKind::Call { identity, args } => std::iter::once((identity, self))
+                .chain(args.iter().flat_map(|arg| arg.identity_nodes()))
+                .collect(),
+            AstKind::Grouping(inner) => inner.identity_nodes(),
+            AstKind::Lambda { arg_names, body } => body
+                .identity_nodes()
+                .into_iter()
+                .filter(|(identity, node)| {
+                    matches!(node.kind, AstKind::Call { .. }) || !arg_names.contains(identity)
+                })
+                .collect(),
+        }
+    }
+
+    /// this will collect every identity in the tree, including the signatures of invoked functions.
+    pub fn identities(&self) -> Vec<&String> {
+        self.identity_nodes()
+            .into_iter()
+            .map(|(identity, _)| identity)
+            .collect()
+    }
+}
+
+/// how tightly each kind of node binds when printed, matching the precedence levels of the parser.
+const LAMBDA_PRECEDENCE: u8 = 0;
+const OR_PRECEDENCE: u8 = 1;
+const AND_PRECEDENCE: u8 = 2;
+const EQUALITY_PRECEDENCE: u8 = 3;
+const COMPARISON_PRECEDENCE: u8 = 4;
+const ADDITIVE_PRECEDENCE: u8 = 5;
+const MULTIPLICATIVE_PRECEDENCE: u8 = 6;
+const UNARY_PRECEDENCE: u8 = 7;
+const POWER_PRECEDENCE: u8 = 8;
+const PRIMARY_PRECEDENCE: u8 = 10;
+
+impl Ast {
+    fn precedence(&self) -> u8 {
+        match &self.kind {
+            AstKind::Lambda { .. } => LAMBDA_PRECEDENCE,
+            AstKind::Number(num) if num.value < 0f64 => UNARY_PRECEDENCE,
+            AstKind::UnaryOperation { operator, .. } if operator.is_prefix_operator() => {
+                UNARY_PRECEDENCE
+            }
+            AstKind::BinaryOperation { operator, .. } => match operator {
+                Operator::Or => OR_PRECEDENCE,
+                Operator::And => AND_PRECEDENCE,
+                Operator::Equal | Operator::NotEqual => EQUALITY_PRECEDENCE,
+                operator if operator.is_comparison_operator() => COMPARISON_PRECEDENCE,
+                Operator::Addition | Operator::Subtraction => ADDITIVE_PRECEDENCE,
+                Operator::Exponentiation => POWER_PRECEDENCE,
+                _ => MULTIPLICATIVE_PRECEDENCE,
+            },
+            _ => PRIMARY_PRECEDENCE,
+        }
+    }
+
+    /// this will print the node, wrapping it in parentheses if it binds looser than the given precedence.
+    fn fmt_at_least(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+/// this will print the tree back to text that parses to the same tree, with only the parentheses it needs.
+impl fmt::Display for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            AstKind::Number(num) => write!(f, "{}", num.value),
+            AstKind::Identity(identity) => write!(f, "{identity}"),
+            AstKind::Grouping(inner) => write!(f, "({inner})"),
+            AstKind::Lambda { arg_names, body } => match arg_names.as_slice() {
+                [arg_name] => write!(f, "{arg_name} -> {body}"),
+                _ => write!(f, "({}) -> {body}", arg_names.join(", ")),
+            },
+            AstKind::Call { identity, args } => {
+                write!(f, "{identity}(")?;
+                for (index, arg) in args.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+            AstKind::UnaryOperation { operator, operand } if operator.is_prefix_operator() => {
+                write!(f, "{}", operator.symbol())?;
+                operand.fmt_at_least(f, UNARY_PRECEDENCE)
+            }
+            // a postfix operand is always a primary so "(3!)!" is not printed as the double factorial "3!!"
+            AstKind::UnaryOperation { operator, operand } => {
+                operand.fmt_at_least(f, PRIMARY_PRECEDENCE)?;
+                write!(f, "{}", operator.symbol())
+            }
+            AstKind::BinaryOperation {
+                operator: Operator::Exponentiation,
+                left,
+                right,
+            } => {
+                left.fmt_at_least(f, POWER_PRECEDENCE + 1)?;
+                write!(f, "^")?;
+                right.fmt_at_least(f, UNARY_PRECEDENCE)
+            }
+            // comparisons can not be chained, so a comparison on either side is wrapped in parentheses
+            AstKind::BinaryOperation {
+                operator,
+                left,
+                right,
+            } if operator.is_comparison_operator() => {
+                let precedence = self.precedence();
+                left.fmt_at_least(f, precedence + 1)?;
+                write!(f, " {} ", operator.symbol())?;
+                right.fmt_at_least(f, precedence + 1)
+            }
+            AstKind::BinaryOperation {
+                operator,
+                left,
+                right,
+            } => {
+                let precedence = self.precedence();
+                left.fmt_at_least(f, precedence)?;
+                write!(f, " {} ", operator.symbol())?;
+                right.fmt_at_least(f, precedence + 1)
+            }
+        }
+    }
+}
+
+type AstParseResult<'a> = Result<(Ast, &'a [SpannedToken]), MathError>;
+type CallArgsParseResult<'a> = Result<(Vec<Ast>, Option<Span>, &'a [SpannedToken]), MathError>;
+
+/// this will parse a string into a syntax tree.
+pub fn parse_ast_str(input: &str) -> Result<Ast, MathError> {
+    Ast::parse_str(input)
+}
+
+/// this will parse a string into a syntax tree using the given parse options.
+pub fn parse_ast_str_with_options(input: &str, options: &ParseOptions) -> Result<Ast, MathError> {
+    Ast::parse_str_with_options(input, options)
+}
+
+/// this will parse a token string into a syntax tree. the whole token string must be consumed.
+/// a bare token string has no spans so neither will the tree or its errors.
+pub fn parse_ast(expression: &[Token]) -> Result<Ast, MathError> {
+    parse_spanned_ast(
+        &expression
+            .iter()
+            .cloned()
+            .map(SpannedToken::from)
+            .collect::<Vec<SpannedToken>>(),
+    )
+}
+
+/// this will parse a spanned token string into a syntax tree whose nodes and errors point back into the input.
+pub fn parse_spanned_ast(expression: &[SpannedToken]) -> Result<Ast, MathError> {
+    let end_of_input = expression
+        .last()
+        .and_then(|spanned_token| spanned_token.span)
+        .map(|span| Span::new(span.end, span.end));
+
+    let (ast, remaining_tokens) = parse_lambda(expression).map_err(|error| match error {
+        MathError::UnexpectedEnd { .. } => error.with_span(end_of_input),
+        _ => error,
+    })?;
+
+    match remaining_tokens.first() {
+        None => Ok(ast),
+        Some(spanned_token) if spanned_token.token.is_close_paren() => {
+            Err(MathError::UnbalancedParens {
+                span: spanned_token.span,
+            })
+        }
+        Some(spanned_token) => Err(unexpected_token(spanned_token)),
+    }
+}
+
+fn unexpected_token(spanned_token: &SpannedToken) -> MathError {
+    MathError::UnexpectedToken {
+        span: spanned_token.span,
+        found: spanned_token.token.to_string(),
+    }
+}
+
+fn next_operator_in<'a>(
+    tokens: &'a [SpannedToken],
+    operators: &[Operator],
+) -> Option<(&'a Operator, Option<Span>)> {
+    tokens
+        .first()
+        .and_then(|spanned_token| {
+            spanned_token
+                .token
+                .get_operator()
+                .map(|operator| (operator, spanned_token.span))
+        })
+        .filter(|(operator, _)| operators.contains(operator))
+}
+
+/// this will parse a left associative chain of binary operations of the same precedence.
+fn parse_left_associative<'a>(
+    tokens: &'a [SpannedToken],
+    operators: &[Operator],
+    parse_operand: fn(&'a [SpannedToken]) -> AstParseResult<'a>,
+) -> AstParseResult<'a> {
+    let (mut left, mut tokens) = parse_operand(tokens)?;
+    while let Some((operator, _)) = next_operator_in(tokens, operators) {
+        let (right, remaining_tokens) = parse_operand(&tokens[1..])?;
+        let span = Span::join(left.span, right.span);
+        left = Ast::new(
+            AstKind::BinaryOperation {
+                operator: *operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            span,
+        );
+        tokens = remaining_tokens;
+    }
+    Ok((left, tokens))
+}
+
+/// this will parse a single binary operation of the given operators, chaining them like "a < b < c" is an error
+/// rather than comparing the boolean "a < b" to c.
+fn parse_non_associative<'a>(
+    tokens: &'a [SpannedToken],
+    operators: &[Operator],
+    parse_operand: fn(&'a [SpannedToken]) -> AstParseResult<'a>,
+) -> AstParseResult<'a> {
+    let (left, tokens) = parse_operand(tokens)?;
+    let Some((operator, _)) = next_operator_in(tokens, operators) else {
+        return Ok((left, tokens));
+    };
+
+    let (right, remaining_tokens) = parse_operand(&tokens[1..])?;
+    if next_operator_in(remaining_tokens, operators).is_some() {
+        return Err(unexpected_token(&remaining_tokens[0]));
+    }
+    let span = Span::join(left.span, right.span);
+    Ok((
+        Ast::new(
+            AstKind::BinaryOperation {
+                operator: *operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            span,
+        ),
+        remaining_tokens,
+    ))
+}
+
+/// a lambda is the loosest binding expression, its body extends as far as it can so "x -> x + 1" is "x -> (x + 1)".
+fn parse_lambda(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    let Some((arg_names, arrow_index)) = lambda_arg_names(tokens) else {
+        return parse_conditional(tokens);
+    };
+
+    let (body, remaining_tokens) = parse_lambda(&tokens[arrow_index + 1..])?;
+    let span = Span::join(tokens[0].span, body.span);
+    Ok((
+        Ast::new(
+            AstKind::Lambda {
+                arg_names,
+                body: Box::new(body),
+            },
+            span,
+        ),
+        remaining_tokens,
+    ))
+}
+
+/// this will find the argument names of a lambda like "x ->" or "(x, y) ->" along with the index of its arrow.
+fn lambda_arg_names(tokens: &[SpannedToken]) -> Option<(Vec<String>, usize)> {
+    let is_arrow = |spanned_token: &SpannedToken| {
+        spanned_token.token.get_operator() == Some(&Operator::Lambda)
+    };
+
+    match tokens {
+        [arg_name, arrow, ..] if is_arrow(arrow) => {
+            Some((vec![arg_name.token.get_identity()?.clone()], 1))
+        }
+        [open_paren, rest @ ..] if open_paren.token.is_open_paren() => {
+            let close_paren_index = rest
+                .iter()
+                .position(|spanned_token| spanned_token.token.is_close_paren())?;
+            if !rest.get(close_paren_index + 1).is_some_and(is_arrow) {
+                return None;
+            }
+
+            let arg_names = rest[..close_paren_index]
+                .split(|spanned_token| spanned_token.token.is_argument_separator())
+                .map(|arg_name| match arg_name {
+                    [arg_name] => arg_name.token.get_identity().cloned(),
+                    _ => None,
+                })
+                .collect::<Option<Vec<String>>>()?;
+            Some((arg_names, close_paren_index + 2))
+        }
+        _ => None,
+    }
+}
+
+/// a conditional like "c ? a : b" is built as an invocation of the special form "if". its branches can be
+/// conditionals too, so "a ? 1 : b ? 2 : 3" is "a ? 1 : (b ? 2 : 3)".
+fn parse_conditional(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    let (condition, tokens) = parse_or(tokens)?;
+    if next_operator_in(tokens, &[Operator::Conditional]).is_none() {
+        return Ok((condition, tokens));
+    }
+
+    let (then, tokens) = parse_conditional(&tokens[1..])?;
+    let tokens = match tokens.split_first() {
+        Some((colon, remaining_tokens))
+            if colon.token.get_operator() == Some(&Operator::FunctionAssignment) =>
+        {
+            remaining_tokens
+        }
+        Some((spanned_token, _)) => return Err(unexpected_token(spanned_token)),
+        None => return Err(MathError::UnexpectedEnd { span: None }),
+    };
+    let (otherwise, remaining_tokens) = parse_conditional(tokens)?;
+
+    let span = Span::join(condition.span, otherwise.span);
+    Ok((
+        Ast::new(
+            AstKind::Call {
+                identity: "if".to_string(),
+                args: vec![condition, then, otherwise],
+            },
+            span,
+        ),
+        remaining_tokens,
+    ))
+}
+
+fn parse_or(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    parse_left_associative(tokens, &[Operator::Or], parse_and)
+}
+
+fn parse_and(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    parse_left_associative(tokens, &[Operator::And], parse_equality)
+}
+
+/// an equality binds looser than the other comparisons so "a < b == c < d" compares two booleans.
+fn parse_equality(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    parse_non_associative(
+        tokens,
+        &[Operator::Equal, Operator::NotEqual],
+        parse_comparison,
+    )
+}
+
+fn parse_comparison(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    parse_non_associative(
+        tokens,
+        &[
+            Operator::LessThan,
+            Operator::LessThanOrEqual,
+            Operator::GreaterThan,
+            Operator::GreaterThanOrEqual,
+        ],
+        parse_additive,
+    )
+}
+
+/// an interval around a value like "x ± dx" binds like an addition, and is built as an invocation of "interval"
+/// from "x - dx" to "x + dx".
+fn parse_additive(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    let (mut left, mut tokens) = parse_multiplicative(tokens)?;
+    while let Some((operator, _)) = next_operator_in(
+        tokens,
+        &[
+            Operator::Addition,
+            Operator::Subtraction,
+            Operator::PlusMinus,
+        ],
+    ) {
+        let (right, remaining_tokens) = parse_multiplicative(&tokens[1..])?;
+        let span = Span::join(left.span, right.span);
+        let kind = match operator {
+            Operator::PlusMinus => AstKind::Call {
+                identity: INTERVAL.to_string(),
+                args: vec![
+                    Ast::binary_operation(Operator::Subtraction, left.clone(), right.clone()),
+                    Ast::binary_operation(Operator::Addition, left, right),
+                ],
+            },
+            _ => AstKind::BinaryOperation {
+                operator: *operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        };
+        left = Ast::new(kind, span);
+        tokens = remaining_tokens;
+    }
+    Ok((left, tokens))
+}
+
+fn parse_multiplicative(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    parse_left_associative(
+        tokens,
+        &[
+            Operator::Multiplication,
+            Operator::Division,
+            Operator::Modulus,
+        ],
+        parse_implicit_multiplicative,
+    )
+}
+
+/// an implicit multiplication binds tighter than "*" and "/" so "1/2x" is "1/(2x)", but looser than a sign or
+/// exponentiation so "-2x" is "(-2)x" and "2x^2" is "2(x^2)".
+/// it is built as an ordinary multiplication, the tree does not remember how it was written.
+fn parse_implicit_multiplicative(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    let (mut left, mut tokens) = parse_unary(tokens)?;
+    while next_operator_in(tokens, &[Operator::ImplicitMultiplication]).is_some() {
+        let (right, remaining_tokens) = parse_unary(&tokens[1..])?;
+        let span = Span::join(left.span, right.span);
+        left = Ast::new(
+            AstKind::BinaryOperation {
+                operator: Operator::Multiplication,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            span,
+        );
+        tokens = remaining_tokens;
+    }
+    Ok((left, tokens))
+}
+
+/// a sign or a not binds looser than exponentiation so "-2^2" is "-(2^2)".
+/// a factorial can not start an operand, so a "!" there is a not and a "!!" is two of them.
+fn parse_unary(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    let not = |operand: Ast, span: Option<Span>| {
+        Ast::new(
+            AstKind::UnaryOperation {
+                operator: Operator::Not,
+                operand: Box::new(operand),
+            },
+            span,
+        )
+    };
+
+    match next_operator_in(
+        tokens,
+        &[
+            Operator::Addition,
+            Operator::Subtraction,
+            Operator::Factorial,
+            Operator::DoubleFactorial,
+        ],
+    ) {
+        Some((operator, operator_span)) => {
+            let (operand, remaining_tokens) = parse_unary(&tokens[1..])?;
+            let span = Span::join(operator_span, operand.span);
+            let ast = match operator {
+                Operator::Factorial => not(operand, span),
+                Operator::DoubleFactorial => {
+                    let inner_span = Span::join(
+                        operator_span.map(|span| Span::new(span.start + 1, span.end)),
+                        operand.span,
+                    );
+                    not(not(operand, inner_span), span)
+                }
+                _ => Ast::new(
+                    AstKind::UnaryOperation {
+                        operator: *operator,
+                        operand: Box::new(operand),
+                    },
+                    span,
+                ),
+            };
+            Ok((ast, remaining_tokens))
+        }
+        None => parse_power(tokens),
+    }
+}
+
+/// exponentiation is right associative and its exponent may carry a sign, like "2^-1".
+fn parse_power(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    let (base, tokens) = parse_postfix(tokens)?;
+    match next_operator_in(tokens, &[Operator::Exponentiation]) {
+        Some((operator, _)) => {
+            let (exponent, remaining_tokens) = parse_unary(&tokens[1..])?;
+            let span = Span::join(base.span, exponent.span);
+            Ok((
+                Ast::new(
+                    AstKind::BinaryOperation {
+                        operator: *operator,
+                        left: Box::new(base),
+                        right: Box::new(exponent),
+                    },
+                    span,
+                ),
+                remaining_tokens,
+            ))
+        }
+        None => Ok((base, tokens)),
+    }
+}
+
+/// postfix operators bind tighter than exponentiation so "2^3!" is "2^(3!)".
+fn parse_postfix(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    let (mut operand, mut tokens) = parse_primary(tokens)?;
+    while let Some((operator, operator_span)) =
+        next_operator_in(tokens, &[Operator::Factorial, Operator::DoubleFactorial])
+    {
+        let span = Span::join(operand.span, operator_span);
+        operand = Ast::new(
+            AstKind::UnaryOperation {
+                operator: *operator,
+                operand: Box::new(operand),
+            },
+            span,
+        );
+        tokens = &tokens[1..];
+    }
+    Ok((operand, tokens))
+}
+
+fn parse_primary(tokens: &[SpannedToken]) -> AstParseResult<'_> {
+    let Some((first_token, remaining_tokens)) = tokens.split_first() else {
+        return Err(MathError::UnexpectedEnd { span: None });
+    };
+
+    match (&first_token.token, remaining_tokens) {
+        (Token::Number(num), _) => Ok((
+            Ast::new(AstKind::Number(num.clone()), first_token.span),
+            remaining_tokens,
+        )),
+        (Token::Identity(identity), [open_paren, remaining_tokens @ ..])
+            if open_paren.token.is_open_paren() =>
+        {
+            let (args, close_paren_span, remaining_tokens) =
+                parse_call_args(remaining_tokens, open_paren.span)?;
+            Ok((
+                Ast::new(
+                    AstKind::Call {
+                        identity: identity.clone(),
+                        args,
+                    },
+                    Span::join(first_token.span, close_paren_span),
+                ),
+                remaining_tokens,
+            ))
+        }
+        (Token::Identity(identity), _) => Ok((
+            Ast::new(AstKind::Identity(identity.clone()), first_token.span),
+            remaining_tokens,
+        )),
+        (Token::Operator(Operator::OpenParen), _) => {
+            let (inner, remaining_tokens) = parse_lambda(remaining_tokens)?;
+            match remaining_tokens.split_first() {
+                Some((close_paren, remaining_tokens)) if close_paren.token.is_close_paren() => {
+                    Ok((
+                        Ast::new(
+                            AstKind::Grouping(Box::new(inner)),
+                            Span::join(first_token.span, close_paren.span),
+                        ),
+                        remaining_tokens,
+                    ))
+                }
+                Some((spanned_token, _)) => Err(unexpected_token(spanned_token)),
+                None => Err(MathError::UnbalancedParens {
+                    span: first_token.span,
+                }),
+            }
+        }
+        (Token::Operator(Operator::OpenBracket), _) => {
+            let (interval, remaining_tokens) = parse_interval(remaining_tokens, first_token.span)?;
+            Ok((interval, remaining_tokens))
+        }
+        (Token::Operator(Operator::CloseParen | Operator::CloseBracket), _) => {
+            Err(MathError::UnbalancedParens {
+                span: first_token.span,
+            })
+        }
+        _ => Err(unexpected_token(first_token)),
+    }
+}
+
+/// this will parse the bounds of an interval literal like "[1.9, 2.1]" up to and including its close bracket, which
+/// is built as an invocation of "interval".
+fn parse_interval(tokens: &[SpannedToken], open_bracket_span: Option<Span>) -> AstParseResult<'_> {
+    let (lower, tokens) = parse_lambda(tokens)?;
+    let tokens = match tokens.split_first() {
+        Some((separator, remaining_tokens)) if separator.token.is_argument_separator() => {
+            remaining_tokens
+        }
+        Some((spanned_token, _)) => return Err(unexpected_token(spanned_token)),
+        None => {
+            return Err(MathError::UnbalancedParens {
+                span: open_bracket_span,
+            })
+        }
+    };
+    let (upper, tokens) = parse_lambda(tokens)?;
+    match tokens.split_first() {
+        Some((close_bracket, remaining_tokens)) if close_bracket.token.is_close_bracket() => Ok((
+            Ast::new(
+                AstKind::Call {
+                    identity: INTERVAL.to_string(),
+                    args: vec![lower, upper],
+                },
+                Span::join(open_bracket_span, close_bracket.span),
+            ),
+            remaining_tokens,
+        )),
+        Some((spanned_token, _)) => Err(unexpected_token(spanned_token)),
+        None => Err(MathError::UnbalancedParens {
+            span: open_bracket_span,
+        }),
+    }
+}
+
+/// this will parse the comma separated arguments of a function invocation up to and including its close paren.
+fn parse_call_args(
+    tokens: &[SpannedToken],
+    open_paren_span: Option<Span>,
+) -> CallArgsParseResult<'_> {
+    if let Some((close_paren, remaining_tokens)) = tokens.split_first() {
+        if close_paren.token.is_close_paren() {
+            return Ok((vec![], close_paren.span, remaining_tokens));
+        }
+    }
+
+    let mut args = vec![];
+    let mut tokens = tokens;
+    loop {
+        let (arg, remaining_tokens) = parse_lambda(tokens)?;
+        args.push(arg);
+        match remaining_tokens.split_first() {
+            Some((separator, remaining_tokens)) if separator.token.is_argument_separator() => {
+                tokens = remaining_tokens
+            }
+            Some((close_paren, remaining_tokens)) if close_paren.token.is_close_paren() => {
+                return Ok((args, close_paren.span, remaining_tokens))
+            }
+            Some((spanned_token, _)) => return Err(unexpected_token(spanned_token)),
+            None => {
+                return Err(MathError::UnbalancedParens {
+                    span: open_paren_span,
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use crate::*;
+use std::fmt;
+
+/// a byte range in the original input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// this will make a span covering both spans and everything between them.
+    pub fn to(&self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// this will join two optional spans, keeping whichever is known if only one is.
+    pub fn join(first: Option<Span>, second: Option<Span>) -> Option<Span> {
+        match (first, second) {
+            (Some(first), Some(second)) => Some(first.to(second)),
+            _ => first.or(second),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MathError {
+    Lex {
+        span: Option<Span>,
+        message: String,
+    },
+    UnbalancedParens {
+        span: Option<Span>,
+    },
+    UnexpectedToken {
+        span: Option<Span>,
+        found: String,
+    },
+    UnexpectedEnd {
+        span: Option<Span>,
+    },
+    UnknownIdentifier {
+        span: Option<Span>,
+        identifier: String,
+    },
+    NotAFunction {
+        span: Option<Span>,
+        identifier: String,
+    },
+    NotAValue {
+        span: Option<Span>,
+        identifier: String,
+    },
+    ArityMismatch {
+        span: Option<Span>,
+        expected: usize,
+        received: usize,
+    },
+    MissingArguments {
+        span: Option<Span>,
+    },
+    InvalidOperator {
+        span: Option<Span>,
+        operator: Operator,
+    },
+    InvalidDefinition {
+        span: Option<Span>,
+        message: String,
+    },
+    Domain {
+        span: Option<Span>,
+        message: String,
+    },
+    InvalidArgument {
+        span: Option<Span>,
+        message: String,
+    },
+    /// an iterative method like root finding gave up before reaching its tolerance.
+    NotConverged {
+        span: Option<Span>,
+        message: String,
+    },
+    /// defined functions were nested in each other deeper than the recursion depth limit, like a recursive
+    /// function that never reaches its base case.
+    RecursionLimit {
+        span: Option<Span>,
+        limit: usize,
+    },
+    /// the input only defined functions or had no statements at all, so there is no value to return.
+    MissingValue {
+        span: Option<Span>,
+    },
+    /// a built in function was invoked while evaluating over a numeric backend that does not support it.
+    Unsupported {
+        span: Option<Span>,
+        name: String,
+        backend: String,
+    },
+}
+impl MathError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Lex { span, .. }
+            | Self::UnbalancedParens { span }
+            | Self::UnexpectedToken { span, .. }
+            | Self::UnexpectedEnd { span }
+            | Self::UnknownIdentifier { span, .. }
+            | Self::NotAFunction { span, .. }
+            | Self::NotAValue { span, .. }
+            | Self::ArityMismatch { span, .. }
+            | Self::MissingArguments { span }
+            | Self::InvalidOperator { span, .. }
+            | Self::InvalidDefinition { span, .. }
+            | Self::Domain { span, .. }
+            | Self::InvalidArgument { span, .. }
+            | Self::NotConverged { span, .. }
+            | Self::RecursionLimit { span, .. }
+            | Self::MissingValue { span }
+            | Self::Unsupported { span, .. } => *span,
+        }
+    }
+
+    fn span_mut(&mut self) -> &mut Option<Span> {
+        match self {
+            Self::Lex { span, .. }
+            | Self::UnbalancedParens { span }
+            | Self::UnexpectedToken { span, .. }
+            | Self::UnexpectedEnd { span }
+            | Self::UnknownIdentifier { span, .. }
+            | Self::NotAFunction { span, .. }
+            | Self::NotAValue { span, .. }
+            | Self::ArityMismatch { span, .. }
+            | Self::MissingArguments { span }
+            | Self::InvalidOperator { span, .. }
+            | Self::InvalidDefinition { span, .. }
+            | Self::Domain { span, .. }
+            | Self::InvalidArgument { span, .. }
+            | Self::NotConverged { span, .. }
+            | Self::RecursionLimit { span, .. }
+            | Self::MissingValue { span }
+            | Self::Unsupported { span, .. } => span,
+        }
+    }
+
+    /// this will give the error a span if it does not already have one.
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        if self.span().is_none() {
+            *self.span_mut() = span;
+        }
+        self
+    }
+
+    /// this will replace the span of the error, used when the original span points into a different input.
+    pub fn at(mut self, span: Option<Span>) -> Self {
+        *self.span_mut() = span;
+        self
+    }
+
+    /// this will render the error with the line of the input it occured on and a caret under the offending span.
+    pub fn render(&self, input: &str) -> String {
+        let message = format!("error : {self}");
+
+        let Some(span) = self.span() else {
+            return message;
+        };
+
+        let line_start = input
+            .get(..span.start)
+            .map(|before| before.rfind('\n').map_or(0, |index| index + 1));
+        let line_end = input.get(span.start..).map(|after| {
+            after
+                .find('\n')
+                .map_or(input.len(), |index| span.start + index)
+        });
+
+        match (line_start, line_end) {
+            (Some(line_start), Some(line_end)) => {
+                let column = input[line_start..span.start].chars().count();
+                let width = input
+                    .get(span.start..span.end.min(line_end))
+                    .map_or(1, |spanned| spanned.chars().count().max(1));
+                format!(
+                    "{message}\n{}\n{}{}",
+                    &input[line_start..line_end],
+                    " ".repeat(column),
+                    "^".repeat(width)
+                )
+            }
+            _ => message,
+        }
+    }
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lex { message, .. } => write!(f, "{message}"),
+            Self::UnbalancedParens { .. } => write!(f, "unbalanced parentheses"),
+            Self::UnexpectedToken { found, .. } => write!(f, "unexpected {found:?}"),
+            Self::UnexpectedEnd { .. } => write!(f, "unexpected end of input"),
+            Self::UnknownIdentifier { identifier, .. } => {
+                write!(f, "unknown identifier {identifier:?}")
+            }
+            Self::NotAFunction { identifier, .. } => write!(f, "{identifier:?} is not a function"),
+            Self::NotAValue { identifier, .. } => {
+                write!(f, "{identifier:?} is a function and must be invoked")
+            }
+            Self::ArityMismatch {
+                expected, received, ..
+            } => write!(f, "expected {expected} args but recieved : {received}"),
+            Self::MissingArguments { .. } => {
+                write!(f, "arguments must be supplied to a function")
+            }
+            Self::InvalidOperator { operator, .. } => {
+                write!(f, "{} can not be used here", operator.symbol())
+            }
+            Self::InvalidDefinition { message, .. } => {
+                write!(f, "invalid definition : {message}")
+            }
+            Self::Domain { message, .. } => write!(f, "{message}"),
+            Self::InvalidArgument { message, .. } => write!(f, "invalid argument : {message}"),
+            Self::NotConverged { message, .. } => write!(f, "did not converge : {message}"),
+            Self::RecursionLimit { limit, .. } => {
+                write!(f, "exceeded the maximum recursion depth of {limit}")
+            }
+            Self::MissingValue { .. } => write!(f, "there is no value to evaluate"),
+            Self::Unsupported { name, backend, .. } => {
+                write!(
+                    f,
+                    "{name:?} is not supported when evaluating {backend} numbers"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
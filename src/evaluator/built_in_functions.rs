@@ -6,13 +6,13 @@ use crate::*;
 #[derive(Debug, Clone)]
 pub struct BuiltInFunction {
     pub arg_count : Option<usize>,
-    pub function : fn(&[f64]) -> Result<f64, String>,
+    pub function : fn(&[f64]) -> Result<f64, MathError>,
 }
 impl BuiltInFunction {
-    pub fn new(function : fn(&[f64]) -> Result<f64, String>, arg_count : Option<usize>) -> Self {
+    pub fn new(function : fn(&[f64]) -> Result<f64, MathError>, arg_count : Option<usize>) -> Self {
         Self {
-            arg_count : arg_count,
-            function : function
+            arg_count,
+            function
         }
     }
 
@@ -41,28 +41,45 @@ impl BuiltInFunction {
             "log10" =>          Self::new(|args| Ok(args[0].log10()), Some(1)),
             "log2" =>           Self::new(|args| Ok(args[0].log2()), Some(1)),
             "ln" =>             Self::new(|args| Ok(args[0].ln()), Some(1)),
+            "gamma" =>          Self::new(|args| gamma(args[0]), Some(1)),
+            "lgamma" =>         Self::new(|args| ln_gamma(args[0]), Some(1)),
+            "factorial" =>      Self::new(|args| factorial(args[0]), Some(1)),
+            "polygamma" =>      Self::new(|args| polygamma(args[0], args[1]), Some(2)),
             "mean" =>           Self::new(|args| Ok(args.iter().fold(0f64, |acc, x| acc + x) / args.len() as f64), None),
+            // the parts of a real number, these are more useful when evaluating complex numbers
+            "re" =>             Self::new(|args| Ok(args[0]), Some(1)),
+            "im" =>             Self::new(|_| Ok(0f64), Some(1)),
+            "conj" =>           Self::new(|args| Ok(args[0]), Some(1)),
+            "arg" =>            Self::new(|args| Ok(0f64.atan2(args[0])), Some(1)),
+            // an interval literal, which is only supported when evaluating intervals
+            "interval" =>       Self::new(|_| Err(unsupported_interval(f64::NAME)), Some(2)),
         };
         built_in_function_map
             .into_iter()
             .map(|(identifier, built_in_function)| (identifier, MathDefinition::BuiltInFunction(built_in_function)))
             .collect()
     }
-}
 
-impl MathFunction for BuiltInFunction {
-    fn evaluate(&self, args : &[f64], _math_definitions : &HashMap<&str, MathDefinition>) -> Result<f64, String> {
-        if args.len() == 0 {
-            return Err("error : arguments must be supplied to a function".to_string());
+    /// this will check the function can be invoked with a number of arguments.
+    pub fn check_arg_count(&self, received : usize) -> Result<(), MathError> {
+        if received == 0 {
+            return Err(MathError::MissingArguments { span: None });
         }
-        if self.arg_count.is_some_and(|arg_count| arg_count != args.len()) {
-            return Err(format!(
-                "expected {} args but recieved : {}",
-                self.arg_count.unwrap(),
-                args.len()
-            ));
+        if let Some(arg_count) = self.arg_count.filter(|arg_count| *arg_count != received) {
+            return Err(MathError::ArityMismatch {
+                span: None,
+                expected: arg_count,
+                received,
+            });
         }
-        (self.function)(&args)
+        Ok(())
+    }
+}
+
+impl MathFunction for BuiltInFunction {
+    fn evaluate(&self, args : &[f64], _math_definitions : &HashMap<String, MathDefinition>) -> Result<f64, MathError> {
+        self.check_arg_count(args.len())?;
+        (self.function)(args)
     }
 }
 
@@ -73,10 +90,13 @@ pub fn get_built_in_constants_map() -> HashMap::<&'static str, MathDefinition> {
         "π" => consts::PI,
         "tau" => consts::TAU,
         "τ" => consts::TAU,
+        "true" => boolean_value(true),
+        "false" => boolean_value(false),
     };
 
     constants_map
         .into_iter()
         .map(|(key, value)| (key, MathDefinition::Constant(value)))
+        .chain([(IMAGINARY_UNIT, MathDefinition::ComplexConstant(Complex::I))])
         .collect()
 }
\ No newline at end of file
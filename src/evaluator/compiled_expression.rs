@@ -0,0 +1,644 @@
+use crate::*;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+/// the most arguments a built in function can be invoked with before evaluation falls back to a heap allocated buffer.
+const MAX_STACK_ARGS: usize = 8;
+
+/// the most slots an expression can be evaluated with before evaluation falls back to a heap allocated buffer.
+const MAX_STACK_SLOTS: usize = 32;
+
+#[derive(Clone, Debug)]
+enum CompiledNode {
+    Number(f64),
+    Slot(usize),
+    UnaryOperation(Operator, Box<CompiledNode>),
+    BinaryOperation(Operator, Box<CompiledNode>, Box<CompiledNode>),
+    BuiltInCall(fn(&[f64]) -> Result<f64, MathError>, Vec<CompiledNode>),
+    /// a condition and the branches of an if, only the chosen branch is evaluated.
+    Conditional(
+        Box<CompiledNode>,
+        Box<CompiledNode>,
+        Option<Box<CompiledNode>>,
+    ),
+    /// the argument of an inlined function stored in a slot once, which its body reads wherever it uses the argument.
+    Let(usize, Box<CompiledNode>, Box<CompiledNode>),
+    /// an invocation of a recursive function by its index in the compiled functions, which is evaluated with slots of
+    /// its own rather than inlined.
+    Call(usize, Vec<CompiledNode>),
+    /// a special form that uses the variables in scope, like "sum(k -> k * x, 1, n)", with the function it is passed
+    /// and its other arguments. a sum or product has its bounds, an integral its bounds and tolerance and a root
+    /// either the bounds it is between or the point newton's method starts from.
+    SpecialForm(SpecialForm, Box<CompiledFunctionValue>, Vec<CompiledNode>),
+}
+impl CompiledNode {
+    fn evaluate(
+        &self,
+        variables: &mut [f64],
+        functions: &[CompiledFunction],
+    ) -> Result<f64, MathError> {
+        match self {
+            Self::Number(num) => Ok(*num),
+            Self::Slot(slot) => Ok(variables[*slot]),
+            Self::Let(slot, value, body) => {
+                variables[*slot] = value.evaluate(variables, functions)?;
+                body.evaluate(variables, functions)
+            }
+            Self::UnaryOperation(operator, operand) => {
+                operator.apply_operation_1_operand(operand.evaluate(variables, functions)?)
+            }
+            Self::BinaryOperation(operator, left, right) => {
+                let left = left.evaluate(variables, functions)?;
+                match operator.short_circuit(left)? {
+                    Some(result) => Ok(result),
+                    None => operator
+                        .apply_operation_2_operands(left, right.evaluate(variables, functions)?),
+                }
+            }
+            Self::Conditional(condition, then, otherwise) => {
+                if as_boolean(condition.evaluate(variables, functions)?)? {
+                    then.evaluate(variables, functions)
+                } else {
+                    otherwise
+                        .as_ref()
+                        .ok_or(no_case_applies(None))?
+                        .evaluate(variables, functions)
+                }
+            }
+            Self::BuiltInCall(function, args) if args.len() <= MAX_STACK_ARGS => {
+                let mut arg_buffer = [0f64; MAX_STACK_ARGS];
+                for (reduced_arg, arg) in arg_buffer.iter_mut().zip(args) {
+                    *reduced_arg = arg.evaluate(variables, functions)?;
+                }
+                function(&arg_buffer[..args.len()])
+            }
+            Self::BuiltInCall(function, args) => function(
+                &args
+                    .iter()
+                    .map(|arg| arg.evaluate(variables, functions))
+                    .collect::<Result<Vec<f64>, MathError>>()?,
+            ),
+            Self::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(variables, functions))
+                    .collect::<Result<Vec<f64>, MathError>>()?;
+                let function = &functions[*function];
+                nested(|| evaluate_in_slots(&function.body, &args, function.slot_count, functions))
+            }
+            Self::SpecialForm(special_form, function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.evaluate(variables, functions))
+                    .collect::<Result<Vec<f64>, MathError>>()?;
+                let variables = RefCell::new(variables);
+                let f = |x| function.evaluate(x, &mut variables.borrow_mut(), functions);
+                match (special_form, args.as_slice()) {
+                    (SpecialForm::Sum, [from, to]) => series_sum(f, *from, *to),
+                    (SpecialForm::Prod, [from, to]) => series_product(f, *from, *to),
+                    (SpecialForm::Integrate, [a, b, tolerance]) => integrate(f, *a, *b, *tolerance),
+                    (SpecialForm::Root, [a, b]) => brent(f, *a, *b),
+                    (SpecialForm::Root, [x0]) => newton(
+                        |x| {
+                            let derivative = function.evaluate_derivative(
+                                x,
+                                &mut variables.borrow_mut(),
+                                functions,
+                            )?;
+                            Ok((f(x)?, derivative))
+                        },
+                        *x0,
+                    ),
+                    _ => unreachable!(
+                        "the arguments of a special form are checked when it is compiled"
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// a function passed to a special form, its argument is put in a slot before its body is evaluated.
+#[derive(Clone, Debug)]
+struct CompiledFunctionValue {
+    slot: usize,
+    body: CompiledNode,
+    /// the derivative of the body with respect to the argument, only compiled for newton's method.
+    derivative: Option<CompiledNode>,
+}
+impl CompiledFunctionValue {
+    fn evaluate(
+        &self,
+        arg: f64,
+        variables: &mut [f64],
+        functions: &[CompiledFunction],
+    ) -> Result<f64, MathError> {
+        variables[self.slot] = arg;
+        self.body.evaluate(variables, functions)
+    }
+
+    fn evaluate_derivative(
+        &self,
+        arg: f64,
+        variables: &mut [f64],
+        functions: &[CompiledFunction],
+    ) -> Result<f64, MathError> {
+        variables[self.slot] = arg;
+        self.derivative
+            .as_ref()
+            .expect("the derivative is compiled for newton's method")
+            .evaluate(variables, functions)
+    }
+}
+
+/// a recursive defined function compiled once, its arguments are in its first slots.
+#[derive(Clone, Debug)]
+struct CompiledFunction {
+    body: CompiledNode,
+    /// the arguments and the arguments of every function inlined in the body.
+    slot_count: usize,
+}
+
+/// this will evaluate a node with the given values in its first slots, the other slots are taken by the arguments of
+/// inlined functions.
+fn evaluate_in_slots(
+    node: &CompiledNode,
+    values: &[f64],
+    slot_count: usize,
+    functions: &[CompiledFunction],
+) -> Result<f64, MathError> {
+    if slot_count <= MAX_STACK_SLOTS {
+        let mut slots = [0f64; MAX_STACK_SLOTS];
+        slots[..values.len()].copy_from_slice(values);
+        node.evaluate(&mut slots, functions)
+    } else {
+        let mut slots = values.to_vec();
+        slots.resize(slot_count, 0f64);
+        node.evaluate(&mut slots, functions)
+    }
+}
+
+/// an expression whose identities have been resolved ahead of time so it can be evaluated many times.
+///
+/// variables are resolved to slots in the order they were given to `compile`, constants are substituted
+/// and defined functions are inlined with each argument evaluated once into a slot its body reads. a recursive
+/// function is compiled once and invoked when the expression is evaluated, as deeply as the recursion limit allows.
+#[derive(Clone, Debug)]
+pub struct CompiledExpression {
+    variable_names: Vec<String>,
+    root: CompiledNode,
+    /// the variables and the arguments of every inlined function.
+    slot_count: usize,
+    functions: Vec<CompiledFunction>,
+}
+impl CompiledExpression {
+    pub fn parse_str(
+        input: &str,
+        variable_names: &[&str],
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<Self, MathError> {
+        Self::compile(&parse_ast_str(input)?, variable_names, math_definitions)
+    }
+
+    pub fn compile(
+        ast: &Ast,
+        variable_names: &[&str],
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<Self, MathError> {
+        let slots = variable_names
+            .iter()
+            .enumerate()
+            .map(|(slot, variable_name)| (*variable_name, CompiledNode::Slot(slot)))
+            .collect::<HashMap<&str, CompiledNode>>();
+
+        let slot_count = Cell::new(variable_names.len());
+        let functions = RefCell::new(vec![]);
+        let root = compile_node(ast, math_definitions, &slots, &[], &slot_count, &functions)?;
+
+        Ok(Self {
+            variable_names: variable_names.iter().map(|name| name.to_string()).collect(),
+            root,
+            slot_count: slot_count.get(),
+            functions: functions
+                .into_inner()
+                .into_iter()
+                .map(|(_, function)| {
+                    function.expect("a recursive function is compiled before the expression")
+                })
+                .collect(),
+        })
+    }
+
+    pub fn variable_names(&self) -> &[String] {
+        &self.variable_names
+    }
+
+    /// this will evaluate the expression with one value per variable, in the order the variables were compiled with.
+    pub fn eval(&self, variables: &[f64]) -> Result<f64, MathError> {
+        if variables.len() != self.variable_names.len() {
+            return Err(MathError::ArityMismatch {
+                span: None,
+                expected: self.variable_names.len(),
+                received: variables.len(),
+            });
+        }
+        evaluate_in_slots(&self.root, variables, self.slot_count, &self.functions)
+    }
+}
+
+/// the recursive functions compiled so far with their names. a function takes its index before its body is compiled,
+/// so the body can invoke it.
+type RecursiveFunctions = RefCell<Vec<(String, Option<CompiledFunction>)>>;
+
+/// this will compile a syntax tree. `bindings` maps the names in scope to the nodes that replace them, `inlining` is
+/// the names of the defined functions the tree is inlined in and `slot_count` is the number of slots taken so far.
+fn compile_node(
+    ast: &Ast,
+    math_definitions: &HashMap<String, MathDefinition>,
+    bindings: &HashMap<&str, CompiledNode>,
+    inlining: &[&str],
+    slot_count: &Cell<usize>,
+    functions: &RecursiveFunctions,
+) -> Result<CompiledNode, MathError> {
+    let compile = |ast: &Ast| {
+        compile_node(
+            ast,
+            math_definitions,
+            bindings,
+            inlining,
+            slot_count,
+            functions,
+        )
+    };
+
+    match &ast.kind {
+        AstKind::Number(num) => Ok(CompiledNode::Number(num.value)),
+        AstKind::Identity(identity) => {
+            if let Some(node) = bindings.get(identity.as_str()) {
+                return Ok(node.clone());
+            }
+            match math_definitions.get(identity.as_str()) {
+                // a variable is substituted with the value it has when the expression is compiled
+                Some(MathDefinition::Constant(value) | MathDefinition::Variable(value)) => {
+                    Ok(CompiledNode::Number(*value))
+                }
+                Some(MathDefinition::ComplexConstant(_)) => {
+                    Err(numeric_value_error(identity, Complex::NAME, ast.span))
+                }
+                Some(MathDefinition::NumericVariable(value)) => value
+                    .to_f64()
+                    .map(CompiledNode::Number)
+                    .ok_or(numeric_value_error(identity, value.backend(), ast.span)),
+                Some(_) => Err(MathError::NotAValue {
+                    span: ast.span,
+                    identifier: identity.clone(),
+                }),
+                None => Err(MathError::UnknownIdentifier {
+                    span: ast.span,
+                    identifier: identity.clone(),
+                }),
+            }
+        }
+        AstKind::Grouping(inner) => compile(inner),
+        // a lambda is only a value when it is passed to a function
+        AstKind::Lambda { .. } => Err(MathError::NotAValue {
+            span: ast.span,
+            identifier: ast.to_string(),
+        }),
+        AstKind::UnaryOperation { operator, operand } => {
+            if !operator.is_prefix_operator() && !operator.is_postfix_operator() {
+                return Err(MathError::InvalidOperator {
+                    span: ast.span,
+                    operator: *operator,
+                });
+            }
+            match compile(operand)? {
+                CompiledNode::Number(num) => operator
+                    .apply_operation_1_operand(num)
+                    .map(CompiledNode::Number)
+                    .map_err(|error| error.with_span(ast.span)),
+                operand => Ok(CompiledNode::UnaryOperation(*operator, Box::new(operand))),
+            }
+        }
+        AstKind::BinaryOperation {
+            operator,
+            left,
+            right,
+        } => {
+            if !operator.is_binary_operator() {
+                return Err(MathError::InvalidOperator {
+                    span: ast.span,
+                    operator: *operator,
+                });
+            }
+            let left = compile(left)?;
+            if let CompiledNode::Number(left) = left {
+                if let Some(result) = operator
+                    .short_circuit(left)
+                    .map_err(|error| error.with_span(ast.span))?
+                {
+                    return Ok(CompiledNode::Number(result));
+                }
+            }
+            match (left, compile(right)?) {
+                (CompiledNode::Number(left), CompiledNode::Number(right)) => operator
+                    .apply_operation_2_operands(left, right)
+                    .map(CompiledNode::Number)
+                    .map_err(|error| error.with_span(ast.span)),
+                (left, right) => Ok(CompiledNode::BinaryOperation(
+                    *operator,
+                    Box::new(left),
+                    Box::new(right),
+                )),
+            }
+        }
+        AstKind::Call { identity, args } => {
+            let math_definition =
+                math_definitions
+                    .get(identity.as_str())
+                    .ok_or(MathError::UnknownIdentifier {
+                        span: ast.span,
+                        identifier: identity.clone(),
+                    })?;
+
+            let is_variable = |identity: &str| bindings.contains_key(identity);
+            let compile_args = || {
+                spread_args_in_scope(args, math_definitions, &is_variable)?
+                    .iter()
+                    .map(compile)
+                    .collect::<Result<Vec<CompiledNode>, MathError>>()
+            };
+
+            match math_definition {
+                MathDefinition::BuiltInFunction(function) => {
+                    let args = compile_args()?;
+                    if args.is_empty() {
+                        return Err(MathError::MissingArguments { span: ast.span });
+                    }
+                    if let Some(arg_count) = function
+                        .arg_count
+                        .filter(|arg_count| *arg_count != args.len())
+                    {
+                        return Err(MathError::ArityMismatch {
+                            span: ast.span,
+                            expected: arg_count,
+                            received: args.len(),
+                        });
+                    }
+                    Ok(CompiledNode::BuiltInCall(function.function, args))
+                }
+                MathDefinition::DefinedFunction(function) => {
+                    let args = compile_args()?;
+                    if args.len() != function.arg_names.len() {
+                        return Err(MathError::ArityMismatch {
+                            span: ast.span,
+                            expected: function.arg_names.len(),
+                            received: args.len(),
+                        });
+                    }
+
+                    // a function invoked inside its own body would be inlined forever, so it is compiled once and
+                    // invoked when the expression is evaluated
+                    if inlining.contains(&identity.as_str()) {
+                        let function = compile_recursive_function(
+                            identity,
+                            function,
+                            math_definitions,
+                            functions,
+                        )
+                        .map_err(|error| error.at(ast.span))?;
+                        return Ok(CompiledNode::Call(function, args));
+                    }
+
+                    // inline the function body with its argument names bound to the compiled arguments. an argument
+                    // that is not a number or a slot is stored in a slot of its own, so nested invocations like
+                    // "f(f(x))" do not copy it everywhere the body uses it.
+                    let mut lets = vec![];
+                    let function_bindings = function
+                        .arg_names
+                        .iter()
+                        .map(|arg_name| arg_name.as_str())
+                        .zip(args)
+                        .map(|(arg_name, arg)| match arg {
+                            CompiledNode::Number(_) | CompiledNode::Slot(_) => (arg_name, arg),
+                            arg => {
+                                let slot = slot_count.replace(slot_count.get() + 1);
+                                lets.push((slot, arg));
+                                (arg_name, CompiledNode::Slot(slot))
+                            }
+                        })
+                        .collect::<HashMap<&str, CompiledNode>>();
+
+                    let inlining = [inlining, &[identity.as_str()]].concat();
+                    let body = nested(|| {
+                        compile_node(
+                            &function.expression,
+                            math_definitions,
+                            &function_bindings,
+                            &inlining,
+                            slot_count,
+                            functions,
+                        )
+                    })
+                    .map_err(|error| error.at(ast.span))?;
+                    Ok(lets.into_iter().rev().fold(body, |body, (slot, arg)| {
+                        CompiledNode::Let(slot, Box::new(arg), Box::new(body))
+                    }))
+                }
+                MathDefinition::Constant(_)
+                | MathDefinition::Variable(_)
+                | MathDefinition::ComplexConstant(_)
+                | MathDefinition::NumericVariable(_) => Err(MathError::NotAFunction {
+                    span: ast.span,
+                    identifier: identity.clone(),
+                }),
+                // an if with a constant condition is compiled as the branch it chooses
+                MathDefinition::SpecialForm(SpecialForm::If) => {
+                    let (condition, then, otherwise) = if_args(args, ast.span)?;
+                    match compile(condition)? {
+                        CompiledNode::Number(condition_value) => {
+                            let holds = as_boolean(condition_value)
+                                .map_err(|error| error.with_span(condition.span))?;
+                            match (holds, otherwise) {
+                                (true, _) => compile(then),
+                                (false, Some(otherwise)) => compile(otherwise),
+                                (false, None) => Err(no_case_applies(ast.span)),
+                            }
+                        }
+                        condition => Ok(CompiledNode::Conditional(
+                            Box::new(condition),
+                            Box::new(compile(then)?),
+                            otherwise.map(compile).transpose()?.map(Box::new),
+                        )),
+                    }
+                }
+                // a sum, product, integral or root that uses the variables in scope can only be evaluated once they
+                // have values, so it is evaluated whenever the expression is
+                MathDefinition::SpecialForm(
+                    special_form @ (SpecialForm::Sum
+                    | SpecialForm::Prod
+                    | SpecialForm::Integrate
+                    | SpecialForm::Root),
+                ) if args
+                    .iter()
+                    .flat_map(Ast::identity_nodes)
+                    .any(|(identity, _)| bindings.contains_key(identity.as_str())) =>
+                {
+                    let (function, form_args) = match (special_form, args.as_slice()) {
+                        (SpecialForm::Sum | SpecialForm::Prod, [function, from, to]) => {
+                            (function, vec![compile(from)?, compile(to)?])
+                        }
+                        (SpecialForm::Integrate, [function, a, b]) => (
+                            function,
+                            vec![
+                                compile(a)?,
+                                compile(b)?,
+                                CompiledNode::Number(DEFAULT_INTEGRATION_TOLERANCE),
+                            ],
+                        ),
+                        (SpecialForm::Integrate, [function, a, b, tolerance]) => (
+                            function,
+                            vec![compile(a)?, compile(b)?, compile(tolerance)?],
+                        ),
+                        (SpecialForm::Root, [function, a, b]) => {
+                            (function, vec![compile(a)?, compile(b)?])
+                        }
+                        (SpecialForm::Root, [function, x0]) => (function, vec![compile(x0)?]),
+                        _ => {
+                            return Err(MathError::ArityMismatch {
+                                span: ast.span,
+                                expected: 3,
+                                received: args.len(),
+                            })
+                        }
+                    };
+                    // newton's method needs the derivative of the function
+                    let is_newton = *special_form == SpecialForm::Root && form_args.len() == 1;
+                    let function = compile_function_value(
+                        function,
+                        is_newton,
+                        math_definitions,
+                        bindings,
+                        inlining,
+                        slot_count,
+                        functions,
+                    )?;
+                    Ok(CompiledNode::SpecialForm(
+                        *special_form,
+                        Box::new(function),
+                        form_args,
+                    ))
+                }
+                // any other special form is compiled as the syntax tree it expands into
+                MathDefinition::SpecialForm(special_form) => {
+                    compile(&special_form.expand_in_scope(
+                        args,
+                        ast.span,
+                        math_definitions,
+                        |identity| bindings.contains_key(identity),
+                    )?)
+                }
+            }
+        }
+    }
+}
+
+/// this will compile a function of one argument passed to a special form, with its argument in a slot of its own. a
+/// lambda can use the names in scope, a function passed by name is invoked with the argument.
+fn compile_function_value(
+    ast: &Ast,
+    with_derivative: bool,
+    math_definitions: &HashMap<String, MathDefinition>,
+    bindings: &HashMap<&str, CompiledNode>,
+    inlining: &[&str],
+    slot_count: &Cell<usize>,
+    functions: &RecursiveFunctions,
+) -> Result<CompiledFunctionValue, MathError> {
+    let is_variable = |identity: &str| bindings.contains_key(identity);
+    let function = FunctionValue::from_ast_in_scope(ast, math_definitions, &is_variable, 1)?;
+    let (arg_name, body, mut function_bindings) = match &function {
+        FunctionValue::Defined(DefinedFunction {
+            signature: None,
+            arg_names,
+            expression,
+        }) => (
+            arg_names[0].as_str(),
+            Cow::Borrowed(expression),
+            bindings.clone(),
+        ),
+        function => (
+            "x",
+            Cow::Owned(function.apply_ast(vec![Ast::identity("x")], ast.span)),
+            HashMap::new(),
+        ),
+    };
+
+    let slot = slot_count.replace(slot_count.get() + 1);
+    function_bindings.insert(arg_name, CompiledNode::Slot(slot));
+    let compile = |ast: &Ast| {
+        compile_node(
+            ast,
+            math_definitions,
+            &function_bindings,
+            inlining,
+            slot_count,
+            functions,
+        )
+    };
+    let derivative = if with_derivative {
+        Some(compile(&derivative_ast(
+            &body,
+            arg_name,
+            math_definitions,
+        )?)?)
+    } else {
+        None
+    };
+    Ok(CompiledFunctionValue {
+        slot,
+        body: compile(&body)?,
+        derivative,
+    })
+}
+
+/// this will compile a recursive function with its arguments in its first slots, returning its index in the compiled
+/// functions. a function is only compiled the first time.
+fn compile_recursive_function(
+    identity: &str,
+    function: &DefinedFunction,
+    math_definitions: &HashMap<String, MathDefinition>,
+    functions: &RecursiveFunctions,
+) -> Result<usize, MathError> {
+    if let Some(index) = functions
+        .borrow()
+        .iter()
+        .position(|(name, _)| name == identity)
+    {
+        return Ok(index);
+    }
+    let index = functions.borrow().len();
+    functions.borrow_mut().push((identity.to_string(), None));
+
+    let bindings = function
+        .arg_names
+        .iter()
+        .enumerate()
+        .map(|(slot, arg_name)| (arg_name.as_str(), CompiledNode::Slot(slot)))
+        .collect::<HashMap<&str, CompiledNode>>();
+    let slot_count = Cell::new(function.arg_names.len());
+    let body = compile_node(
+        &function.expression,
+        math_definitions,
+        &bindings,
+        &[identity],
+        &slot_count,
+        functions,
+    )?;
+
+    functions.borrow_mut()[index].1 = Some(CompiledFunction {
+        body,
+        slot_count: slot_count.get(),
+    });
+    Ok(index)
+}
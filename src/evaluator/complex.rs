@@ -0,0 +1,374 @@
+use crate::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// the name of the imaginary unit, which is only a value when evaluating complex numbers.
+pub const IMAGINARY_UNIT: &str = "i";
+
+/// a complex number, the value of an expression evaluated in complex mode where "sqrt(-1)" is i rather than NaN.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+impl Complex {
+    pub const I: Self = Self { re: 0f64, im: 1f64 };
+
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn real(re: f64) -> Self {
+        Self { re, im: 0f64 }
+    }
+
+    pub fn from_polar(modulus: f64, arg: f64) -> Self {
+        Self::new(modulus * arg.cos(), modulus * arg.sin())
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im == 0f64
+    }
+
+    /// this will get the number as a real number, erroring if it has an imaginary part.
+    pub fn as_real(&self) -> Result<f64, MathError> {
+        if self.is_real() {
+            Ok(self.re)
+        } else {
+            Err(MathError::Domain {
+                span: None,
+                message: format!("expected a real number but found {self}"),
+            })
+        }
+    }
+
+    /// the modulus, the distance of the number from zero.
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// the argument, the angle of the number from the positive real axis between -pi and pi. a negative real
+    /// number has an argument of pi, whatever the sign of its zero imaginary part.
+    pub fn arg(&self) -> f64 {
+        if self.is_real() {
+            return 0f64.atan2(self.re);
+        }
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(&self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn exp(&self) -> Self {
+        if self.is_real() {
+            return Self::real(self.re.exp());
+        }
+        Self::from_polar(self.re.exp(), self.im)
+    }
+
+    /// the principal natural logarithm, its imaginary part is the argument so "ln(-1)" is pi i.
+    pub fn ln(&self) -> Self {
+        Self::new(self.abs().ln(), self.arg())
+    }
+
+    /// the principal square root, the root with a positive real part.
+    pub fn sqrt(&self) -> Self {
+        match (self.re, self.im) {
+            (re, 0f64) if re >= 0f64 => Self::real(re.sqrt()),
+            (re, 0f64) => Self::new(0f64, (-re).sqrt()),
+            (re, im) => {
+                let modulus = self.abs();
+                Self::new(
+                    ((modulus + re) / 2f64).sqrt(),
+                    ((modulus - re) / 2f64).sqrt().copysign(im),
+                )
+            }
+        }
+    }
+
+    /// the principal power. an integer exponent multiplies the number by itself so "i^2" is exactly -1.
+    pub fn pow(&self, exponent: Self) -> Self {
+        if self.is_real() && exponent.is_real() {
+            let value = self.re.powf(exponent.re);
+            if !value.is_nan() {
+                return Self::real(value);
+            }
+        }
+
+        match (exponent.is_real(), exponent.re) {
+            (true, n) if n.fract() == 0f64 && n.abs() <= i32::MAX as f64 => self.powi(n as i32),
+            _ if *self == Self::default() => Self::real(0f64.powf(exponent.re)),
+            _ => (exponent * self.ln()).exp(),
+        }
+    }
+
+    fn powi(&self, n: i32) -> Self {
+        let mut result = Self::real(1f64);
+        let mut base = *self;
+        let mut remaining = n.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            remaining >>= 1;
+        }
+
+        if n < 0 {
+            Self::real(1f64) / result
+        } else {
+            result
+        }
+    }
+
+    pub fn sin(&self) -> Self {
+        Self::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+
+    pub fn cos(&self) -> Self {
+        Self::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+
+    pub fn tan(&self) -> Self {
+        self.sin() / self.cos()
+    }
+
+    pub fn sinh(&self) -> Self {
+        Self::new(
+            self.re.sinh() * self.im.cos(),
+            self.re.cosh() * self.im.sin(),
+        )
+    }
+
+    pub fn cosh(&self) -> Self {
+        Self::new(
+            self.re.cosh() * self.im.cos(),
+            self.re.sinh() * self.im.sin(),
+        )
+    }
+
+    pub fn tanh(&self) -> Self {
+        self.sinh() / self.cosh()
+    }
+
+    pub fn recip(&self) -> Self {
+        Self::real(1f64) / *self
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        // dividing by a real number is exactly the real division of each part
+        if other.is_real() {
+            return Self::new(self.re / other.re, self.im / other.re);
+        }
+        let denominator = other.re * other.re + other.im * other.im;
+        Self::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+/// this will print the number like "3", "2i" or "1 - 0.5i", leaving out a part that is zero. a very small or large
+/// part is printed in scientific notation, so "e^(i*pi)" is "-1 + 1.2246467991473532e-16i".
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let part = |num: f64| {
+            let part = format!("{num:?}");
+            match part.strip_suffix(".0") {
+                Some(integer) => integer.to_string(),
+                None => part,
+            }
+        };
+        let imaginary = |f: &mut fmt::Formatter<'_>, im: f64| match im {
+            1f64 => write!(f, "{IMAGINARY_UNIT}"),
+            _ => write!(f, "{}{IMAGINARY_UNIT}", part(im)),
+        };
+
+        match (self.re, self.im) {
+            (re, 0f64) => write!(f, "{}", part(re)),
+            (0f64, -1f64) => write!(f, "-{IMAGINARY_UNIT}"),
+            (0f64, im) => imaginary(f, im),
+            (re, im) if im < 0f64 => {
+                write!(f, "{} - ", part(re))?;
+                imaginary(f, -im)
+            }
+            (re, im) => {
+                write!(f, "{} + ", part(re))?;
+                imaginary(f, im)
+            }
+        }
+    }
+}
+
+/// complex numbers, ordering, modulus and logic are only defined for the real numbers so they error on a number with
+/// an imaginary part. every built in function is supported, one without a complex version only for real arguments.
+impl Numeric for Complex {
+    const NAME: &'static str = "complex";
+
+    fn from_f64(num: f64) -> Result<Self, MathError> {
+        Ok(Self::real(num))
+    }
+
+    fn from_complex(num: Complex) -> Option<Self> {
+        Some(num)
+    }
+
+    fn from_numeric_value(value: &NumericValue) -> Option<Self> {
+        match value {
+            NumericValue::Complex(value) => Some(*value),
+            _ => value.to_f64().map(Complex::real),
+        }
+    }
+
+    fn to_f64(&self) -> Result<f64, MathError> {
+        self.as_real()
+    }
+
+    fn to_variable(&self) -> MathDefinition {
+        MathDefinition::complex_variable(*self)
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(*self + *other)
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(*self - *other)
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(*self * *other)
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(*self / *other)
+    }
+
+    fn try_rem(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(Self::real(self.as_real()? % other.as_real()?))
+    }
+
+    fn try_pow(&self, exponent: &Self) -> Result<Self, MathError> {
+        Ok(self.pow(*exponent))
+    }
+
+    fn try_neg(&self) -> Result<Self, MathError> {
+        Ok(-*self)
+    }
+
+    fn partial_compare(&self, other: &Self) -> Result<Option<Ordering>, MathError> {
+        Ok(self.as_real()?.partial_cmp(&other.as_real()?))
+    }
+
+    fn equals(&self, other: &Self) -> Result<bool, MathError> {
+        Ok(self == other)
+    }
+
+    fn supports_built_in(identity: &str) -> bool {
+        identity != INTERVAL
+    }
+
+    fn evaluate_built_in(
+        identity: &str,
+        function: &BuiltInFunction,
+        args: &[Self],
+    ) -> Result<Self, MathError> {
+        let values = args.iter().map(|arg| arg.re).collect::<Vec<f64>>();
+        // a real result is given exactly as the real version would give it
+        let real_value = match args.iter().all(Complex::is_real) {
+            true => Some((function.function)(&values)?),
+            false => None,
+        };
+        if let Some(value) = real_value.filter(|value| !value.is_nan()) {
+            return Ok(Complex::real(value));
+        }
+
+        let z = args[0];
+        let complex_value = match identity {
+            "sqrt" => z.sqrt(),
+            "pow" => z.pow(args[1]),
+            "abs" => Complex::real(z.abs()),
+            "sin" | "sine" => z.sin(),
+            "cos" | "cosine" => z.cos(),
+            "tan" | "tangent" => z.tan(),
+            "sec" | "secant" => z.cos().recip(),
+            "csc" | "cosecant" => z.sin().recip(),
+            "cot" | "cotangent" => z.tan().recip(),
+            "sinh" => z.sinh(),
+            "cosh" => z.cosh(),
+            "tanh" => z.tanh(),
+            "log" | "log10" => z.ln() / Complex::real(std::f64::consts::LN_10),
+            "log2" => z.ln() / Complex::real(std::f64::consts::LN_2),
+            "ln" => z.ln(),
+            "re" => Complex::real(z.re),
+            "im" => Complex::real(z.im),
+            "conj" => z.conj(),
+            "arg" => Complex::real(z.arg()),
+            "mean" => {
+                args.iter().fold(Complex::default(), |sum, arg| sum + *arg)
+                    / Complex::real(args.len() as f64)
+            }
+            _ => real_value.map(Complex::real).ok_or(MathError::Domain {
+                span: None,
+                message: format!("{identity} is only defined for real numbers"),
+            })?,
+        };
+        Ok(complex_value)
+    }
+}
+
+/// this will evaluate a string math expression or program over complex numbers, so "sqrt(-4)" is 2i.
+pub fn eval_str_complex(string: &str) -> Result<Complex, MathError> {
+    eval_str_numeric(string)
+}
+
+/// this will evaluate a syntax tree over complex numbers.
+pub fn eval_ast_complex(
+    ast: &Ast,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Complex, MathError> {
+    eval_ast_numeric(ast, math_definitions)
+}
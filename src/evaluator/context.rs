@@ -0,0 +1,301 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// the name the result of the last successful evaluation is stored under.
+pub const ANS: &str = "ans";
+
+/// an evaluation session that owns its math definitions, so functions and variables defined in it are kept
+/// across every evaluation. it starts out with the default math definitions, which can not be redefined or removed.
+#[derive(Clone, Debug)]
+pub struct Context {
+    math_definitions: HashMap<String, MathDefinition>,
+    max_recursion_depth: usize,
+    decimal_context: DecimalContext,
+    rational_fallback: RationalFallback,
+}
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Context {
+    pub fn new() -> Self {
+        Self {
+            math_definitions: MathDefinition::default_math_definitions(),
+            max_recursion_depth: DEFAULT_MAX_RECURSION_DEPTH,
+            decimal_context: DecimalContext::default(),
+            rational_fallback: RationalFallback::default(),
+        }
+    }
+
+    /// this will set how deeply functions can invoke each other or themselves before evaluation errors, at most
+    /// `MAX_RECURSION_DEPTH_LIMIT`.
+    pub fn with_max_recursion_depth(self, max_recursion_depth: usize) -> Self {
+        Self {
+            max_recursion_depth: max_recursion_depth.min(MAX_RECURSION_DEPTH_LIMIT),
+            ..self
+        }
+    }
+
+    pub fn max_recursion_depth(&self) -> usize {
+        self.max_recursion_depth
+    }
+
+    /// this will set the precision and rounding of the decimal results of this context.
+    pub fn with_decimal_context(self, decimal_context: DecimalContext) -> Self {
+        Self {
+            decimal_context,
+            ..self
+        }
+    }
+
+    pub fn decimal_context(&self) -> DecimalContext {
+        self.decimal_context
+    }
+
+    /// this will set what the rational results of this context do when a value can not be exact.
+    pub fn with_rational_fallback(self, rational_fallback: RationalFallback) -> Self {
+        Self {
+            rational_fallback,
+            ..self
+        }
+    }
+
+    pub fn rational_fallback(&self) -> RationalFallback {
+        self.rational_fallback
+    }
+
+    pub fn math_definitions(&self) -> &HashMap<String, MathDefinition> {
+        &self.math_definitions
+    }
+
+    /// the value of the last statement that had one, which expressions can also refer to as "ans".
+    pub fn ans(&self) -> Option<f64> {
+        self.math_definitions
+            .get(ANS)
+            .and_then(MathDefinition::get_value)
+    }
+
+    /// the value of the last statement that had one like `ans`, which may be complex.
+    pub fn complex_ans(&self) -> Option<Complex> {
+        self.math_definitions
+            .get(ANS)
+            .and_then(MathDefinition::get_complex_value)
+    }
+
+    /// this will run every statement of a program like "r = 5; pi * r^2", returning the value of the last one.
+    /// errors if the program has no value, like a program that only defines functions.
+    pub fn eval(&mut self, input: &str) -> Result<f64, MathError> {
+        self.execute(input)?
+            .ok_or(MathError::MissingValue { span: None })
+    }
+
+    /// this will run every statement of a program, returning the value of the last statement if it has one.
+    /// the statements run in order and stop at the first error, keeping whatever the statements before it defined.
+    /// a function may invoke functions defined after it in the same program, so functions can invoke each other.
+    pub fn execute(&mut self, input: &str) -> Result<Option<f64>, MathError> {
+        self.execute_numeric(input)
+    }
+
+    /// this will run every statement of a program like `eval` over complex numbers, so "sqrt(-4)" is 2i. a variable
+    /// assigned a number with an imaginary part only has a value when evaluating complex numbers.
+    pub fn eval_complex(&mut self, input: &str) -> Result<Complex, MathError> {
+        self.execute_complex(input)?
+            .ok_or(MathError::MissingValue { span: None })
+    }
+
+    /// this will run every statement of a program like `execute` over complex numbers.
+    pub fn execute_complex(&mut self, input: &str) -> Result<Option<Complex>, MathError> {
+        self.execute_numeric(input)
+    }
+
+    /// this will run every statement of a program like `eval` over a numeric backend, like
+    /// `context.eval_numeric::<f32>("1 / 3")`.
+    pub fn eval_numeric<N: Numeric>(&mut self, input: &str) -> Result<N, MathError> {
+        self.execute_numeric(input)?
+            .ok_or(MathError::MissingValue { span: None })
+    }
+
+    /// this will run every statement of a program like `execute` over a numeric backend. the variables it assigns
+    /// are kept as the backend stores them.
+    pub fn execute_numeric<N: Numeric>(&mut self, input: &str) -> Result<Option<N>, MathError> {
+        let statements = parse_statements_str(input)?;
+        let function_names = statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::FunctionDefinition {
+                    defined_function, ..
+                } => defined_function.signature.as_deref(),
+                _ => None,
+            })
+            .collect::<Vec<&str>>();
+
+        let mut result: Option<N> = None;
+        for statement in &statements {
+            result = self
+                .with_settings(|context| context.execute_statement(statement, &function_names))?;
+            if let Some(value) = &result {
+                self.math_definitions
+                    .insert(ANS.to_string(), value.to_variable());
+            }
+        }
+        Ok(result)
+    }
+
+    /// this will run f with the settings of the context, which only apply while it runs.
+    fn with_settings<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let (max_recursion_depth, decimal_context, rational_fallback) = (
+            self.max_recursion_depth,
+            self.decimal_context,
+            self.rational_fallback,
+        );
+        with_max_recursion_depth(max_recursion_depth, || {
+            with_decimal_context(decimal_context, || {
+                with_rational_fallback(rational_fallback, || f(self))
+            })
+        })
+    }
+
+    fn execute_statement<N: Numeric>(
+        &mut self,
+        statement: &Statement,
+        function_names: &[&str],
+    ) -> Result<Option<N>, MathError> {
+        match statement {
+            Statement::Expression(ast) => eval_ast_numeric(ast, &self.math_definitions).map(Some),
+            // assigning a lambda or a function name defines a function, which has no value
+            Statement::Assignment {
+                identity,
+                identity_span,
+                value,
+            } if is_function_value(value, &self.math_definitions) => {
+                let defined_function =
+                    FunctionValue::resolve(value, &self.math_definitions, &HashMap::new())?
+                        .into_defined_function(identity)
+                        .map_err(|error| error.with_span(value.span))?;
+                self.add_function_with(defined_function, function_names)
+                    .map_err(|error| error.with_span(*identity_span))?;
+                Ok(None)
+            }
+            Statement::Assignment {
+                identity,
+                identity_span,
+                value,
+            } => {
+                let value = eval_ast_numeric::<N>(value, &self.math_definitions)?;
+                self.set_definition(identity, value.to_variable())
+                    .map_err(|error| error.with_span(*identity_span))?;
+                Ok(Some(value))
+            }
+            Statement::FunctionDefinition {
+                defined_function,
+                signature_span,
+            } => {
+                self.add_function_with(defined_function.clone(), function_names)
+                    .map_err(|error| error.with_span(*signature_span))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// this will parse a function definition like "f(x): x^2" and define it, returning its name.
+    pub fn define_function(&mut self, definition: &str) -> Result<String, MathError> {
+        let tokens = parse_spanned_str(definition)?;
+        let name_span = tokens.first().and_then(|spanned_token| spanned_token.span);
+        let defined_function = DefinedFunction::parse_spanned_unvalidated(tokens)?;
+        self.add_function(defined_function)
+            .map_err(|error| error.with_span(name_span))
+    }
+
+    /// this will define an already parsed function under its signature, returning its name. its body may use any
+    /// function or variable in the context, and may invoke the function itself.
+    pub fn add_function(&mut self, defined_function: DefinedFunction) -> Result<String, MathError> {
+        self.add_function_with(defined_function, &[])
+    }
+
+    /// this will define a function that may also invoke the given functions, which are defined later.
+    fn add_function_with(
+        &mut self,
+        defined_function: DefinedFunction,
+        function_names: &[&str],
+    ) -> Result<String, MathError> {
+        let name = defined_function
+            .signature
+            .clone()
+            .ok_or(MathError::InvalidDefinition {
+                span: None,
+                message: "an anonymous function can not be defined without a name".to_string(),
+            })?;
+        self.check_redefinable(&name)?;
+        defined_function.validate_with_functions(&self.math_definitions, function_names)?;
+        self.math_definitions.insert(
+            name.clone(),
+            MathDefinition::DefinedFunction(defined_function),
+        );
+        Ok(name)
+    }
+
+    /// this will set a variable, replacing any function or variable with the same name.
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<(), MathError> {
+        self.set_definition(name, MathDefinition::Variable(value))
+    }
+
+    /// this will set a variable to a complex number, which only has a value when evaluating complex numbers unless
+    /// it has no imaginary part.
+    pub fn set_complex_variable(&mut self, name: &str, value: Complex) -> Result<(), MathError> {
+        self.set_definition(name, MathDefinition::complex_variable(value))
+    }
+
+    fn set_definition(
+        &mut self,
+        name: &str,
+        math_definition: MathDefinition,
+    ) -> Result<(), MathError> {
+        self.check_redefinable(name)?;
+        self.math_definitions
+            .insert(name.to_string(), math_definition);
+        Ok(())
+    }
+
+    /// this will remove a function or variable, returning its definition. built in definitions are never removed.
+    pub fn remove(&mut self, name: &str) -> Option<MathDefinition> {
+        if self.math_definitions.get(name)?.is_built_in() {
+            return None;
+        }
+        self.math_definitions.remove(name)
+    }
+
+    /// a name can be defined if it is a valid identity that is not one of the built in definitions.
+    fn check_redefinable(&self, name: &str) -> Result<(), MathError> {
+        let is_identity =
+            name.starts_with(is_identity_start) && name.chars().all(is_identity_continue);
+        if !is_identity {
+            return Err(MathError::InvalidDefinition {
+                span: None,
+                message: format!("{name:?} is not a valid name"),
+            });
+        }
+
+        match self.math_definitions.get(name) {
+            Some(math_definition) if math_definition.is_built_in() => {
+                Err(MathError::InvalidDefinition {
+                    span: None,
+                    message: format!("{name:?} is built in and can not be redefined"),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// a lambda or the name of a function is a function value rather than a number.
+fn is_function_value(ast: &Ast, math_definitions: &HashMap<String, MathDefinition>) -> bool {
+    match &ast.kind {
+        AstKind::Grouping(inner) => is_function_value(inner, math_definitions),
+        AstKind::Lambda { .. } => true,
+        AstKind::Identity(identity) => math_definitions
+            .get(identity.as_str())
+            .is_some_and(MathDefinition::is_function),
+        _ => false,
+    }
+}
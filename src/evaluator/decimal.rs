@@ -0,0 +1,459 @@
+use crate::*;
+use bigdecimal::{BigDecimal, RoundingMode};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroU64;
+use std::str::FromStr;
+
+/// the significant digits decimal results are rounded to, unless another precision is set.
+pub const DEFAULT_DECIMAL_PRECISION: u64 = 28;
+
+/// the extra significant digits the logarithm and exponential of a power with a fractional exponent are computed to.
+const POWER_GUARD_DIGITS: u64 = 10;
+
+/// the most digits before the decimal point the logarithm of a power can have, past which the power has an exponent
+/// too large for a decimal number.
+const MAX_POWER_LOGARITHM_DIGITS: u64 = 15;
+
+/// the largest numerator and denominator of a fractional exponent whose power is checked for being exact.
+const MAX_EXACT_POWER_FRACTION: u32 = 64;
+
+/// how a decimal result with more significant digits than the precision is rounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DecimalRounding {
+    /// to the nearest, a tie to the even neighbour, so 2.5 is 2 and 3.5 is 4.
+    #[default]
+    HalfEven,
+    /// to the nearest, a tie away from zero, so 2.5 is 3 and -2.5 is -3.
+    HalfUp,
+    /// towards negative infinity.
+    Floor,
+    /// towards positive infinity.
+    Ceiling,
+}
+impl DecimalRounding {
+    pub const NAMES: [(&'static str, DecimalRounding); 4] = [
+        ("half-even", Self::HalfEven),
+        ("half-up", Self::HalfUp),
+        ("floor", Self::Floor),
+        ("ceiling", Self::Ceiling),
+    ];
+
+    /// this will get a rounding mode from its name like "half-up".
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(rounding_name, _)| *rounding_name == name)
+            .map(|(_, rounding)| *rounding)
+    }
+
+    fn rounding_mode(&self) -> RoundingMode {
+        match self {
+            Self::HalfEven => RoundingMode::HalfEven,
+            Self::HalfUp => RoundingMode::HalfUp,
+            Self::Floor => RoundingMode::Floor,
+            Self::Ceiling => RoundingMode::Ceiling,
+        }
+    }
+}
+impl fmt::Display for DecimalRounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = Self::NAMES
+            .iter()
+            .find(|(_, rounding)| rounding == self)
+            .map_or("half-even", |(name, _)| *name);
+        write!(f, "{name}")
+    }
+}
+
+/// the precision and rounding of decimal results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecimalContext {
+    pub precision: NonZeroU64,
+    pub rounding: DecimalRounding,
+}
+impl Default for DecimalContext {
+    fn default() -> Self {
+        Self {
+            precision: NonZeroU64::new(DEFAULT_DECIMAL_PRECISION).unwrap(),
+            rounding: DecimalRounding::default(),
+        }
+    }
+}
+impl DecimalContext {
+    /// a context rounding to a number of significant digits, none if the precision is 0.
+    pub fn new(precision: u64, rounding: DecimalRounding) -> Option<Self> {
+        Some(Self {
+            precision: NonZeroU64::new(precision)?,
+            rounding,
+        })
+    }
+
+    fn context(&self) -> bigdecimal::Context {
+        bigdecimal::Context::new(self.precision, self.rounding.rounding_mode())
+    }
+
+    /// this will round a value to the precision, without trailing zeros.
+    fn round(&self, value: BigDecimal) -> Decimal {
+        Decimal(self.context().round_decimal(value).normalized())
+    }
+}
+
+thread_local! {
+    static DECIMAL_CONTEXT: Cell<DecimalContext> = Cell::new(DecimalContext::default());
+}
+
+/// the precision and rounding of decimal results on this thread.
+pub fn decimal_context() -> DecimalContext {
+    DECIMAL_CONTEXT.get()
+}
+
+/// this will set the precision and rounding of decimal results on this thread, a `Context` evaluates with its own.
+pub fn set_decimal_context(context: DecimalContext) {
+    DECIMAL_CONTEXT.set(context);
+}
+
+/// this will run f with a different decimal context, restoring the previous context afterwards.
+pub fn with_decimal_context<T>(context: DecimalContext, f: impl FnOnce() -> T) -> T {
+    struct Restore(DecimalContext);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            DECIMAL_CONTEXT.set(self.0);
+        }
+    }
+
+    let _restore = Restore(DECIMAL_CONTEXT.replace(context));
+    f()
+}
+
+/// a decimal number of any precision, so "0.1 + 0.2" is exactly 0.3. every result is rounded to the significant
+/// digits of the decimal context using its rounding mode.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decimal(pub BigDecimal);
+impl Decimal {
+    pub fn parse(string: &str) -> Result<Self, MathError> {
+        BigDecimal::from_str(string)
+            .map(Self)
+            .map_err(|error| MathError::Domain {
+                span: None,
+                message: format!("{string:?} is not a decimal number: {error}"),
+            })
+    }
+}
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+fn division_by_zero() -> MathError {
+    MathError::Domain {
+        span: None,
+        message: "division by zero".to_string(),
+    }
+}
+
+/// the quotient rounded once to the precision. the quotient is computed to more digits than the precision, and an
+/// inexact quotient gets one more nonzero digit, so it rounds the same way the exact quotient would.
+fn divide(dividend: &BigDecimal, divisor: &BigDecimal) -> Result<Decimal, MathError> {
+    if divisor.is_zero() {
+        return Err(division_by_zero());
+    }
+    let context = decimal_context();
+    let (dividend_digits, dividend_scale) = dividend.as_bigint_and_exponent();
+    let (divisor_digits, divisor_scale) = divisor.as_bigint_and_exponent();
+
+    let extra_digits =
+        (context.precision.get() + 2 + divisor.digits()).saturating_sub(dividend.digits());
+    let shifted = dividend_digits * BigInt::from(10).pow(extra_digits as u32);
+    let quotient = &shifted / &divisor_digits;
+    let scale = dividend_scale - divisor_scale + extra_digits as i64;
+    let quotient = match (&shifted % &divisor_digits).is_zero() {
+        true => BigDecimal::new(quotient, scale),
+        false => {
+            let sticky = if shifted.is_negative() != divisor_digits.is_negative() {
+                -1
+            } else {
+                1
+            };
+            BigDecimal::new(quotient * 10 + sticky, scale + 1)
+        }
+    };
+    Ok(context.round(quotient))
+}
+
+/// a value only computed to the precision of a float, like "sin(1)", rounded to the precision.
+fn from_float(num: f64) -> Result<Decimal, MathError> {
+    Ok(decimal_context().round(Decimal::from_f64(num)?.0))
+}
+
+/// the digits before the decimal point of a value, 0 for a value below 1.
+fn integer_digits(value: &BigDecimal) -> u64 {
+    let (_, scale) = value.as_bigint_and_exponent();
+    (value.digits() as i64 - scale).max(0) as u64
+}
+
+/// the natural logarithm of a value between 0.1 and 10 to a number of significant digits. square roots bring the value
+/// near 1, where ln(x) = 2 atanh(z) = 2 (z + z^3 / 3 + z^5 / 5 + ...) with z = (x - 1) / (x + 1) converges quickly.
+fn ln_near_one(x: &BigDecimal, context: &bigdecimal::Context) -> BigDecimal {
+    let one = BigDecimal::one();
+    let near_one = BigDecimal::new(BigInt::one(), 2);
+    let mut x = x.clone();
+    let mut square_roots = 0u32;
+    while (&x - &one).abs() > near_one {
+        x = x.sqrt_with_context(context).unwrap_or_default();
+        square_roots += 1;
+    }
+
+    let z = context.multiply(&(&x - &one), &(&x + &one).inverse_with_context(context));
+    let z_squared = context.multiply(&z, &z);
+    let tolerance = BigDecimal::new(BigInt::one(), context.precision().get() as i64 + 2);
+    let mut power = z.clone();
+    let mut sum = z;
+    for k in (3u64..).step_by(2) {
+        power = context.multiply(&power, &z_squared);
+        let term = context.multiply(&power, &BigDecimal::from(k).inverse_with_context(context));
+        if term.abs() < tolerance {
+            break;
+        }
+        sum += term;
+    }
+    sum * BigDecimal::from(Pow::pow(BigInt::from(2), square_roots + 1))
+}
+
+/// the natural logarithm of a positive value to a number of significant digits after the decimal point. the value is
+/// split into a mantissa between 0.1 and 1 and a power of ten, so ln(x) = ln(mantissa) + exponent ln(10).
+fn ln(x: &BigDecimal, precision: u64) -> BigDecimal {
+    let (digits, scale) = x.as_bigint_and_exponent();
+    let exponent = BigDecimal::from(x.digits() as i64 - scale);
+    let mantissa = BigDecimal::new(digits, x.digits() as i64);
+    let context = bigdecimal::Context::default()
+        .with_prec(precision + integer_digits(&exponent))
+        .unwrap_or_default();
+    ln_near_one(&mantissa, &context) + ln_near_one(&BigDecimal::from(10), &context) * exponent
+}
+
+/// a positive value to a fractional power rounded to the precision, computed as e^(exponent ln(x)) with enough extra
+/// digits that the logarithm and the exponential do not change how it rounds.
+fn fractional_power(x: &BigDecimal, exponent: &BigDecimal) -> Result<Decimal, MathError> {
+    let context = decimal_context();
+    let precision = context.precision.get() + POWER_GUARD_DIGITS + integer_digits(exponent);
+    let logarithm = exponent * ln(x, precision);
+    if integer_digits(&logarithm) > MAX_POWER_LOGARITHM_DIGITS {
+        return Err(MathError::Domain {
+            span: None,
+            message: format!("{x}^{exponent} is too large or too small to be a decimal number"),
+        });
+    }
+    let exponential_context = bigdecimal::Context::default()
+        .with_prec(precision)
+        .unwrap_or_default();
+    let approximation = logarithm.exp_with_context(&exponential_context);
+    Ok(exact_fractional_power(x, exponent, &approximation)
+        .unwrap_or_else(|| context.round(approximation)))
+}
+
+/// a value to a natural power, with every digit of the result.
+fn exact_integer_power(value: &BigDecimal, exponent: u32) -> BigDecimal {
+    let (digits, scale) = value.as_bigint_and_exponent();
+    BigDecimal::new(Pow::pow(digits, exponent), scale * exponent as i64)
+}
+
+/// the power x^(numerator / denominator) if it is exact with at most the precision's digits, like 0.01^0.5 = 0.1. the
+/// approximation of an exact power could otherwise round the wrong way, like 0.0999... rounding down to 0.09.
+fn exact_fractional_power(
+    x: &BigDecimal,
+    exponent: &BigDecimal,
+    approximation: &BigDecimal,
+) -> Option<Decimal> {
+    let (digits, scale) = exponent.as_bigint_and_exponent();
+    let fraction = BigRational::new(digits, Pow::pow(BigInt::from(10), scale.max(0) as u64));
+    let numerator = fraction
+        .numer()
+        .to_i64()
+        .filter(|numerator| numerator.unsigned_abs() <= MAX_EXACT_POWER_FRACTION as u64)?;
+    let denominator = fraction
+        .denom()
+        .to_u32()
+        .filter(|denominator| *denominator <= MAX_EXACT_POWER_FRACTION)?;
+
+    let candidate = bigdecimal::Context::new(decimal_context().precision, RoundingMode::HalfEven)
+        .round_decimal(approximation.clone());
+    let candidate_power = exact_integer_power(&candidate, denominator);
+    let x_power = exact_integer_power(x, numerator.unsigned_abs() as u32);
+    let is_exact = match numerator < 0 {
+        true => (candidate_power * x_power).is_one(),
+        false => candidate_power == x_power,
+    };
+    is_exact.then(|| Decimal(candidate.normalized()))
+}
+
+/// the factorial of a natural number rounded to the precision, none for any other number.
+fn exact_factorial(value: &BigDecimal, step: usize) -> Option<Decimal> {
+    let n = Some(value)
+        .filter(|value| value.is_integer())
+        .and_then(|value| value.to_u32())?;
+    big_factorial(n, step).map(|product| decimal_context().round(BigDecimal::from(product)))
+}
+
+/// decimal numbers, the root and power built in functions are evaluated to the precision and any other built in
+/// function is as precise as a float before it is rounded.
+impl Numeric for Decimal {
+    const NAME: &'static str = "decimal";
+
+    /// a float is the shortest decimal that parses back to it, so 0.1 is exactly 0.1.
+    fn from_f64(num: f64) -> Result<Self, MathError> {
+        if !num.is_finite() {
+            return Err(MathError::Domain {
+                span: None,
+                message: format!("{num} is not a decimal number"),
+            });
+        }
+        Self::parse(&num.to_string())
+    }
+
+    /// a literal is the exact decimal it was written as, however many digits it has.
+    fn from_literal(literal: &str, _num: f64) -> Result<Self, MathError> {
+        Self::parse(literal)
+    }
+
+    fn from_numeric_value(value: &NumericValue) -> Option<Self> {
+        match value {
+            NumericValue::Decimal(value) => Some(value.clone()),
+            _ => value.to_f64().and_then(|num| Self::from_f64(num).ok()),
+        }
+    }
+
+    fn to_f64(&self) -> Result<f64, MathError> {
+        Ok(self.0.to_f64().unwrap_or(f64::NAN))
+    }
+
+    fn to_variable(&self) -> MathDefinition {
+        MathDefinition::NumericVariable(NumericValue::Decimal(self.clone()))
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(decimal_context().round(&self.0 + &other.0))
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(decimal_context().round(&self.0 - &other.0))
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(decimal_context().round(&self.0 * &other.0))
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, MathError> {
+        divide(&self.0, &other.0)
+    }
+
+    /// the remainder has the sign of the dividend, like the remainder of floats.
+    fn try_rem(&self, other: &Self) -> Result<Self, MathError> {
+        match other.0.is_zero() {
+            true => Err(division_by_zero()),
+            false => Ok(decimal_context().round(&self.0 % &other.0)),
+        }
+    }
+
+    fn try_pow(&self, exponent: &Self) -> Result<Self, MathError> {
+        let context = decimal_context();
+        match Some(&exponent.0)
+            .filter(|exponent| exponent.is_integer())
+            .and_then(|exponent| exponent.to_i64())
+        {
+            Some(exponent) if self.0.is_zero() && exponent < 0 => Err(division_by_zero()),
+            Some(exponent) => {
+                Ok(context.round(self.0.powi_with_context(exponent, &context.context())))
+            }
+            None if self.0.is_positive() => fractional_power(&self.0, &exponent.0),
+            None if self.0.is_zero() && exponent.0.is_positive() => Ok(Self(BigDecimal::zero())),
+            None if self.0.is_zero() => Err(division_by_zero()),
+            None => Err(MathError::Domain {
+                span: None,
+                message: format!("{self}^{exponent} is not a real number"),
+            }),
+        }
+    }
+
+    fn try_neg(&self) -> Result<Self, MathError> {
+        Ok(Self(-&self.0))
+    }
+
+    fn partial_compare(&self, other: &Self) -> Result<Option<Ordering>, MathError> {
+        Ok(Some(self.0.cmp(&other.0)))
+    }
+
+    fn factorial(&self) -> Result<Self, MathError> {
+        match exact_factorial(&self.0, 1) {
+            Some(factorial) => Ok(factorial),
+            None => from_float(factorial(self.to_f64()?)?),
+        }
+    }
+
+    fn double_factorial(&self) -> Result<Self, MathError> {
+        match exact_factorial(&self.0, 2) {
+            Some(double_factorial) => Ok(double_factorial),
+            None => from_float(double_factorial(self.to_f64()?)?),
+        }
+    }
+
+    fn supports_built_in(identity: &str) -> bool {
+        identity != INTERVAL
+    }
+
+    fn evaluate_built_in(
+        identity: &str,
+        function: &BuiltInFunction,
+        args: &[Self],
+    ) -> Result<Self, MathError> {
+        let context = decimal_context();
+        let x = &args[0].0;
+        match identity {
+            "pow" => args[0].try_pow(&args[1]),
+            "factorial" => args[0].factorial(),
+            "sqrt" => x
+                .sqrt_with_context(&context.context())
+                .map(|root| context.round(root))
+                .ok_or(MathError::Domain {
+                    span: None,
+                    message: format!("the square root of {x} is not a real number"),
+                }),
+            "cbrt" => Ok(context.round(x.cbrt_with_context(&context.context()))),
+            "abs" => Ok(Self(x.abs())),
+            "re" | "conj" => Ok(args[0].clone()),
+            "im" => Ok(Self(BigDecimal::zero())),
+            "mean" => {
+                let sum = args
+                    .iter()
+                    .fold(BigDecimal::zero(), |sum, arg| sum + &arg.0);
+                divide(&sum, &BigDecimal::from(args.len() as u64))
+            }
+            _ => {
+                let values = args
+                    .iter()
+                    .map(Numeric::to_f64)
+                    .collect::<Result<Vec<f64>, MathError>>()?;
+                from_float((function.function)(&values)?)
+            }
+        }
+    }
+}
+
+/// this will evaluate a string math expression or program over decimal numbers, so "0.1 + 0.2" is exactly 0.3.
+pub fn eval_str_decimal(string: &str) -> Result<Decimal, MathError> {
+    eval_str_numeric(string)
+}
+
+/// this will evaluate a syntax tree over decimal numbers.
+pub fn eval_ast_decimal(
+    ast: &Ast,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Decimal, MathError> {
+    eval_ast_numeric(ast, math_definitions)
+}
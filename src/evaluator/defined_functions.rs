@@ -1,69 +1,90 @@
-use itertools::Itertools;
-
 use crate::*;
 use std::collections::HashMap;
+use std::fmt;
+
+/// the keyword between the value of a case of a piecewise function and the condition the case applies under.
+pub const WHEN: &str = "when";
+
+/// the keyword after the value of the last case of a piecewise function, which applies when no other case does.
+pub const OTHERWISE: &str = "otherwise";
 
 #[derive(Clone, Debug)]
 pub struct DefinedFunction {
     pub signature: Option<String>,
     pub arg_names: Vec<String>,
-    pub expression: Expression,
+    pub expression: Ast,
 }
 impl DefinedFunction {
-    pub fn anonymous(arg_names: Vec<String>, expression: Expression) -> Result<Self, String> {
+    pub fn anonymous(arg_names: Vec<String>, expression: Ast) -> Result<Self, MathError> {
         Ok(Self {
             signature: None,
-            arg_names: arg_names,
-            expression: expression,
+            arg_names,
+            expression,
         })
     }
 
-    pub fn parse_str(str: &str) -> Result<Self, String> {
-        Self::parse(parse_str(str)?)
+    pub fn parse_str(str: &str) -> Result<Self, MathError> {
+        Self::parse_spanned(parse_spanned_str(str)?)
     }
 
-    pub fn parse(expression: Expression) -> Result<Self, String> {
-        let mut expression = expression;
+    pub fn parse(expression: Expression) -> Result<Self, MathError> {
+        Self::parse_spanned(expression.into_iter().map(SpannedToken::from).collect())
+    }
 
-        // parse out the signature of the function
-        let signature = if let Token::Identity(signature) = expression.remove(0) {
-            signature
-        } else {
-            return Err("missing signature".to_string());
+    /// this will parse a function definition, validating it against the default math definitions.
+    pub fn parse_spanned(expression: Vec<SpannedToken>) -> Result<Self, MathError> {
+        let defined_function = Self::parse_spanned_unvalidated(expression)?;
+        defined_function.validate()?;
+        Ok(defined_function)
+    }
+
+    /// this will parse a function definition without validating the identities in its body, for definitions that
+    /// are validated later against the definitions they are added to.
+    pub fn parse_spanned_unvalidated(expression: Vec<SpannedToken>) -> Result<Self, MathError> {
+        let invalid_definition = |spanned_token: Option<&SpannedToken>, message: &str| {
+            MathError::InvalidDefinition {
+                span: spanned_token.and_then(|spanned_token| spanned_token.span),
+                message: message.to_string(),
+            }
         };
 
-        if let Token::Operator(Operator::OpenParen) = expression.remove(0) {
-        } else {
-            return Err("expected open bracket".to_string());
-        }
+        // parse out the signature of the function
+        let (signature, expression) = match expression.as_slice() {
+            [SpannedToken {
+                token: Token::Identity(signature),
+                ..
+            }, open_paren, expression @ ..]
+                if open_paren.token.is_open_paren() =>
+            {
+                (signature.clone(), expression)
+            }
+            [SpannedToken {
+                token: Token::Identity(_),
+                ..
+            }, spanned_token, ..] => {
+                return Err(invalid_definition(Some(spanned_token), "expected open bracket"))
+            }
+            _ => {
+                return Err(invalid_definition(
+                    expression.first(),
+                    "missing signature",
+                ))
+            }
+        };
 
         let index_of_closing_brace = expression
             .iter()
-            .enumerate()
-            .find_or_first(|(_index, token)| token.is_close_paren())
-            .map(|(index, _token)| index)
-            .ok_or("expected close bracket".to_string())?;
-
-        let argument_names_tokens = expression
-            .drain(0..index_of_closing_brace)
-            .collect::<Expression>();
-
-        if argument_names_tokens.iter().any(|token| {
-            if let Token::Identity(_) | Token::Operator(Operator::ArgumentSeparator) = token {
-                false
-            } else {
-                true
-            }
-        }) {
-            return Err("expected identity or argument seperator".to_string());
-        }
+            .position(|spanned_token| spanned_token.token.is_close_paren())
+            .ok_or(invalid_definition(expression.last(), "expected close bracket"))?;
+
+        let (argument_names_tokens, expression) = expression.split_at(index_of_closing_brace);
 
         let mut argument_names: Vec<String> = vec![];
         let mut expected_token_type: Token = Token::Identity(String::default());
-        for token in argument_names_tokens.into_iter() {
-            match token {
+        for spanned_token in argument_names_tokens {
+            match &spanned_token.token {
                 Token::Identity(identity) if expected_token_type.is_identity() => {
-                    argument_names.push(identity);
+                    argument_names.push(identity.clone());
                     expected_token_type = Token::Operator(Operator::ArgumentSeparator)
                 }
                 Token::Operator(Operator::ArgumentSeparator)
@@ -71,77 +92,274 @@ impl DefinedFunction {
                 {
                     expected_token_type = Token::Identity(String::default())
                 }
-                _ => return Err(format!("expected {expected_token_type:?}")),
+                _ if expected_token_type.is_identity() => {
+                    return Err(invalid_definition(
+                        Some(spanned_token),
+                        "expected an argument name",
+                    ))
+                }
+                _ => {
+                    return Err(invalid_definition(
+                        Some(spanned_token),
+                        "expected argument seperator",
+                    ))
+                }
             }
         }
 
-        if let &[Token::Operator(Operator::CloseParen), Token::Operator(Operator::FunctionAssignment)] =
-            expression.drain(0..2).as_slice()
-        {
-        } else {
-            return Err("expected close paren and function assigment operator".to_string());
-        }
+        let body = match expression {
+            [_close_paren, function_assignment, body @ ..]
+                if function_assignment.token.get_operator()
+                    == Some(&Operator::FunctionAssignment) =>
+            {
+                body
+            }
+            _ => {
+                return Err(invalid_definition(
+                    expression.get(1).or(expression.first()),
+                    "expected close paren and function assigment operator",
+                ))
+            }
+        };
 
-        let defined_function = Self {
+        Ok(Self {
             arg_names: argument_names,
-            expression: expression,
+            expression: parse_body(body)?,
             signature: Some(signature),
+        })
+    }
+
+    /// this will validate a dynamic function against the default math definitions.
+    pub fn validate(&self) -> Result<(), MathError> {
+        self.validate_with_definitions(&MathDefinition::default_math_definitions())
+    }
+
+    /// this will validate a dynamic function, erroring on the first identity that is not one of its arguments, a
+    /// name in the math definitions or an invocation of a function. the function may invoke itself.
+    pub fn validate_with_definitions(
+        &self,
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<(), MathError> {
+        self.validate_with_functions(math_definitions, &[])
+    }
+
+    /// this will validate a dynamic function like `validate_with_definitions`, also allowing invocations of the
+    /// given function names. they are functions that will be defined later, so functions can invoke each other.
+    pub fn validate_with_functions(
+        &self,
+        math_definitions: &HashMap<String, MathDefinition>,
+        function_names: &[&str],
+    ) -> Result<(), MathError> {
+        let is_function = |identity: &str| {
+            self.signature.as_deref() == Some(identity)
+                || function_names.contains(&identity)
+                || math_definitions
+                    .get(identity)
+                    .is_some_and(MathDefinition::is_function)
         };
 
-        if !defined_function.valid() {
-            Err("invalid function, undeclared variable used".to_string())
-        } else {
-            Ok(defined_function)
+        for (identity, node) in self.expression.identity_nodes() {
+            match node.kind {
+                AstKind::Call { .. } if is_function(identity) => {}
+                AstKind::Call { .. } if math_definitions.contains_key(identity) => {
+                    return Err(MathError::NotAFunction {
+                        span: node.span,
+                        identifier: identity.clone(),
+                    })
+                }
+                // a name that is not invoked is a value or a function passed to another function
+                AstKind::Identity(_)
+                    if self.arg_names.contains(identity)
+                        || math_definitions.contains_key(identity)
+                        || is_function(identity) => {}
+                _ => {
+                    return Err(MathError::UnknownIdentifier {
+                        span: node.span,
+                        identifier: identity.clone(),
+                    })
+                }
+            }
         }
+        Ok(())
     }
 
-    /// this will validate a dynamic function.
-    /// TODO : we need to make more in depth errors here and possibly account for external variables
-    /// EX if functions and variables are stored in external state but are used in a function it needs to
-    /// be accounted for.
     pub fn valid(&self) -> bool {
-        self.expression.iter().all(|token| {
-            !token.is_identity()
-                || token
-                    .get_identity()
-                    .is_some_and(|identity| self.arg_names.contains(identity))
-        })
+        self.validate().is_ok()
+    }
+
+    /// this will evaluate the function over dual numbers, the derivatives of the result are with respect to whatever
+    /// variables the dual arguments carry derivatives for.
+    pub fn evaluate_dual(
+        &self,
+        args: &[Dual],
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<Dual, MathError> {
+        if args.is_empty() {
+            return Err(MathError::MissingArguments { span: None });
+        }
+
+        if args.len() != self.arg_names.len() {
+            return Err(MathError::ArityMismatch {
+                span: None,
+                expected: self.arg_names.len(),
+                received: args.len(),
+            });
+        }
+
+        let variable_arg_map = self
+            .arg_names
+            .iter()
+            .zip(args)
+            .map(|(arg_name, arg)| (arg_name.as_str(), arg.clone()))
+            .collect::<HashMap<&str, Dual>>();
+
+        nested(|| eval_ast_dual(&self.expression, math_definitions, &variable_arg_map))
+    }
+
+    /// this will evaluate the function and its gradient at the given arguments, the derivatives of the result are
+    /// with respect to each argument in order.
+    pub fn gradient(
+        &self,
+        args: &[f64],
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<Dual, MathError> {
+        let args = args
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| Dual::variable(*arg, index, args.len()))
+            .collect::<Vec<Dual>>();
+        self.evaluate_dual(&args, math_definitions)
     }
 }
 
 impl MathFunction for DefinedFunction {
-    fn evaluate(&self, args : &[f64], math_definitions : &HashMap<&str, MathDefinition>) -> Result<f64, String> {
-        if args.len() == 0 {
-            return Err("error : arguments must be supplied to a function".to_string());
+    fn evaluate(&self, args : &[f64], math_definitions : &HashMap<String, MathDefinition>) -> Result<f64, MathError> {
+        self.evaluate_numeric(args, math_definitions)
+    }
+}
+
+impl DefinedFunction {
+    /// this will evaluate the function over a numeric backend.
+    pub fn evaluate_numeric<N: Numeric>(
+        &self,
+        args: &[N],
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<N, MathError> {
+        if args.is_empty() {
+            return Err(MathError::MissingArguments { span: None });
         }
 
         if args.len() != self.arg_names.len() {
-            return Err(format!(
-                "expected {} args but recieved : {}",
-                self.arg_names.len(),
-                args.len()
-            ));
+            return Err(MathError::ArityMismatch {
+                span: None,
+                expected: self.arg_names.len(),
+                received: args.len(),
+            });
         }
 
         let variable_arg_map = self
             .arg_names
             .iter()
-            .enumerate()
-            .map(|(index, arg_name)| (arg_name, args[index]))
-            .collect::<HashMap<&String, f64>>();
-
-        let expression_to_evaluate = self
-            .expression
-            .clone()
-            .into_iter()
-            .map(|token| {
-                match token {
-                    Token::Identity(identity) if !math_definitions.contains_key(identity.as_str()) => Token::Number(variable_arg_map[&identity]),
-                    _ => token
+            .zip(args)
+            .map(|(arg_name, arg)| (arg_name.as_str(), arg.clone()))
+            .collect::<HashMap<&str, N>>();
+
+        nested(|| {
+            eval_ast_numeric_with_variables(&self.expression, math_definitions, &variable_arg_map)
+        })
+    }
+}
+
+fn is_keyword(spanned_token: &SpannedToken, keyword: &str) -> bool {
+    spanned_token
+        .token
+        .get_identity()
+        .is_some_and(|identity| identity == keyword)
+}
+
+/// a case of a piecewise function has a "when" and a condition, or ends with "otherwise".
+pub fn is_piecewise_case(tokens: &[SpannedToken]) -> bool {
+    tokens.iter().any(|spanned_token| {
+        is_keyword(spanned_token, WHEN) || is_keyword(spanned_token, OTHERWISE)
+    })
+}
+
+/// this will parse the body of a function, which is an expression or the cases of a piecewise function like
+/// "x^2 when x < 0; sqrt(x) otherwise" separated like statements. the cases become nested ifs so their conditions
+/// are checked in order, without an otherwise case the function errors where none of them apply.
+fn parse_body(body: &[SpannedToken]) -> Result<Ast, MathError> {
+    if !is_piecewise_case(body) {
+        return parse_spanned_ast(body);
+    }
+
+    let cases = body
+        .split(|spanned_token| spanned_token.token.is_statement_separator())
+        .filter(|case| !case.is_empty())
+        .collect::<Vec<&[SpannedToken]>>();
+
+    let mut guarded_cases = vec![];
+    let mut otherwise = None;
+    for (index, case) in cases.iter().enumerate() {
+        match case.split_last() {
+            Some((keyword, value)) if is_keyword(keyword, OTHERWISE) => {
+                if index + 1 < cases.len() {
+                    return Err(MathError::InvalidDefinition {
+                        span: keyword.span,
+                        message: "the otherwise case must be the last case".to_string(),
+                    });
                 }
-            })
-            .collect();
+                otherwise =
+                    Some(parse_spanned_ast(value).map_err(|error| error.with_span(keyword.span))?);
+            }
+            _ => {
+                let when_index = case
+                    .iter()
+                    .position(|spanned_token| is_keyword(spanned_token, WHEN))
+                    .ok_or(MathError::InvalidDefinition {
+                        span: Span::join(case[0].span, case[case.len() - 1].span),
+                        message: "expected a case like \"x^2 when x < 0\" or \"sqrt(x) otherwise\""
+                            .to_string(),
+                    })?;
+                let when_span = case[when_index].span;
+                let value = parse_spanned_ast(&case[..when_index])
+                    .map_err(|error| error.with_span(when_span))?;
+                let condition = parse_spanned_ast(&case[when_index + 1..])
+                    .map_err(|error| error.with_span(when_span))?;
+                guarded_cases.push((condition, value));
+            }
+        }
+    }
 
-        eval_expression(expression_to_evaluate, math_definitions, true)
+    Ok(guarded_cases
+        .into_iter()
+        .rev()
+        .fold(otherwise, |otherwise, (condition, value)| {
+            let end_span = otherwise
+                .as_ref()
+                .map_or(condition.span, |otherwise| otherwise.span);
+            let span = Span::join(value.span, end_span);
+            let mut args = vec![condition, value];
+            args.extend(otherwise);
+            Some(Ast::new(
+                AstKind::Call {
+                    identity: "if".to_string(),
+                    args,
+                },
+                span,
+            ))
+        })
+        .expect("a piecewise body has at least one case"))
+}
+
+/// this will print the function back to a definition like "f(x, y): x * y", leaving out the name if it has none.
+impl fmt::Display for DefinedFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}): {}",
+            self.signature.as_deref().unwrap_or_default(),
+            self.arg_names.join(", "),
+            self.expression
+        )
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,391 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// this will differentiate a defined function with respect to one of its arguments, giving a function with the same
+/// arguments named like "df_dx". the function can only call the default math definitions.
+pub fn derivative(function: &DefinedFunction, var: &str) -> Result<DefinedFunction, MathError> {
+    derivative_with_definitions(function, var, &MathDefinition::default_math_definitions())
+}
+
+/// this will differentiate a defined function with respect to one of its arguments, resolving the functions it calls
+/// in the given math definitions.
+pub fn derivative_with_definitions(
+    function: &DefinedFunction,
+    var: &str,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<DefinedFunction, MathError> {
+    if !function.arg_names.iter().any(|arg_name| arg_name == var) {
+        return Err(MathError::InvalidArgument {
+            span: None,
+            message: format!("{var:?} is not an argument of the function"),
+        });
+    }
+
+    Ok(DefinedFunction {
+        signature: function
+            .signature
+            .as_ref()
+            .map(|signature| format!("d{signature}_d{var}")),
+        arg_names: function.arg_names.clone(),
+        expression: derivative_ast(&function.expression, var, math_definitions)?,
+    })
+}
+
+/// this will differentiate a syntax tree with respect to a variable. every other identity is held constant and
+/// invoked defined functions are inlined, so the result only invokes built in functions.
+pub fn derivative_ast(
+    ast: &Ast,
+    var: &str,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Ast, MathError> {
+    // the arguments of inlined functions nest in each other, so the tree can be as deep as the inlining limit
+    let d = |ast: &Ast| grow_stack(|| derivative_ast(ast, var, math_definitions));
+
+    match &ast.kind {
+        AstKind::Number(_) => Ok(Ast::number(0f64)),
+        AstKind::Identity(identity) if identity == var => Ok(Ast::number(1f64)),
+        AstKind::Identity(_) => Ok(Ast::number(0f64)),
+        AstKind::Grouping(inner) => d(inner),
+        AstKind::Lambda { .. } => Err(MathError::NotAValue {
+            span: ast.span,
+            identifier: ast.to_string(),
+        }),
+        AstKind::UnaryOperation { operator, operand } => match operator {
+            Operator::Addition => d(operand),
+            Operator::Subtraction => Ok(neg(d(operand)?)),
+            Operator::Factorial => built_in_derivative(
+                "factorial",
+                std::slice::from_ref(operand),
+                ast,
+                var,
+                math_definitions,
+            ),
+            // a boolean is constant except where it jumps between 0 and 1
+            Operator::Not => Ok(Ast::number(0f64)),
+            _ => Err(no_derivative(operator.symbol(), ast.span)),
+        },
+        AstKind::BinaryOperation {
+            operator,
+            left,
+            right,
+        } => match operator {
+            Operator::Addition => Ok(add(d(left)?, d(right)?)),
+            Operator::Subtraction => Ok(sub(d(left)?, d(right)?)),
+            // (uv)' = u'v + uv'
+            Operator::Multiplication => Ok(add(
+                mul(d(left)?, (**right).clone()),
+                mul((**left).clone(), d(right)?),
+            )),
+            // (u/v)' = (u'v - uv') / v^2
+            Operator::Division => Ok(div(
+                sub(
+                    mul(d(left)?, (**right).clone()),
+                    mul((**left).clone(), d(right)?),
+                ),
+                pow((**right).clone(), Ast::number(2f64)),
+            )),
+            // u % v is u minus a multiple of v that only changes where it is discontinuous
+            Operator::Modulus if !depends_on(right, var) => d(left),
+            Operator::Exponentiation => power_derivative(left, right, var, math_definitions),
+            operator if operator.is_boolean_operator() => Ok(Ast::number(0f64)),
+            _ => Err(no_derivative(operator.symbol(), ast.span)),
+        },
+        AstKind::Call { identity, args } => match math_definitions.get(identity.as_str()) {
+            Some(MathDefinition::BuiltInFunction(function)) => {
+                let args = spread_args(args, math_definitions)?;
+                match function
+                    .arg_count
+                    .filter(|arg_count| *arg_count != args.len())
+                {
+                    Some(arg_count) => Err(MathError::ArityMismatch {
+                        span: ast.span,
+                        expected: arg_count,
+                        received: args.len(),
+                    }),
+                    None => built_in_derivative(identity, &args, ast, var, math_definitions),
+                }
+            }
+            Some(MathDefinition::DefinedFunction(function)) => {
+                let args = spread_args(args, math_definitions)?;
+                if args.len() != function.arg_names.len() {
+                    return Err(MathError::ArityMismatch {
+                        span: ast.span,
+                        expected: function.arg_names.len(),
+                        received: args.len(),
+                    });
+                }
+                // a recursive function would be inlined forever, so inlining is limited like recursion
+                let arg_map = function
+                    .arg_names
+                    .iter()
+                    .map(String::as_str)
+                    .zip(args.iter())
+                    .collect::<HashMap<&str, &Ast>>();
+                inlined(|| d(&substitute(&function.expression, &arg_map)))
+                    .map_err(|error| error.at(ast.span))
+            }
+            // the derivative of an if is the derivative of each branch, which is wrong only where the condition flips
+            Some(MathDefinition::SpecialForm(SpecialForm::If)) => {
+                let (condition, then, otherwise) = if_args(args, ast.span)?;
+                let mut args = vec![condition.clone(), d(then)?];
+                if let Some(otherwise) = otherwise {
+                    args.push(d(otherwise)?);
+                }
+                Ok(Ast::new(
+                    AstKind::Call {
+                        identity: identity.clone(),
+                        args,
+                    },
+                    ast.span,
+                ))
+            }
+            Some(MathDefinition::SpecialForm(special_form)) => {
+                d(&special_form.expand(args, ast.span, math_definitions)?)
+            }
+            Some(
+                MathDefinition::Constant(_)
+                | MathDefinition::Variable(_)
+                | MathDefinition::ComplexConstant(_)
+                | MathDefinition::NumericVariable(_),
+            ) => Err(MathError::NotAFunction {
+                span: ast.span,
+                identifier: identity.clone(),
+            }),
+            None => Err(MathError::UnknownIdentifier {
+                span: ast.span,
+                identifier: identity.clone(),
+            }),
+        },
+    }
+}
+
+/// this will tell if a tree refers to a variable anywhere in it.
+pub fn depends_on(ast: &Ast, var: &str) -> bool {
+    ast.identities().into_iter().any(|identity| identity == var)
+}
+
+/// this will replace identities with trees, all at once so a replacement is never replaced again.
+pub fn substitute(ast: &Ast, replacements: &HashMap<&str, &Ast>) -> Ast {
+    let replace = |ast: &Ast| Box::new(substitute(ast, replacements));
+
+    let kind = match &ast.kind {
+        AstKind::Identity(identity) => match replacements.get(identity.as_str()) {
+            Some(replacement) => return (*replacement).clone(),
+            None => AstKind::Identity(identity.clone()),
+        },
+        AstKind::Number(num) => AstKind::Number(num.clone()),
+        AstKind::Grouping(inner) => AstKind::Grouping(replace(inner)),
+        // the arguments of a lambda shadow any replacement of the same name
+        AstKind::Lambda { arg_names, body } => {
+            let replacements = replacements
+                .iter()
+                .filter(|(identity, _)| !arg_names.iter().any(|arg_name| arg_name == *identity))
+                .map(|(identity, replacement)| (*identity, *replacement))
+                .collect();
+            AstKind::Lambda {
+                arg_names: arg_names.clone(),
+                body: Box::new(substitute(body, &replacements)),
+            }
+        }
+        AstKind::UnaryOperation { operator, operand } => AstKind::UnaryOperation {
+            operator: *operator,
+            operand: replace(operand),
+        },
+        AstKind::BinaryOperation {
+            operator,
+            left,
+            right,
+        } => AstKind::BinaryOperation {
+            operator: *operator,
+            left: replace(left),
+            right: replace(right),
+        },
+        AstKind::Call { identity, args } => AstKind::Call {
+            identity: identity.clone(),
+            args: args
+                .iter()
+                .map(|arg| substitute(arg, replacements))
+                .collect(),
+        },
+    };
+    Ast::new(kind, ast.span)
+}
+
+fn no_derivative(name: &str, span: Option<Span>) -> MathError {
+    MathError::Domain {
+        span,
+        message: format!("{name} has no derivative"),
+    }
+}
+
+/// (u^v)' with the simpler rules when only one of the base and exponent depends on the variable.
+fn power_derivative(
+    base: &Ast,
+    exponent: &Ast,
+    var: &str,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Ast, MathError> {
+    let power = || pow(base.clone(), exponent.clone());
+
+    match (depends_on(base, var), depends_on(exponent, var)) {
+        (false, false) => Ok(Ast::number(0f64)),
+        // (u^n)' = n u^(n - 1) u'
+        (true, false) => Ok(mul(
+            mul(
+                exponent.clone(),
+                pow(base.clone(), sub(exponent.clone(), Ast::number(1f64))),
+            ),
+            derivative_ast(base, var, math_definitions)?,
+        )),
+        // (a^v)' = a^v ln(a) v'
+        (false, true) => Ok(mul(
+            mul(power(), call("ln", base.clone())),
+            derivative_ast(exponent, var, math_definitions)?,
+        )),
+        // (u^v)' = u^v (v' ln(u) + v u' / u)
+        (true, true) => Ok(mul(
+            power(),
+            add(
+                mul(
+                    derivative_ast(exponent, var, math_definitions)?,
+                    call("ln", base.clone()),
+                ),
+                div(
+                    mul(
+                        exponent.clone(),
+                        derivative_ast(base, var, math_definitions)?,
+                    ),
+                    base.clone(),
+                ),
+            ),
+        )),
+    }
+}
+
+/// the chain rule for every built in function, f(u)' = f'(u) u'.
+fn built_in_derivative(
+    identity: &str,
+    args: &[Ast],
+    ast: &Ast,
+    var: &str,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Ast, MathError> {
+    let d = |ast: &Ast| derivative_ast(ast, var, math_definitions);
+
+    match identity {
+        "pow" => return power_derivative(&args[0], &args[1], var, math_definitions),
+        "mean" => {
+            return Ok(Ast::call(
+                "mean",
+                args.iter()
+                    .map(d)
+                    .collect::<Result<Vec<Ast>, MathError>>()?,
+            ))
+        }
+        "polygamma" if depends_on(&args[0], var) => {
+            return Err(no_derivative("polygamma of a variable order", ast.span))
+        }
+        "polygamma" => {
+            return Ok(mul(
+                Ast::call(
+                    "polygamma",
+                    vec![add(args[0].clone(), Ast::number(1f64)), args[1].clone()],
+                ),
+                d(&args[1])?,
+            ))
+        }
+        _ => {}
+    }
+
+    let u = || args[0].clone();
+    let outer_derivative = match identity {
+        "sqrt" => div(Ast::number(1f64), mul(Ast::number(2f64), call("sqrt", u()))),
+        "cbrt" => div(
+            Ast::number(1f64),
+            mul(Ast::number(3f64), pow(call("cbrt", u()), Ast::number(2f64))),
+        ),
+        "abs" => div(u(), call("abs", u())),
+        "sin" | "sine" => call("cos", u()),
+        "cos" | "cosine" => neg(call("sin", u())),
+        "tan" | "tangent" => pow(call("sec", u()), Ast::number(2f64)),
+        "sec" | "secant" => mul(call("sec", u()), call("tan", u())),
+        "csc" | "cosecant" => neg(mul(call("csc", u()), call("cot", u()))),
+        "cot" | "cotangent" => neg(pow(call("csc", u()), Ast::number(2f64))),
+        "sinh" => call("cosh", u()),
+        "cosh" => call("sinh", u()),
+        "tanh" => sub(Ast::number(1f64), pow(call("tanh", u()), Ast::number(2f64))),
+        "log" | "log10" => div(Ast::number(1f64), mul(u(), call("ln", Ast::number(10f64)))),
+        "log2" => div(Ast::number(1f64), mul(u(), call("ln", Ast::number(2f64)))),
+        "ln" => div(Ast::number(1f64), u()),
+        "gamma" => mul(call("gamma", u()), digamma(u())),
+        "lgamma" => digamma(u()),
+        "factorial" => mul(call("factorial", u()), digamma(add(u(), Ast::number(1f64)))),
+        "re" | "conj" => Ast::number(1f64),
+        "im" | "arg" => Ast::number(0f64),
+        _ => return Err(no_derivative(identity, ast.span)),
+    };
+
+    Ok(mul(outer_derivative, d(&args[0])?))
+}
+
+fn digamma(u: Ast) -> Ast {
+    Ast::call("polygamma", vec![Ast::number(0f64), u])
+}
+
+fn call(identity: &str, arg: Ast) -> Ast {
+    Ast::call(identity, vec![arg])
+}
+
+// these build the nodes of a derivative, skipping the additions of zero and multiplications by one that the rules
+// would otherwise be full of.
+
+fn add(left: Ast, right: Ast) -> Ast {
+    match (left.get_num(), right.get_num()) {
+        (Some(left), Some(right)) => Ast::number(left + right),
+        (Some(0f64), _) => right,
+        (_, Some(0f64)) => left,
+        _ => Ast::binary_operation(Operator::Addition, left, right),
+    }
+}
+
+fn sub(left: Ast, right: Ast) -> Ast {
+    match (left.get_num(), right.get_num()) {
+        (Some(left), Some(right)) => Ast::number(left - right),
+        (Some(0f64), _) => neg(right),
+        (_, Some(0f64)) => left,
+        _ => Ast::binary_operation(Operator::Subtraction, left, right),
+    }
+}
+
+fn mul(left: Ast, right: Ast) -> Ast {
+    match (left.get_num(), right.get_num()) {
+        (Some(left), Some(right)) => Ast::number(left * right),
+        (Some(0f64), _) | (_, Some(0f64)) => Ast::number(0f64),
+        (Some(1f64), _) => right,
+        (_, Some(1f64)) => left,
+        _ => Ast::binary_operation(Operator::Multiplication, left, right),
+    }
+}
+
+fn div(left: Ast, right: Ast) -> Ast {
+    match (left.get_num(), right.get_num()) {
+        (Some(0f64), _) => Ast::number(0f64),
+        (_, Some(1f64)) => left,
+        _ => Ast::binary_operation(Operator::Division, left, right),
+    }
+}
+
+fn pow(base: Ast, exponent: Ast) -> Ast {
+    match exponent.get_num() {
+        Some(0f64) => Ast::number(1f64),
+        Some(1f64) => base,
+        _ => Ast::binary_operation(Operator::Exponentiation, base, exponent),
+    }
+}
+
+fn neg(operand: Ast) -> Ast {
+    match operand.get_num() {
+        Some(num) => Ast::number(-num),
+        None => Ast::unary_operation(Operator::Subtraction, operand),
+    }
+}
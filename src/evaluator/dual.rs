@@ -0,0 +1,353 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// a dual number, a value along with its partial derivatives with respect to some chosen variables.
+/// a constant has no derivatives, any derivative that is missing is zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dual {
+    pub value: f64,
+    pub derivatives: Vec<f64>,
+}
+impl Dual {
+    pub fn constant(value: f64) -> Self {
+        Self {
+            value,
+            derivatives: vec![],
+        }
+    }
+
+    /// the variable at an index of the chosen variables, its derivative with respect to itself is one.
+    pub fn variable(value: f64, index: usize, variable_count: usize) -> Self {
+        let mut derivatives = vec![0f64; variable_count];
+        derivatives[index] = 1f64;
+        Self { value, derivatives }
+    }
+
+    /// this will get the partial derivative with respect to the variable at an index.
+    pub fn derivative(&self, index: usize) -> f64 {
+        self.derivatives.get(index).copied().unwrap_or_default()
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.derivatives
+            .iter()
+            .all(|derivative| *derivative == 0f64)
+    }
+
+    /// this will apply the chain rule to the result of a function of some dual arguments, given the partial
+    /// derivative of the function with respect to each argument. partial derivatives are only asked for the arguments
+    /// that are not constant, so a function can be used on constants where it has no derivative.
+    pub fn from_partials(
+        value: f64,
+        args: &[&Dual],
+        partial_derivative: impl Fn(usize) -> Result<f64, MathError>,
+    ) -> Result<Self, MathError> {
+        let mut derivatives = vec![];
+        for (index, arg) in args.iter().enumerate() {
+            if arg.is_constant() {
+                continue;
+            }
+            let partial_derivative = partial_derivative(index)?;
+            derivatives.resize(derivatives.len().max(arg.derivatives.len()), 0f64);
+            for (derivative, arg_derivative) in derivatives.iter_mut().zip(&arg.derivatives) {
+                *derivative += partial_derivative * arg_derivative;
+            }
+        }
+        Ok(Self { value, derivatives })
+    }
+}
+
+fn no_derivative(name: &str) -> MathError {
+    MathError::Domain {
+        span: None,
+        message: format!("{name} has no derivative"),
+    }
+}
+
+impl Operator {
+    pub fn apply_dual_operation_2_operands(&self, a: &Dual, b: &Dual) -> Result<Dual, MathError> {
+        let value = self.apply_operation_2_operands(a.value, b.value)?;
+        let (x, y) = (a.value, b.value);
+
+        Dual::from_partials(value, &[a, b], |index| match (self, index) {
+            (Self::Addition, _) | (Self::Subtraction, 0) | (Self::Modulus, 0) => Ok(1f64),
+            (Self::Subtraction, _) => Ok(-1f64),
+            (Self::Multiplication, 0) => Ok(y),
+            (Self::Multiplication, _) => Ok(x),
+            (Self::Division, 0) => Ok(1f64 / y),
+            (Self::Division, _) => Ok(-x / (y * y)),
+            (Self::Modulus, _) => Ok(-(x / y).trunc()),
+            (Self::Exponentiation, 0) => Ok(y * x.powf(y - 1f64)),
+            (Self::Exponentiation, _) => Ok(value * x.ln()),
+            // a boolean only changes where it jumps between 0 and 1
+            _ if self.is_boolean_operator() => Ok(0f64),
+            _ => Err(MathError::InvalidOperator {
+                span: None,
+                operator: *self,
+            }),
+        })
+    }
+
+    pub fn apply_dual_operation_1_operand(&self, a: &Dual) -> Result<Dual, MathError> {
+        let value = self.apply_operation_1_operand(a.value)?;
+
+        Dual::from_partials(value, &[a], |_| match self {
+            Self::Addition => Ok(1f64),
+            Self::Subtraction => Ok(-1f64),
+            Self::Factorial => Ok(value * polygamma(0f64, a.value + 1f64)?),
+            Self::Not => Ok(0f64),
+            _ => Err(no_derivative(self.symbol())),
+        })
+    }
+}
+
+/// the partial derivative of a built in function with respect to one of its arguments, given the arguments and the
+/// value of the function at them.
+pub fn built_in_partial_derivative(
+    identity: &str,
+    args: &[f64],
+    value: f64,
+    index: usize,
+) -> Result<f64, MathError> {
+    let x = args[0];
+    match (identity, index) {
+        ("sqrt", _) => Ok(0.5f64 / value),
+        ("cbrt", _) => Ok(1f64 / (3f64 * value * value)),
+        ("abs", _) => Ok(x.signum()),
+        ("sin" | "sine", _) => Ok(x.cos()),
+        ("cos" | "cosine", _) => Ok(-x.sin()),
+        ("tan" | "tangent", _) => Ok(1f64 / (x.cos() * x.cos())),
+        ("sec" | "secant", _) => Ok(value * x.tan()),
+        ("csc" | "cosecant", _) => Ok(-value / x.tan()),
+        ("cot" | "cotangent", _) => Ok(-1f64 / (x.sin() * x.sin())),
+        ("sinh", _) => Ok(x.cosh()),
+        ("cosh", _) => Ok(x.sinh()),
+        ("tanh", _) => Ok(1f64 - value * value),
+        ("log" | "log10", _) => Ok(1f64 / (x * std::f64::consts::LN_10)),
+        ("log2", _) => Ok(1f64 / (x * std::f64::consts::LN_2)),
+        ("ln", _) => Ok(1f64 / x),
+        ("gamma", _) => Ok(value * polygamma(0f64, x)?),
+        ("lgamma", _) => polygamma(0f64, x),
+        ("factorial", _) => Ok(value * polygamma(0f64, x + 1f64)?),
+        ("polygamma", 0) => Err(no_derivative("polygamma of a variable order")),
+        ("polygamma", _) => polygamma(x + 1f64, args[1]),
+        ("pow", 0) => Ok(args[1] * x.powf(args[1] - 1f64)),
+        ("pow", _) => Ok(value * x.ln()),
+        ("mean", _) => Ok(1f64 / args.len() as f64),
+        ("re" | "conj", _) => Ok(1f64),
+        ("im" | "arg", _) => Ok(0f64),
+        _ => Err(no_derivative(identity)),
+    }
+}
+
+impl BuiltInFunction {
+    /// this will evaluate the built in function over dual numbers, its value is exactly what `evaluate` gives.
+    pub fn evaluate_dual(
+        &self,
+        identity: &str,
+        args: &[Dual],
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<Dual, MathError> {
+        let values = args.iter().map(|arg| arg.value).collect::<Vec<f64>>();
+        let value = self.evaluate(&values, math_definitions)?;
+        Dual::from_partials(value, &args.iter().collect::<Vec<&Dual>>(), |index| {
+            built_in_partial_derivative(identity, &values, value, index)
+        })
+    }
+}
+
+/// this will evaluate a syntax tree and its partial derivatives with respect to the chosen variables in one pass.
+/// every chosen variable must be given a value, the derivatives of the result are in the order they were chosen.
+pub fn eval_gradient(
+    ast: &Ast,
+    math_definitions: &HashMap<String, MathDefinition>,
+    variables: &HashMap<&str, f64>,
+    with_respect_to: &[&str],
+) -> Result<Dual, MathError> {
+    if let Some(var) = with_respect_to
+        .iter()
+        .find(|var| !variables.contains_key(*var))
+    {
+        return Err(MathError::InvalidArgument {
+            span: None,
+            message: format!("{var:?} has no value to differentiate at"),
+        });
+    }
+
+    let dual_variables = variables
+        .iter()
+        .map(|(name, value)| {
+            let dual = match with_respect_to.iter().position(|var| var == name) {
+                Some(index) => Dual::variable(*value, index, with_respect_to.len()),
+                None => Dual::constant(*value),
+            };
+            (*name, dual)
+        })
+        .collect::<HashMap<&str, Dual>>();
+
+    eval_ast_dual(ast, math_definitions, &dual_variables)
+}
+
+/// this will evaluate a syntax tree over dual numbers. variables shadow any math definition of the same name.
+pub fn eval_ast_dual(
+    ast: &Ast,
+    math_definitions: &HashMap<String, MathDefinition>,
+    variables: &HashMap<&str, Dual>,
+) -> Result<Dual, MathError> {
+    let eval = |ast: &Ast| eval_ast_dual(ast, math_definitions, variables);
+
+    match &ast.kind {
+        AstKind::Number(num) => Ok(Dual::constant(num.value)),
+        AstKind::Identity(identity) => {
+            if let Some(value) = variables.get(identity.as_str()) {
+                return Ok(value.clone());
+            }
+            match math_definitions.get(identity.as_str()) {
+                Some(MathDefinition::Constant(value) | MathDefinition::Variable(value)) => {
+                    Ok(Dual::constant(*value))
+                }
+                Some(MathDefinition::ComplexConstant(_)) => {
+                    Err(numeric_value_error(identity, Complex::NAME, ast.span))
+                }
+                Some(MathDefinition::NumericVariable(value)) => value
+                    .to_f64()
+                    .map(Dual::constant)
+                    .ok_or(numeric_value_error(identity, value.backend(), ast.span)),
+                Some(_) => Err(MathError::NotAValue {
+                    span: ast.span,
+                    identifier: identity.clone(),
+                }),
+                None => Err(MathError::UnknownIdentifier {
+                    span: ast.span,
+                    identifier: identity.clone(),
+                }),
+            }
+        }
+        AstKind::Grouping(inner) => eval(inner),
+        // a lambda is only a value when it is passed to a function
+        AstKind::Lambda { .. } => Err(MathError::NotAValue {
+            span: ast.span,
+            identifier: ast.to_string(),
+        }),
+        AstKind::UnaryOperation { operator, operand } => operator
+            .apply_dual_operation_1_operand(&eval(operand)?)
+            .map_err(|error| error.with_span(ast.span)),
+        AstKind::BinaryOperation {
+            operator,
+            left,
+            right,
+        } => {
+            let left = eval(left)?;
+            if let Some(result) = operator
+                .short_circuit(left.value)
+                .map_err(|error| error.with_span(ast.span))?
+            {
+                return Ok(Dual::constant(result));
+            }
+            operator
+                .apply_dual_operation_2_operands(&left, &eval(right)?)
+                .map_err(|error| error.with_span(ast.span))
+        }
+        AstKind::Call { identity, args } => {
+            let math_definition =
+                math_definitions
+                    .get(identity.as_str())
+                    .ok_or(MathError::UnknownIdentifier {
+                        span: ast.span,
+                        identifier: identity.clone(),
+                    })?;
+
+            let is_variable = |identity: &str| variables.contains_key(identity);
+            let reduce_args = || {
+                spread_args_in_scope(args, math_definitions, &is_variable)?
+                    .iter()
+                    .map(eval)
+                    .collect::<Result<Vec<Dual>, MathError>>()
+            };
+
+            match math_definition {
+                MathDefinition::BuiltInFunction(function) => function
+                    .evaluate_dual(identity, &reduce_args()?, math_definitions)
+                    .map_err(|error| error.with_span(ast.span)),
+                // errors inside the function body point into its definition, not this input
+                MathDefinition::DefinedFunction(function) => function
+                    .evaluate_dual(&reduce_args()?, math_definitions)
+                    .map_err(|error| error.at(ast.span)),
+                // only the branch the condition chooses is evaluated, its derivatives are the derivatives of the if
+                MathDefinition::SpecialForm(SpecialForm::If) => {
+                    let (condition, then, otherwise) = if_args(args, ast.span)?;
+                    if condition_holds(condition, |ast| eval(ast).map(|dual| dual.value))? {
+                        eval(then)
+                    } else {
+                        eval(otherwise.ok_or(no_case_applies(ast.span))?)
+                    }
+                }
+                // the terms of a sum or product are evaluated over dual numbers, so a lambda can use the variables
+                MathDefinition::SpecialForm(
+                    special_form @ (SpecialForm::Sum | SpecialForm::Prod),
+                ) => {
+                    let [function, from, to] = args.as_slice() else {
+                        return Err(MathError::ArityMismatch {
+                            span: ast.span,
+                            expected: 3,
+                            received: args.len(),
+                        });
+                    };
+                    let function = FunctionValue::from_ast_in_scope(
+                        function,
+                        math_definitions,
+                        &is_variable,
+                        1,
+                    )?;
+                    let from = eval(from)?.value;
+                    let terms = series_terms(from, eval(to)?.value)
+                        .map_err(|error| error.with_span(ast.span))?
+                        as usize;
+                    let (operator, empty) = match special_form {
+                        SpecialForm::Sum => (Operator::Addition, 0f64),
+                        _ => (Operator::Multiplication, 1f64),
+                    };
+                    (0..terms).try_fold(Dual::constant(empty), |result, k| {
+                        let term = eval(
+                            &function.apply_ast(vec![Ast::number(from + k as f64)], ast.span),
+                        )?;
+                        operator
+                            .apply_dual_operation_2_operands(&result, &term)
+                            .map_err(|error| error.with_span(ast.span))
+                    })
+                }
+                // a root or an integral is found over real numbers, with the variables it uses held constant
+                MathDefinition::SpecialForm(
+                    special_form @ (SpecialForm::Root | SpecialForm::Integrate),
+                ) => {
+                    let real_variables = args
+                        .iter()
+                        .flat_map(Ast::identity_nodes)
+                        .filter_map(|(identity, _)| {
+                            variables
+                                .get_key_value(identity.as_str())
+                                .map(|(name, value)| (*name, value.value))
+                        })
+                        .collect::<HashMap<&str, f64>>();
+                    special_form
+                        .evaluate(args, ast.span, math_definitions, &real_variables)
+                        .map(Dual::constant)
+                }
+                MathDefinition::SpecialForm(special_form) => eval(&special_form.expand_in_scope(
+                    args,
+                    ast.span,
+                    math_definitions,
+                    |identity| variables.contains_key(identity),
+                )?)
+                .map_err(|error| error.with_span(ast.span)),
+                MathDefinition::Constant(_)
+                | MathDefinition::Variable(_)
+                | MathDefinition::ComplexConstant(_)
+                | MathDefinition::NumericVariable(_) => Err(MathError::NotAFunction {
+                    span: ast.span,
+                    identifier: identity.clone(),
+                }),
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// a function passed as an argument, like the "f" in "integrate(f, 0, 1)" or the lambda in "sum(k -> 1/k^2, 1, 10)".
+#[derive(Clone, Debug)]
+pub enum FunctionValue {
+    BuiltIn {
+        identity: String,
+        function: BuiltInFunction,
+    },
+    Defined(DefinedFunction),
+}
+impl FunctionValue {
+    /// this will resolve an argument that names a function or is a lambda, checking that the function can be called
+    /// with the given number of arguments. a lambda captures the values of the variables it uses, so a lambda inside
+    /// a function body can use the arguments of that function.
+    pub fn from_ast(
+        ast: &Ast,
+        math_definitions: &HashMap<String, MathDefinition>,
+        variables: &HashMap<&str, f64>,
+        arg_count: usize,
+    ) -> Result<Self, MathError> {
+        Self::resolve(ast, math_definitions, variables)?.with_arg_count(ast, arg_count)
+    }
+
+    /// this will resolve an argument like `from_ast` where only the names of the variables in scope are known, like
+    /// when compiling. a lambda uses the variables when it is applied rather than capturing their values.
+    pub fn from_ast_in_scope(
+        ast: &Ast,
+        math_definitions: &HashMap<String, MathDefinition>,
+        is_variable: &dyn Fn(&str) -> bool,
+        arg_count: usize,
+    ) -> Result<Self, MathError> {
+        Self::resolve_in_scope(ast, math_definitions, is_variable)?.with_arg_count(ast, arg_count)
+    }
+
+    /// this will check that the function can be called with the given number of arguments, an error points at the
+    /// argument the function was resolved from.
+    fn with_arg_count(self, ast: &Ast, arg_count: usize) -> Result<Self, MathError> {
+        match self.arg_count() {
+            Some(expected) if expected != arg_count => Err(MathError::ArityMismatch {
+                span: ast.span,
+                expected,
+                received: arg_count,
+            }),
+            _ => Ok(self),
+        }
+    }
+
+    /// this will resolve an argument that names a function or is a lambda, whatever number of arguments it takes.
+    pub fn resolve(
+        ast: &Ast,
+        math_definitions: &HashMap<String, MathDefinition>,
+        variables: &HashMap<&str, f64>,
+    ) -> Result<Self, MathError> {
+        let is_variable = |identity: &str| variables.contains_key(identity);
+        match Self::resolve_in_scope(ast, math_definitions, &is_variable)? {
+            Self::Defined(DefinedFunction {
+                signature: None,
+                arg_names,
+                expression,
+            }) => {
+                let captured_values = variables
+                    .iter()
+                    .filter(|(name, _)| !arg_names.iter().any(|arg_name| arg_name == *name))
+                    .map(|(name, value)| (*name, Ast::number(*value)))
+                    .collect::<Vec<(&str, Ast)>>();
+                let captures = captured_values
+                    .iter()
+                    .map(|(name, value)| (*name, value))
+                    .collect::<HashMap<&str, &Ast>>();
+
+                Ok(Self::Defined(DefinedFunction::anonymous(
+                    arg_names,
+                    substitute(&expression, &captures),
+                )?))
+            }
+            function => Ok(function),
+        }
+    }
+
+    /// this will resolve an argument like `resolve` where only the names of the variables in scope are known.
+    pub fn resolve_in_scope(
+        ast: &Ast,
+        math_definitions: &HashMap<String, MathDefinition>,
+        is_variable: &dyn Fn(&str) -> bool,
+    ) -> Result<Self, MathError> {
+        let not_a_function = || MathError::InvalidArgument {
+            span: ast.span,
+            message: format!(
+                "expected a function name or a lambda like \"x -> x^2\" but found \"{ast}\""
+            ),
+        };
+
+        match &ast.kind {
+            AstKind::Grouping(inner) => {
+                Self::resolve_in_scope(inner, math_definitions, is_variable)
+            }
+            AstKind::Lambda { arg_names, body } => Ok(Self::Defined(DefinedFunction::anonymous(
+                arg_names.clone(),
+                (**body).clone(),
+            )?)),
+            AstKind::Identity(identity) if !is_variable(identity) => {
+                match math_definitions.get(identity.as_str()) {
+                    Some(MathDefinition::BuiltInFunction(function)) => Ok(Self::BuiltIn {
+                        identity: identity.clone(),
+                        function: function.clone(),
+                    }),
+                    Some(MathDefinition::DefinedFunction(function)) => {
+                        Ok(Self::Defined(function.clone()))
+                    }
+                    Some(_) => Err(not_a_function()),
+                    None => Err(MathError::UnknownIdentifier {
+                        span: ast.span,
+                        identifier: identity.clone(),
+                    }),
+                }
+            }
+            _ => Err(not_a_function()),
+        }
+    }
+
+    /// the number of arguments the function takes, or none if it takes any number of them.
+    pub fn arg_count(&self) -> Option<usize> {
+        match self {
+            Self::BuiltIn { function, .. } => function.arg_count,
+            Self::Defined(function) => Some(function.arg_names.len()),
+        }
+    }
+
+    /// this will build the tree of the function invoked with the trees of its arguments, a lambda is applied by
+    /// substituting the arguments into its body.
+    pub fn apply_ast(&self, args: Vec<Ast>, span: Option<Span>) -> Ast {
+        match self {
+            Self::BuiltIn { identity, .. }
+            | Self::Defined(DefinedFunction {
+                signature: Some(identity),
+                ..
+            }) => Ast::new(
+                AstKind::Call {
+                    identity: identity.clone(),
+                    args,
+                },
+                span,
+            ),
+            Self::Defined(function) => {
+                let replacements = function
+                    .arg_names
+                    .iter()
+                    .map(String::as_str)
+                    .zip(&args)
+                    .collect::<HashMap<&str, &Ast>>();
+                substitute(&function.expression, &replacements)
+            }
+        }
+    }
+
+    /// this will turn the function into a defined function with a name, so it can be defined under that name.
+    /// a built in function that takes any number of arguments can not be, it has no argument names.
+    pub fn into_defined_function(self, name: &str) -> Result<DefinedFunction, MathError> {
+        match self {
+            Self::Defined(function) => Ok(DefinedFunction {
+                signature: Some(name.to_string()),
+                ..function
+            }),
+            Self::BuiltIn {
+                identity,
+                function: BuiltInFunction {
+                    arg_count: Some(arg_count),
+                    ..
+                },
+            } => {
+                let arg_names = match arg_count {
+                    1 => vec!["x".to_string()],
+                    _ => (1..=arg_count).map(|index| format!("x{index}")).collect(),
+                };
+                let args = arg_names.iter().map(|arg_name| Ast::identity(arg_name)).collect();
+                Ok(DefinedFunction {
+                    signature: Some(name.to_string()),
+                    arg_names,
+                    expression: Ast::call(&identity, args),
+                })
+            }
+            Self::BuiltIn { identity, .. } => Err(MathError::InvalidDefinition {
+                span: None,
+                message: format!("{identity:?} takes any number of arguments, so it can not be given another name"),
+            }),
+        }
+    }
+
+    pub fn evaluate(
+        &self,
+        args: &[f64],
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<f64, MathError> {
+        match self {
+            Self::BuiltIn { function, .. } => function.evaluate(args, math_definitions),
+            Self::Defined(function) => function.evaluate(args, math_definitions),
+        }
+    }
+
+    pub fn evaluate_dual(
+        &self,
+        args: &[Dual],
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<Dual, MathError> {
+        match self {
+            Self::BuiltIn { identity, function } => {
+                function.evaluate_dual(identity, args, math_definitions)
+            }
+            Self::Defined(function) => function.evaluate_dual(args, math_definitions),
+        }
+    }
+}
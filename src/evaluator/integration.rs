@@ -0,0 +1,209 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// integrals are estimated to within this error unless another tolerance is given.
+pub const DEFAULT_INTEGRATION_TOLERANCE: f64 = 1e-10;
+
+/// the most intervals the integration range will be split into before giving up.
+pub const MAX_INTEGRATION_INTERVALS: usize = 2000;
+
+/// the most terms a sum or product will add up, so a typo like "sum(f, 1, 1e12)" does not hang.
+pub const MAX_SERIES_TERMS: f64 = 1e7;
+
+/// the nodes of the 15 point kronrod rule on [-1, 1], every other node from the second is a node of the 7 point
+/// gauss rule. only the non negative half is listed, the rules are symmetric.
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0f64,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_2,
+    0.140_653_259_715_525_9,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_8,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+/// a part of the integration range with the kronrod estimate of its integral and the error of that estimate.
+#[derive(Clone, Copy, Debug)]
+struct Subinterval {
+    start: f64,
+    end: f64,
+    integral: f64,
+    error: f64,
+}
+impl Subinterval {
+    /// this will estimate the integral over the subinterval with the 15 point kronrod rule, its error is how far the
+    /// embedded 7 point gauss rule is from it.
+    fn estimate(
+        f: &impl Fn(f64) -> Result<f64, MathError>,
+        start: f64,
+        end: f64,
+    ) -> Result<Self, MathError> {
+        let center = (start + end) / 2f64;
+        let half_length = (end - start) / 2f64;
+        let f = |x: f64| match f(x)? {
+            y if y.is_finite() => Ok(y),
+            y => Err(MathError::Domain {
+                span: None,
+                message: format!("the integrand is {y} at {x:?}"),
+            }),
+        };
+
+        let mut kronrod = 0f64;
+        let mut gauss = 0f64;
+        for (index, (node, weight)) in KRONROD_NODES.iter().zip(KRONROD_WEIGHTS).enumerate() {
+            let sum = if *node == 0f64 {
+                f(center)?
+            } else {
+                f(center - half_length * node)? + f(center + half_length * node)?
+            };
+            kronrod += weight * sum;
+            if index % 2 == 1 {
+                gauss += GAUSS_WEIGHTS[index / 2] * sum;
+            }
+        }
+
+        Ok(Self {
+            start,
+            end,
+            integral: kronrod * half_length,
+            error: ((kronrod - gauss) * half_length).abs(),
+        })
+    }
+}
+
+/// this will integrate a function from a to b with adaptive gauss kronrod quadrature, splitting the interval with
+/// the largest error until the estimated error is within the tolerance, absolute or relative to the integral.
+pub fn integrate(
+    f: impl Fn(f64) -> Result<f64, MathError>,
+    a: f64,
+    b: f64,
+    tolerance: f64,
+) -> Result<f64, MathError> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(MathError::InvalidArgument {
+            span: None,
+            message: format!("the bounds of integration [{a:?}, {b:?}] must be finite"),
+        });
+    }
+    if tolerance.is_nan() || tolerance <= 0f64 {
+        return Err(MathError::InvalidArgument {
+            span: None,
+            message: format!("the tolerance must be positive, not {tolerance:?}"),
+        });
+    }
+    if a == b {
+        return Ok(0f64);
+    }
+    if a > b {
+        return integrate(f, b, a, tolerance).map(|integral| -integral);
+    }
+
+    let mut subintervals = vec![Subinterval::estimate(&f, a, b)?];
+    loop {
+        let integral = subintervals
+            .iter()
+            .map(|subinterval| subinterval.integral)
+            .sum::<f64>();
+        let error = subintervals.iter().map(|subinterval| subinterval.error).sum::<f64>();
+        if error <= tolerance.max(tolerance * integral.abs()) {
+            return Ok(integral);
+        }
+        if subintervals.len() >= MAX_INTEGRATION_INTERVALS {
+            return Err(MathError::NotConverged {
+                span: None,
+                message: format!(
+                    "the integral from {a:?} to {b:?} still had an error of {error:?} after splitting it into \
+                     {MAX_INTEGRATION_INTERVALS} intervals"
+                ),
+            });
+        }
+
+        let (index, worst) = subintervals
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|(_, first), (_, second)| first.error.total_cmp(&second.error))
+            .expect("there is always at least one subinterval");
+        let middle = (worst.start + worst.end) / 2f64;
+        subintervals[index] = Subinterval::estimate(&f, worst.start, middle)?;
+        subintervals.push(Subinterval::estimate(&f, middle, worst.end)?);
+    }
+}
+
+/// this will check the bounds of a sum or product, giving the number of terms between them.
+pub fn series_terms(from: f64, to: f64) -> Result<f64, MathError> {
+    if from.fract() != 0f64 || to.fract() != 0f64 {
+        return Err(MathError::InvalidArgument {
+            span: None,
+            message: format!(
+                "the bounds of a sum or product must be integers, not {from} and {to}"
+            ),
+        });
+    }
+    let terms = (to - from + 1f64).max(0f64);
+    if terms > MAX_SERIES_TERMS {
+        return Err(MathError::InvalidArgument {
+            span: None,
+            message: format!(
+                "{terms} terms is more than the {MAX_SERIES_TERMS} a sum or product can have"
+            ),
+        });
+    }
+    Ok(terms)
+}
+
+/// this will add up f(k) for every integer k from one bound to the other, including both. the sum is zero if the
+/// upper bound is below the lower bound.
+pub fn series_sum(
+    f: impl Fn(f64) -> Result<f64, MathError>,
+    from: f64,
+    to: f64,
+) -> Result<f64, MathError> {
+    let terms = series_terms(from, to)?;
+    (0..terms as usize).try_fold(0f64, |sum, k| Ok(sum + f(from + k as f64)?))
+}
+
+/// this will multiply f(k) for every integer k from one bound to the other, including both. the product is one if
+/// the upper bound is below the lower bound.
+pub fn series_product(
+    f: impl Fn(f64) -> Result<f64, MathError>,
+    from: f64,
+    to: f64,
+) -> Result<f64, MathError> {
+    let terms = series_terms(from, to)?;
+    (0..terms as usize).try_fold(1f64, |product, k| Ok(product * f(from + k as f64)?))
+}
+
+impl DefinedFunction {
+    /// this will integrate a function of one variable from a to b to within the default tolerance.
+    pub fn integrate(
+        &self,
+        a: f64,
+        b: f64,
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<f64, MathError> {
+        integrate(
+            |x| self.evaluate(&[x], math_definitions),
+            a,
+            b,
+            DEFAULT_INTEGRATION_TOLERANCE,
+        )
+    }
+}
@@ -0,0 +1,644 @@
+use crate::*;
+use num_rational::BigRational;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use std::fmt;
+
+/// the name of the built in function an interval literal like "[1.9, 2.1]" or "x ± dx" invokes.
+pub const INTERVAL: &str = "interval";
+
+/// the largest magnitude below which every integer is a float, so an integer result below it is exact.
+const MAX_EXACT_INTEGER: f64 = 9007199254740992f64;
+
+/// the error of an interval like "[1, 2]" or "2 ± 0.1" evaluated over any other kind of number, which can not hold
+/// it. standing for a single number in the interval would drop the bounds the interval guarantees.
+pub fn unsupported_interval(backend: &str) -> MathError {
+    MathError::Unsupported {
+        span: None,
+        name: INTERVAL.to_string(),
+        backend: backend.to_string(),
+    }
+}
+
+fn reversed_bounds(lower: impl fmt::Display, upper: impl fmt::Display) -> MathError {
+    MathError::Domain {
+        span: None,
+        message: format!("the lower bound {lower} of an interval is above its upper bound {upper}"),
+    }
+}
+
+// each bound is rounded outwards, the lower bound down and the upper bound up. the rounding error of an addition,
+// multiplication, division and square root is found exactly, so a bound is only moved to the next float when the
+// exact result is past it.
+
+/// the error of a float addition, the exact sum is the sum plus the error.
+fn sum_error(a: f64, b: f64, sum: f64) -> f64 {
+    let b_virtual = sum - a;
+    (a - (sum - b_virtual)) + (b - b_virtual)
+}
+
+/// this will round a result down given the sign of its error, a finite result that overflowed is the largest float.
+fn down(result: f64, error: f64, operands_are_finite: bool) -> f64 {
+    match result {
+        f64::INFINITY if operands_are_finite => f64::MAX,
+        result if error < 0f64 => result.next_down(),
+        result => result,
+    }
+}
+
+/// this will round a result up given the sign of its error, a finite result that overflowed is the lowest float.
+fn up(result: f64, error: f64, operands_are_finite: bool) -> f64 {
+    match result {
+        f64::NEG_INFINITY if operands_are_finite => f64::MIN,
+        result if error > 0f64 => result.next_up(),
+        result => result,
+    }
+}
+
+fn add_down(a: f64, b: f64) -> f64 {
+    let sum = a + b;
+    down(sum, sum_error(a, b, sum), a.is_finite() && b.is_finite())
+}
+
+fn add_up(a: f64, b: f64) -> f64 {
+    let sum = a + b;
+    up(sum, sum_error(a, b, sum), a.is_finite() && b.is_finite())
+}
+
+/// a product with a zero is zero even if the other factor is infinite, an infinite bound is only a limit.
+fn mul_down(a: f64, b: f64) -> f64 {
+    if a == 0f64 || b == 0f64 {
+        return 0f64;
+    }
+    let product = a * b;
+    down(
+        product,
+        a.mul_add(b, -product),
+        a.is_finite() && b.is_finite(),
+    )
+}
+
+fn mul_up(a: f64, b: f64) -> f64 {
+    if a == 0f64 || b == 0f64 {
+        return 0f64;
+    }
+    let product = a * b;
+    up(
+        product,
+        a.mul_add(b, -product),
+        a.is_finite() && b.is_finite(),
+    )
+}
+
+/// the error of a float division has the sign of the remainder over the divisor.
+fn quotient_error(a: f64, b: f64, quotient: f64) -> f64 {
+    (-quotient).mul_add(b, a) * b.signum()
+}
+
+fn div_down(a: f64, b: f64) -> f64 {
+    if a == 0f64 || b.is_infinite() {
+        return 0f64;
+    }
+    let quotient = a / b;
+    down(quotient, quotient_error(a, b, quotient), a.is_finite())
+}
+
+fn div_up(a: f64, b: f64) -> f64 {
+    if a == 0f64 || b.is_infinite() {
+        return 0f64;
+    }
+    let quotient = a / b;
+    up(quotient, quotient_error(a, b, quotient), a.is_finite())
+}
+
+/// the bound of a non negative number to a natural power, by squaring with the bounds rounded the same way.
+fn pow_bound(x: f64, mut n: u32, mul: fn(f64, f64) -> f64) -> f64 {
+    let (mut result, mut base) = (1f64, x);
+    while n > 0 {
+        if n & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        n >>= 1;
+    }
+    result
+}
+
+/// a closed interval of the real numbers from a lower to an upper bound, either of which may be infinite. every
+/// operation gives an interval that is guaranteed to contain every value it could have for the values in its
+/// operands, like "[1.9, 2.1] * 3" which contains every number from 5.7 to 6.3.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    pub lower: f64,
+    pub upper: f64,
+}
+impl Interval {
+    pub const ENTIRE: Self = Self {
+        lower: f64::NEG_INFINITY,
+        upper: f64::INFINITY,
+    };
+
+    /// an interval between two bounds, erroring if the lower bound is above the upper bound or either bound is NaN.
+    pub fn new(lower: f64, upper: f64) -> Result<Self, MathError> {
+        if lower.is_nan() || upper.is_nan() {
+            return Err(MathError::Domain {
+                span: None,
+                message: "an interval can not have a bound that is not a number".to_string(),
+            });
+        }
+        if lower > upper {
+            return Err(reversed_bounds(lower, upper));
+        }
+        if lower == f64::INFINITY || upper == f64::NEG_INFINITY {
+            return Err(MathError::Domain {
+                span: None,
+                message: "an interval can not only hold an infinity".to_string(),
+            });
+        }
+        Ok(Self { lower, upper })
+    }
+
+    pub fn point(num: f64) -> Self {
+        Self {
+            lower: num,
+            upper: num,
+        }
+    }
+
+    /// the interval from the float below a number to the float above it, which contains a number only known to
+    /// within a float of it.
+    pub fn around(num: f64) -> Self {
+        Self {
+            lower: num.next_down(),
+            upper: num.next_up(),
+        }
+    }
+
+    pub fn is_point(&self) -> bool {
+        self.lower == self.upper
+    }
+
+    pub fn contains(&self, num: f64) -> bool {
+        self.lower <= num && num <= self.upper
+    }
+
+    pub fn width(&self) -> f64 {
+        self.upper - self.lower
+    }
+
+    pub fn midpoint(&self) -> f64 {
+        self.lower / 2f64 + self.upper / 2f64
+    }
+
+    /// this will get the only number in the interval, erroring if it holds more than one.
+    pub fn as_point(&self) -> Result<f64, MathError> {
+        if self.is_point() {
+            Ok(self.lower)
+        } else {
+            Err(MathError::Domain {
+                span: None,
+                message: format!("expected a number but found the interval {self}"),
+            })
+        }
+    }
+
+    /// this will round the value of a library function outwards, which is accurate to within a float.
+    fn widened(lower: f64, upper: f64) -> Self {
+        Self {
+            lower: lower.next_down(),
+            upper: upper.next_up(),
+        }
+    }
+
+    /// this will clamp the bounds to the range of a function, which the outward rounding can leave.
+    fn clamped(self, lower: f64, upper: f64) -> Self {
+        Self {
+            lower: self.lower.clamp(lower, upper),
+            upper: self.upper.clamp(lower, upper),
+        }
+    }
+
+    fn increasing(&self, f: fn(f64) -> f64) -> Self {
+        Self::widened(f(self.lower), f(self.upper))
+    }
+
+    /// this will restrict the interval to the numbers a function is defined for, erroring if there are none.
+    fn restricted(&self, identity: &str, lower: f64) -> Result<Self, MathError> {
+        if self.upper < lower {
+            return Err(MathError::Domain {
+                span: None,
+                message: format!("{identity} is not defined anywhere in {self}"),
+            });
+        }
+        Ok(Self {
+            lower: self.lower.max(lower),
+            upper: self.upper,
+        })
+    }
+
+    /// a number a periodic function peaks at is in the interval, when it is close enough to count either way it
+    /// is counted so the result only gets wider.
+    fn contains_phase(&self, phase: f64, period: f64) -> bool {
+        let margin = (self.lower.abs() + self.upper.abs() + 1f64) * 1e-15;
+        let k = ((self.lower - phase) / period).floor();
+        [k, k + 1f64]
+            .iter()
+            .map(|k| phase + k * period)
+            .any(|peak| self.lower - margin <= peak && peak <= self.upper + margin)
+    }
+
+    /// a sine or cosine, given the phases of its maximum and minimum.
+    fn periodic(&self, f: fn(f64) -> f64, maximum: f64, minimum: f64) -> Self {
+        if self.width() >= TAU || !self.width().is_finite() {
+            return Self::new(-1f64, 1f64).unwrap();
+        }
+        let (a, b) = (f(self.lower), f(self.upper));
+        let result = Self::widened(a.min(b), a.max(b));
+        Self {
+            lower: match self.contains_phase(minimum, TAU) {
+                true => -1f64,
+                false => result.lower,
+            },
+            upper: match self.contains_phase(maximum, TAU) {
+                true => 1f64,
+                false => result.upper,
+            },
+        }
+        .clamped(-1f64, 1f64)
+    }
+
+    pub fn sin(&self) -> Self {
+        self.periodic(f64::sin, FRAC_PI_2, -FRAC_PI_2)
+    }
+
+    pub fn cos(&self) -> Self {
+        self.periodic(f64::cos, 0f64, PI)
+    }
+
+    /// a tangent is the entire line if the interval has a pole of it.
+    pub fn tan(&self) -> Self {
+        if self.width() >= PI || !self.width().is_finite() || self.contains_phase(FRAC_PI_2, PI) {
+            return Self::ENTIRE;
+        }
+        self.increasing(f64::tan)
+    }
+
+    pub fn cosh(&self) -> Self {
+        let (a, b) = (self.lower.cosh(), self.upper.cosh());
+        match self.contains(0f64) {
+            true => Self::widened(1f64, a.max(b)),
+            false => Self::widened(a.min(b), a.max(b)),
+        }
+        .clamped(1f64, f64::INFINITY)
+    }
+
+    pub fn exp(&self) -> Self {
+        self.increasing(f64::exp).clamped(0f64, f64::INFINITY)
+    }
+
+    pub fn ln(&self) -> Result<Self, MathError> {
+        let interval = self.restricted("ln", 0f64)?;
+        if interval.upper == 0f64 {
+            return Err(MathError::Domain {
+                span: None,
+                message: format!("ln is not defined anywhere in {self}"),
+            });
+        }
+        Ok(interval.increasing(f64::ln))
+    }
+
+    pub fn sqrt(&self) -> Result<Self, MathError> {
+        let interval = self.restricted("sqrt", 0f64)?;
+        let root = |x: f64, round: fn(f64, f64, bool) -> f64| {
+            let root = x.sqrt();
+            round(root, (-root).mul_add(root, x), true)
+        };
+        Ok(Self {
+            lower: root(interval.lower, down),
+            upper: root(interval.upper, up),
+        })
+    }
+
+    pub fn abs(&self) -> Self {
+        match (self.lower >= 0f64, self.upper <= 0f64) {
+            (true, _) => *self,
+            (_, true) => Self::point(0f64).sub(self),
+            _ => Self {
+                lower: 0f64,
+                upper: self.upper.max(-self.lower),
+            },
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self {
+            lower: add_down(self.lower, other.lower),
+            upper: add_up(self.upper, other.upper),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        Self {
+            lower: add_down(self.lower, -other.upper),
+            upper: add_up(self.upper, -other.lower),
+        }
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let bounds = [
+            (self.lower, other.lower),
+            (self.lower, other.upper),
+            (self.upper, other.lower),
+            (self.upper, other.upper),
+        ];
+        Self {
+            lower: bounds
+                .iter()
+                .map(|(a, b)| mul_down(*a, *b))
+                .fold(f64::INFINITY, f64::min),
+            upper: bounds
+                .iter()
+                .map(|(a, b)| mul_up(*a, *b))
+                .fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    /// a divisor that holds zero only at a bound gives a quotient unbounded on one side, one that holds zero inside
+    /// gives two unbounded pieces whose smallest enclosure is the entire line.
+    pub fn div(&self, other: &Self) -> Result<Self, MathError> {
+        let entire = Self::ENTIRE;
+        let (a, b, c, d) = (self.lower, self.upper, other.lower, other.upper);
+        if !other.contains(0f64) {
+            let bounds = [(a, c), (a, d), (b, c), (b, d)];
+            return Ok(Self {
+                lower: bounds
+                    .iter()
+                    .map(|(x, y)| div_down(*x, *y))
+                    .fold(f64::INFINITY, f64::min),
+                upper: bounds
+                    .iter()
+                    .map(|(x, y)| div_up(*x, *y))
+                    .fold(f64::NEG_INFINITY, f64::max),
+            });
+        }
+        if other.is_point() {
+            return Err(MathError::Domain {
+                span: None,
+                message: "division by zero".to_string(),
+            });
+        }
+
+        Ok(match (c == 0f64, d == 0f64) {
+            _ if self.contains(0f64) => entire,
+            (true, _) if b < 0f64 => Self::new(f64::NEG_INFINITY, div_up(b, d))?,
+            (true, _) => Self::new(div_down(a, d), f64::INFINITY)?,
+            (_, true) if b < 0f64 => Self::new(div_down(b, c), f64::INFINITY)?,
+            (_, true) => Self::new(f64::NEG_INFINITY, div_up(a, c))?,
+            _ => entire,
+        })
+    }
+
+    /// an integer power takes the extremes of the interval, and an even power of an interval holding zero is
+    /// smallest at zero.
+    fn powi(&self, n: i32) -> Result<Self, MathError> {
+        let magnitude = n.unsigned_abs();
+        let pow_down = |x: f64| match x < 0f64 {
+            true if magnitude % 2 == 1 => -pow_bound(-x, magnitude, mul_up),
+            true => pow_bound(-x, magnitude, mul_down),
+            false => pow_bound(x, magnitude, mul_down),
+        };
+        let pow_up = |x: f64| match x < 0f64 {
+            true if magnitude % 2 == 1 => -pow_bound(-x, magnitude, mul_down),
+            true => pow_bound(-x, magnitude, mul_up),
+            false => pow_bound(x, magnitude, mul_up),
+        };
+
+        let power = match magnitude.is_multiple_of(2) {
+            true if self.contains(0f64) => Self {
+                lower: 0f64,
+                upper: pow_up(self.lower).max(pow_up(self.upper)),
+            },
+            true if self.upper < 0f64 => Self {
+                lower: pow_down(self.upper),
+                upper: pow_up(self.lower),
+            },
+            _ => Self {
+                lower: pow_down(self.lower),
+                upper: pow_up(self.upper),
+            },
+        };
+        match n < 0 {
+            true => Self::point(1f64).div(&power),
+            false => Ok(power),
+        }
+    }
+
+    /// a power with an exponent that is not an integer is only defined for a base that is not negative.
+    pub fn pow(&self, exponent: &Self) -> Result<Self, MathError> {
+        if let Some(n) = Some(exponent.lower)
+            .filter(|n| exponent.is_point() && n.fract() == 0f64 && n.abs() <= i32::MAX as f64)
+        {
+            return self.powi(n as i32);
+        }
+
+        let base = self.restricted("a power that is not an integer", 0f64)?;
+        if base.upper == 0f64 {
+            return match exponent.lower > 0f64 {
+                true => Ok(Self::point(0f64)),
+                false => Err(MathError::Domain {
+                    span: None,
+                    message: format!("0 to the power of {exponent} is not defined"),
+                }),
+            };
+        }
+        Ok(exponent.mul(&base.ln()?).exp())
+    }
+
+    /// this will round an integer result, like a factorial, outwards only if it is too large to be exact.
+    fn exact_or_around(num: f64) -> Self {
+        match num.fract() == 0f64 && num.abs() < MAX_EXACT_INTEGER {
+            true => Self::point(num),
+            false => Self::around(num),
+        }
+    }
+}
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_point() {
+            write!(f, "{:?}", self.lower)
+        } else {
+            write!(f, "[{:?}, {:?}]", self.lower, self.upper)
+        }
+    }
+}
+
+/// intervals, the trigonometric, hyperbolic, logarithmic, root and power built in functions have interval versions
+/// and the special functions like gamma are not supported.
+impl Numeric for Interval {
+    const NAME: &'static str = "interval";
+
+    /// a literal is the interval from the float to the float next to it on the side of the decimal it was written
+    /// as, unless the float is exactly that decimal.
+    fn from_f64(num: f64) -> Result<Self, MathError> {
+        if !num.is_finite() {
+            return Err(MathError::Domain {
+                span: None,
+                message: format!("{num} is not a number an interval can hold"),
+            });
+        }
+        let written = Rational::from_f64(num)?;
+        let exact = BigRational::from_float(num).map(Rational::Exact);
+        Ok(match exact.map(|exact| written.partial_compare(&exact)) {
+            Some(Ok(Some(Ordering::Less))) => Self::new(num.next_down(), num)?,
+            Some(Ok(Some(Ordering::Greater))) => Self::new(num, num.next_up())?,
+            _ => Self::point(num),
+        })
+    }
+
+    /// an irrational constant like pi is only known to within a float.
+    fn from_constant(_identity: &str, num: f64) -> Result<Self, MathError> {
+        match num.fract() == 0f64 {
+            true => Ok(Self::point(num)),
+            false => Ok(Self::around(num)),
+        }
+    }
+
+    fn from_numeric_value(value: &NumericValue) -> Option<Self> {
+        match value {
+            NumericValue::Interval(value) => Some(*value),
+            NumericValue::Complex(value) => value.as_real().ok().map(Self::point),
+            _ => value.to_f64().map(Self::exact_or_around),
+        }
+    }
+
+    fn to_f64(&self) -> Result<f64, MathError> {
+        self.as_point()
+    }
+
+    fn to_variable(&self) -> MathDefinition {
+        match self.is_point() {
+            true => MathDefinition::Variable(self.lower),
+            false => MathDefinition::NumericVariable(NumericValue::Interval(*self)),
+        }
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self.add(other))
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self.sub(other))
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self.mul(other))
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, MathError> {
+        self.div(other)
+    }
+
+    /// a remainder is only defined for numbers, since the remainder of an interval can jump anywhere. the remainder
+    /// of floats is exact so it is a number too.
+    fn try_rem(&self, other: &Self) -> Result<Self, MathError> {
+        let remainder = self.as_point()? % other.as_point()?;
+        Self::new(remainder, remainder)
+    }
+
+    fn try_pow(&self, exponent: &Self) -> Result<Self, MathError> {
+        self.pow(exponent)
+    }
+
+    fn try_neg(&self) -> Result<Self, MathError> {
+        Ok(Self {
+            lower: -self.upper,
+            upper: -self.lower,
+        })
+    }
+
+    /// intervals are ordered if one is entirely below the other, the order of intervals that overlap is unknown.
+    fn partial_compare(&self, other: &Self) -> Result<Option<Ordering>, MathError> {
+        if self.upper < other.lower {
+            Ok(Some(Ordering::Less))
+        } else if self.lower > other.upper {
+            Ok(Some(Ordering::Greater))
+        } else if self.is_point() && self == other {
+            Ok(Some(Ordering::Equal))
+        } else {
+            Err(MathError::Domain {
+                span: None,
+                message: format!("the order of {self} and {other} is unknown since they overlap"),
+            })
+        }
+    }
+
+    fn factorial(&self) -> Result<Self, MathError> {
+        Ok(Self::exact_or_around(factorial(self.as_point()?)?))
+    }
+
+    fn double_factorial(&self) -> Result<Self, MathError> {
+        Ok(Self::exact_or_around(double_factorial(self.as_point()?)?))
+    }
+
+    fn supports_built_in(identity: &str) -> bool {
+        !matches!(identity, "gamma" | "lgamma" | "polygamma")
+    }
+
+    fn evaluate_built_in(
+        identity: &str,
+        _function: &BuiltInFunction,
+        args: &[Self],
+    ) -> Result<Self, MathError> {
+        let x = args[0];
+        let divide = |numerator: Self, denominator: Self| numerator.div(&denominator);
+        let one = Self::point(1f64);
+        match identity {
+            "sqrt" => x.sqrt(),
+            "cbrt" => Ok(x.increasing(f64::cbrt)),
+            "pow" => x.pow(&args[1]),
+            "abs" => Ok(x.abs()),
+            "sin" | "sine" => Ok(x.sin()),
+            "cos" | "cosine" => Ok(x.cos()),
+            "tan" | "tangent" => Ok(x.tan()),
+            "sec" | "secant" => divide(one, x.cos()),
+            "csc" | "cosecant" => divide(one, x.sin()),
+            "cot" | "cotangent" => divide(one, x.tan()),
+            "sinh" => Ok(x.increasing(f64::sinh)),
+            "cosh" => Ok(x.cosh()),
+            "tanh" => Ok(x.increasing(f64::tanh).clamped(-1f64, 1f64)),
+            "ln" => x.ln(),
+            "log" | "log10" => divide(x.ln()?, Self::around(std::f64::consts::LN_10)),
+            "log2" => divide(x.ln()?, Self::around(std::f64::consts::LN_2)),
+            "factorial" => x.factorial(),
+            "mean" => divide(
+                args.iter().fold(Self::point(0f64), |sum, arg| sum.add(arg)),
+                Self::point(args.len() as f64),
+            ),
+            "re" | "conj" => Ok(x),
+            "im" => Ok(Self::point(0f64)),
+            "arg" if x.lower >= 0f64 => Ok(Self::point(0f64)),
+            "arg" if x.upper < 0f64 => Ok(Self::around(PI)),
+            "arg" => Ok(Self::new(0f64, PI.next_up())?),
+            INTERVAL => Self::new(x.lower, args[1].upper).map_err(|_| reversed_bounds(x, args[1])),
+            _ => Err(MathError::Unsupported {
+                span: None,
+                name: identity.to_string(),
+                backend: Self::NAME.to_string(),
+            }),
+        }
+    }
+}
+
+/// this will evaluate a string math expression or program over intervals, like "[1.9, 2.1] * 3" or "2 ± 0.1".
+pub fn eval_str_interval(string: &str) -> Result<Interval, MathError> {
+    eval_str_numeric(string)
+}
+
+/// this will evaluate a syntax tree over intervals.
+pub fn eval_ast_interval(
+    ast: &Ast,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Interval, MathError> {
+    eval_ast_numeric(ast, math_definitions)
+}
@@ -0,0 +1,23 @@
+use crate::*;
+
+/// comparisons and logical operators give 1 for true and 0 for false.
+pub fn boolean_value(boolean: bool) -> f64 {
+    if boolean {
+        1f64
+    } else {
+        0f64
+    }
+}
+
+/// this will read a number as a boolean for a condition or a logical operator. only 1 and 0 are booleans, so a
+/// condition like "if(x, a, b)" errors rather than treating every other number as true.
+pub fn as_boolean(num: f64) -> Result<bool, MathError> {
+    match num {
+        1f64 => Ok(true),
+        0f64 => Ok(false),
+        _ => Err(MathError::InvalidArgument {
+            span: None,
+            message: format!("expected a boolean, 1 for true or 0 for false, but found {num}"),
+        }),
+    }
+}
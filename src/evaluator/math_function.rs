@@ -1,6 +1,6 @@
 use std::collections::HashMap;
-use crate::MathDefinition;
+use crate::{MathDefinition, MathError};
 
 pub trait MathFunction {
-    fn evaluate(&self, args : &[f64], math_definitions : &HashMap<&str, MathDefinition>) -> Result<f64, String>;
+    fn evaluate(&self, args : &[f64], math_definitions : &HashMap<String, MathDefinition>) -> Result<f64, MathError>;
 }
\ No newline at end of file
@@ -1,28 +1,85 @@
-use crate::{DefinedFunction, BuiltInFunction, get_built_in_constants_map};
+use crate::{DefinedFunction, BuiltInFunction, SpecialForm, Complex, NumericValue, get_built_in_constants_map};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub enum MathDefinition {
     Constant(f64),
+    /// a value set at runtime, unlike a constant it can be reassigned or removed.
+    Variable(f64),
+    /// a constant that is only a value when evaluating complex numbers, like i.
+    ComplexConstant(Complex),
+    /// a variable set while evaluating over a backend to a number a real variable can not hold, like a complex number
+    /// with an imaginary part or an exact fraction.
+    NumericVariable(NumericValue),
     BuiltInFunction(BuiltInFunction),
-    DefinedFunction(DefinedFunction)
+    DefinedFunction(DefinedFunction),
+    SpecialForm(SpecialForm),
 } 
 impl MathDefinition {
-    pub fn default_math_definitions<'a>() -> HashMap<&'a str, MathDefinition> {
-        let mut math_state = HashMap::new();
-        math_state.extend(BuiltInFunction::get_built_in_functions_map());
-        math_state.extend(get_built_in_constants_map());
-        math_state
+    pub fn default_math_definitions() -> HashMap<String, MathDefinition> {
+        BuiltInFunction::get_built_in_functions_map()
+            .into_iter()
+            .chain(SpecialForm::get_special_forms_map())
+            .chain(get_built_in_constants_map())
+            .map(|(identity, math_definition)| (identity.to_string(), math_definition))
+            .collect()
     }
 
     pub fn is_constant(&self) -> bool {
-        if let Self::Constant(_) = self {
-            true
-        } else {
-            false
+        matches!(self, Self::Constant(_) | Self::ComplexConstant(_))
+    }
+
+    pub fn is_variable(&self) -> bool {
+        matches!(self, Self::Variable(_) | Self::NumericVariable(_))
+    }
+
+    /// this will get the value of a constant or a variable, the value of a variable set over another backend is the
+    /// nearest real number.
+    pub fn get_value(&self) -> Option<f64> {
+        match self {
+            Self::Constant(value) | Self::Variable(value) => Some(*value),
+            Self::NumericVariable(value) => value.to_f64(),
+            _ => None,
+        }
+    }
+
+    /// this will get the value of any constant or variable as a complex number.
+    pub fn get_complex_value(&self) -> Option<Complex> {
+        match self {
+            Self::Constant(value) | Self::Variable(value) => Some(Complex::real(*value)),
+            Self::ComplexConstant(value) | Self::NumericVariable(NumericValue::Complex(value)) => Some(*value),
+            Self::NumericVariable(value) => value.to_f64().map(Complex::real),
+            _ => None,
         }
     }
 
+    /// the definition of a variable set to a complex number, a number without an imaginary part is a real variable.
+    pub fn complex_variable(value: Complex) -> Self {
+        match value.is_real() {
+            true => Self::Variable(value.re),
+            false => Self::NumericVariable(NumericValue::Complex(value)),
+        }
+    }
+
+    /// this will tell if the definition is one of the defaults rather than one set at runtime.
+    pub fn is_built_in(&self) -> bool {
+        matches!(
+            self,
+            Self::Constant(_)
+                | Self::ComplexConstant(_)
+                | Self::BuiltInFunction(_)
+                | Self::SpecialForm(_)
+        )
+    }
+
+    /// this will tell if the definition can be invoked.
+    pub fn is_function(&self) -> bool {
+        matches!(
+            self,
+            Self::BuiltInFunction(_) | Self::DefinedFunction(_) | Self::SpecialForm(_)
+        )
+    }
+
     pub fn get_constant(&self) -> Option<f64> {
         if let Self::Constant(constant) = self {
             Some(*constant)
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod evaluator;
 pub use evaluator::*;
 
@@ -13,5 +14,56 @@ pub use math_function::*;
 pub mod math_state;
 pub use math_state::*;
 
+pub mod numeric;
+pub use numeric::*;
+
+pub mod special_functions;
+pub use special_functions::*;
+
+pub mod logic;
+pub use logic::*;
+
+pub mod recursion;
+pub use recursion::*;
+
+pub mod special_forms;
+pub use special_forms::*;
+
+pub mod derivative;
+pub use derivative::*;
+
+pub mod simplify;
+pub use simplify::*;
+
+pub mod dual;
+pub use dual::*;
+
+pub mod complex;
+pub use complex::*;
+
+pub mod rational;
+pub use rational::*;
+
+pub mod decimal;
+pub use decimal::*;
+
+pub mod interval;
+pub use interval::*;
+
+pub mod function_value;
+pub use function_value::*;
+
+pub mod root_finding;
+pub use root_finding::*;
+
+pub mod integration;
+pub use integration::*;
+
+pub mod compiled_expression;
+pub use compiled_expression::*;
+
+pub mod context;
+pub use context::*;
+
 // unit testing modules
 pub mod test;
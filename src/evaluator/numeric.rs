@@ -0,0 +1,333 @@
+use crate::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+/// a kind of number expressions can be evaluated over, so the same syntax tree can be evaluated as f64, f32, complex
+/// numbers or any other backend. each backend has its own arithmetic and declares which built in functions it
+/// supports, invoking any other built in function errors.
+pub trait Numeric: Clone + fmt::Debug + fmt::Display + Sized {
+    /// the name of the backend, used in the errors of what it does not support.
+    const NAME: &'static str;
+
+    /// the number a real constant or variable, or a number built rather than parsed, stands for.
+    fn from_f64(num: f64) -> Result<Self, MathError>;
+
+    /// the number a literal like "0.1" stands for, by default the nearest f64 to it.
+    fn from_literal(_literal: &str, num: f64) -> Result<Self, MathError> {
+        Self::from_f64(num)
+    }
+
+    /// the number a built in constant stands for, which unlike a literal may be irrational like pi.
+    fn from_constant(_identity: &str, num: f64) -> Result<Self, MathError> {
+        Self::from_f64(num)
+    }
+
+    /// the number a complex constant stands for, only a backend with complex numbers has one.
+    fn from_complex(_num: Complex) -> Option<Self> {
+        None
+    }
+
+    /// the number a variable set while evaluating over another backend stands for. by default it is the nearest real
+    /// number, a value without one like a complex number with an imaginary part has none.
+    fn from_numeric_value(value: &NumericValue) -> Option<Self> {
+        value.to_f64().and_then(|num| Self::from_f64(num).ok())
+    }
+
+    /// this will get the number as a real number, for what only a real number makes sense for like a condition or
+    /// the bounds of a sum.
+    fn to_f64(&self) -> Result<f64, MathError>;
+
+    /// the definition of a variable assigned the number, which keeps it for later evaluations.
+    fn to_variable(&self) -> MathDefinition;
+
+    fn try_add(&self, other: &Self) -> Result<Self, MathError>;
+    fn try_sub(&self, other: &Self) -> Result<Self, MathError>;
+    fn try_mul(&self, other: &Self) -> Result<Self, MathError>;
+    fn try_div(&self, other: &Self) -> Result<Self, MathError>;
+    fn try_rem(&self, other: &Self) -> Result<Self, MathError>;
+    fn try_pow(&self, exponent: &Self) -> Result<Self, MathError>;
+    fn try_neg(&self) -> Result<Self, MathError>;
+
+    /// the order of two numbers, none if they are unordered like NaN and any other number.
+    fn partial_compare(&self, other: &Self) -> Result<Option<Ordering>, MathError>;
+
+    fn equals(&self, other: &Self) -> Result<bool, MathError> {
+        Ok(self.partial_compare(other)? == Some(Ordering::Equal))
+    }
+
+    fn factorial(&self) -> Result<Self, MathError> {
+        Self::from_f64(factorial(self.to_f64()?)?)
+    }
+
+    fn double_factorial(&self) -> Result<Self, MathError> {
+        Self::from_f64(double_factorial(self.to_f64()?)?)
+    }
+
+    /// the built in functions the backend can evaluate.
+    fn supports_built_in(identity: &str) -> bool;
+
+    /// this will evaluate a built in function the backend supports, the number of arguments is already checked.
+    fn evaluate_built_in(
+        identity: &str,
+        function: &BuiltInFunction,
+        args: &[Self],
+    ) -> Result<Self, MathError>;
+
+    /// this will evaluate a special form the backend evaluates itself, like the root finding of real numbers.
+    /// otherwise an if, a sum and a product are evaluated over the backend and any other form is expanded first.
+    fn evaluate_special_form(
+        _special_form: SpecialForm,
+        _args: &[Ast],
+        _span: Option<Span>,
+        _math_definitions: &HashMap<String, MathDefinition>,
+        _variables: &HashMap<&str, Self>,
+    ) -> Option<Result<Self, MathError>> {
+        None
+    }
+}
+
+/// a number of a backend that a real variable can not hold, like a complex number or an exact fraction. a variable
+/// keeps it so later evaluations over the same backend get it back as it was.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NumericValue {
+    Complex(Complex),
+    Rational(Rational),
+    Decimal(Decimal),
+    Interval(Interval),
+}
+impl NumericValue {
+    /// the name of the backend the value belongs to.
+    pub fn backend(&self) -> &'static str {
+        match self {
+            Self::Complex(_) => Complex::NAME,
+            Self::Rational(_) => Rational::NAME,
+            Self::Decimal(_) => Decimal::NAME,
+            Self::Interval(_) => Interval::NAME,
+        }
+    }
+
+    /// the nearest real number, none if it has no real value like a complex number with an imaginary part or an
+    /// interval wider than a single number.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            Self::Complex(value) => value.as_real().ok(),
+            Self::Rational(value) => value.to_f64().ok(),
+            Self::Decimal(value) => value.to_f64().ok(),
+            Self::Interval(value) => Some(value.lower).filter(|_| value.is_point()),
+        }
+    }
+}
+impl fmt::Display for NumericValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Complex(value) => write!(f, "{value}"),
+            Self::Rational(value) => write!(f, "{value}"),
+            Self::Decimal(value) => write!(f, "{value}"),
+            Self::Interval(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// the error of a constant or variable of a backend used while evaluating over a backend that can not hold it.
+pub fn numeric_value_error(identity: &str, backend: &str, span: Option<Span>) -> MathError {
+    MathError::Domain {
+        span,
+        message: format!(
+            "{identity:?} is a {backend} number, it only has a value when evaluating {backend} numbers"
+        ),
+    }
+}
+
+/// the true or false of a comparison or logical operator in a backend.
+fn boolean<N: Numeric>(boolean: bool) -> Result<N, MathError> {
+    N::from_f64(boolean_value(boolean))
+}
+
+impl Operator {
+    pub fn apply_numeric_operation_2_operands<N: Numeric>(
+        &self,
+        num1: &N,
+        num2: &N,
+    ) -> Result<N, MathError> {
+        let compare =
+            |is: fn(Ordering) -> bool| boolean(num1.partial_compare(num2)?.is_some_and(is));
+
+        match self {
+            Self::Addition => num1.try_add(num2),
+            Self::Subtraction => num1.try_sub(num2),
+            Self::Multiplication => num1.try_mul(num2),
+            Self::Division => num1.try_div(num2),
+            Self::Modulus => num1.try_rem(num2),
+            Self::Exponentiation => num1.try_pow(num2),
+            Self::LessThan => compare(Ordering::is_lt),
+            Self::LessThanOrEqual => compare(Ordering::is_le),
+            Self::GreaterThan => compare(Ordering::is_gt),
+            Self::GreaterThanOrEqual => compare(Ordering::is_ge),
+            Self::Equal => boolean(num1.equals(num2)?),
+            Self::NotEqual => boolean(!num1.equals(num2)?),
+            Self::And => boolean(as_boolean(num1.to_f64()?)? && as_boolean(num2.to_f64()?)?),
+            Self::Or => boolean(as_boolean(num1.to_f64()?)? || as_boolean(num2.to_f64()?)?),
+            _ => Err(MathError::InvalidOperator {
+                span: None,
+                operator: *self,
+            }),
+        }
+    }
+
+    pub fn apply_numeric_operation_1_operand<N: Numeric>(&self, num1: &N) -> Result<N, MathError> {
+        match self {
+            Self::Addition => Ok(num1.clone()),
+            Self::Subtraction => num1.try_neg(),
+            Self::Factorial => num1.factorial(),
+            Self::DoubleFactorial => num1.double_factorial(),
+            Self::Not => boolean(!as_boolean(num1.to_f64()?)?),
+            _ => Err(MathError::InvalidOperator {
+                span: None,
+                operator: *self,
+            }),
+        }
+    }
+}
+
+impl Numeric for f64 {
+    const NAME: &'static str = "f64";
+
+    fn from_f64(num: f64) -> Result<Self, MathError> {
+        Ok(num)
+    }
+
+    fn to_f64(&self) -> Result<f64, MathError> {
+        Ok(*self)
+    }
+
+    fn to_variable(&self) -> MathDefinition {
+        MathDefinition::Variable(*self)
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self + other)
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self - other)
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self * other)
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self / other)
+    }
+
+    fn try_rem(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self % other)
+    }
+
+    fn try_pow(&self, exponent: &Self) -> Result<Self, MathError> {
+        Ok(self.powf(*exponent))
+    }
+
+    fn try_neg(&self) -> Result<Self, MathError> {
+        Ok(-self)
+    }
+
+    fn partial_compare(&self, other: &Self) -> Result<Option<Ordering>, MathError> {
+        Ok(self.partial_cmp(other))
+    }
+
+    fn supports_built_in(identity: &str) -> bool {
+        identity != INTERVAL
+    }
+
+    fn evaluate_built_in(
+        _identity: &str,
+        function: &BuiltInFunction,
+        args: &[Self],
+    ) -> Result<Self, MathError> {
+        (function.function)(args)
+    }
+
+    fn evaluate_special_form(
+        special_form: SpecialForm,
+        args: &[Ast],
+        span: Option<Span>,
+        math_definitions: &HashMap<String, MathDefinition>,
+        variables: &HashMap<&str, Self>,
+    ) -> Option<Result<Self, MathError>> {
+        Some(special_form.evaluate(args, span, math_definitions, variables))
+    }
+}
+
+/// single precision numbers, every built in function is evaluated in double precision and rounded to single.
+impl Numeric for f32 {
+    const NAME: &'static str = "f32";
+
+    fn from_f64(num: f64) -> Result<Self, MathError> {
+        Ok(num as f32)
+    }
+
+    fn to_f64(&self) -> Result<f64, MathError> {
+        Ok(*self as f64)
+    }
+
+    fn to_variable(&self) -> MathDefinition {
+        MathDefinition::Variable(*self as f64)
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self + other)
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self - other)
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self * other)
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self / other)
+    }
+
+    fn try_rem(&self, other: &Self) -> Result<Self, MathError> {
+        Ok(self % other)
+    }
+
+    fn try_pow(&self, exponent: &Self) -> Result<Self, MathError> {
+        Ok(self.powf(*exponent))
+    }
+
+    fn try_neg(&self) -> Result<Self, MathError> {
+        Ok(-self)
+    }
+
+    fn partial_compare(&self, other: &Self) -> Result<Option<Ordering>, MathError> {
+        Ok(self.partial_cmp(other))
+    }
+
+    fn supports_built_in(identity: &str) -> bool {
+        identity != INTERVAL
+    }
+
+    fn evaluate_built_in(
+        _identity: &str,
+        function: &BuiltInFunction,
+        args: &[Self],
+    ) -> Result<Self, MathError> {
+        let args = args.iter().map(|arg| *arg as f64).collect::<Vec<f64>>();
+        Ok((function.function)(&args)? as f32)
+    }
+}
+
+/// this will evaluate a string math expression or program over any numeric backend, like
+/// `eval_str_numeric::<f32>("1 / 3")`. decimal results have the precision and rounding of this thread, and rational
+/// results its fallback.
+pub fn eval_str_numeric<N: Numeric>(string: &str) -> Result<N, MathError> {
+    Context::new()
+        .with_decimal_context(decimal_context())
+        .with_rational_fallback(rational_fallback())
+        .eval_numeric(string)
+}
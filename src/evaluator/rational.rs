@@ -0,0 +1,382 @@
+use crate::*;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+/// the largest factorial evaluated exactly by the rational and decimal backends, a larger one is as large as a float
+/// gets anyway.
+pub const MAX_EXACT_BIG_FACTORIAL: u32 = 10_000;
+
+/// the largest power of ten a literal is scaled by exactly, a literal like "1e100000" is as large or small as a float
+/// gets anyway.
+const MAX_EXACT_LITERAL_EXPONENT: u64 = 10_000;
+
+/// what evaluating rational numbers does when a value can not be exact, like "sin(1)" or pi.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RationalFallback {
+    /// the value becomes a float, and so does everything computed from it.
+    #[default]
+    Float,
+    /// evaluation errors.
+    Error,
+}
+
+thread_local! {
+    static RATIONAL_FALLBACK: Cell<RationalFallback> = const { Cell::new(RationalFallback::Float) };
+}
+
+/// what evaluating rational numbers on this thread does when a value can not be exact.
+pub fn rational_fallback() -> RationalFallback {
+    RATIONAL_FALLBACK.get()
+}
+
+/// this will set what evaluating rational numbers on this thread does when a value can not be exact, a `Context`
+/// evaluates with its own.
+pub fn set_rational_fallback(fallback: RationalFallback) {
+    RATIONAL_FALLBACK.set(fallback);
+}
+
+/// this will run f with a different rational fallback, restoring the previous fallback afterwards.
+pub fn with_rational_fallback<T>(fallback: RationalFallback, f: impl FnOnce() -> T) -> T {
+    struct Restore(RationalFallback);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            RATIONAL_FALLBACK.set(self.0);
+        }
+    }
+
+    let _restore = Restore(RATIONAL_FALLBACK.replace(fallback));
+    f()
+}
+
+/// an exact fraction with an arbitrarily large numerator and denominator, so "1/3 + 1/6" is 1/2. a value that can
+/// not be exact, like "sqrt(2)", is a float instead unless the rational fallback is to error.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rational {
+    Exact(BigRational),
+    Float(f64),
+}
+impl Rational {
+    pub fn integer(num: i64) -> Self {
+        Self::Exact(BigRational::from_integer(BigInt::from(num)))
+    }
+
+    pub fn fraction(numerator: i64, denominator: i64) -> Self {
+        Self::Exact(BigRational::new(
+            BigInt::from(numerator),
+            BigInt::from(denominator),
+        ))
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Self::Exact(_))
+    }
+
+    pub fn as_exact(&self) -> Option<&BigRational> {
+        match self {
+            Self::Exact(value) => Some(value),
+            Self::Float(_) => None,
+        }
+    }
+
+    /// the nearest float.
+    pub fn as_float(&self) -> f64 {
+        match self {
+            Self::Exact(value) => value.to_f64().unwrap_or(f64::NAN),
+            Self::Float(value) => *value,
+        }
+    }
+
+    /// this will apply an operation exactly if both numbers are exact, otherwise to their floats.
+    fn combine(
+        &self,
+        other: &Self,
+        exact: impl FnOnce(&BigRational, &BigRational) -> Result<BigRational, MathError>,
+        float: impl FnOnce(f64, f64) -> f64,
+    ) -> Result<Self, MathError> {
+        match (self, other) {
+            (Self::Exact(num1), Self::Exact(num2)) => exact(num1, num2).map(Self::Exact),
+            _ => Ok(Self::Float(float(self.as_float(), other.as_float()))),
+        }
+    }
+}
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// a value that can not be exact becomes a float, or errors if the rational fallback is to error.
+fn inexact(describe: impl FnOnce() -> String, value: f64) -> Result<Rational, MathError> {
+    match rational_fallback() {
+        RationalFallback::Float => Ok(Rational::Float(value)),
+        RationalFallback::Error => Err(MathError::Domain {
+            span: None,
+            message: format!("{} can not be evaluated exactly", describe()),
+        }),
+    }
+}
+
+fn division_by_zero() -> MathError {
+    MathError::Domain {
+        span: None,
+        message: "division by zero".to_string(),
+    }
+}
+
+/// the exact nth root of a fraction, none if it is irrational. an odd root of a negative fraction is negative.
+fn exact_root(value: &BigRational, n: u32) -> Option<BigRational> {
+    if value.is_negative() && n.is_multiple_of(2) {
+        return None;
+    }
+    let root = |integer: &BigInt| {
+        let root = integer.abs().nth_root(n);
+        (Pow::pow(&root, n) == integer.abs()).then_some(root)
+    };
+    let root = BigRational::new(root(value.numer())?, root(value.denom())?);
+    Some(if value.is_negative() { -root } else { root })
+}
+
+/// the product of every integer from n down to 1 in steps, none if n is larger than the largest exact factorial.
+pub(crate) fn big_factorial(n: u32, step: usize) -> Option<BigInt> {
+    (n <= MAX_EXACT_BIG_FACTORIAL).then(|| {
+        (1..=n)
+            .rev()
+            .step_by(step)
+            .fold(BigInt::one(), |product, k| product * k)
+    })
+}
+
+/// the exact factorial of a natural number, none for any other number.
+fn exact_factorial(value: &BigRational, step: usize) -> Option<BigRational> {
+    let n = Some(value)
+        .filter(|value| value.is_integer())
+        .and_then(|value| value.to_u32())?;
+    big_factorial(n, step).map(BigRational::from_integer)
+}
+
+/// exact fractions, every built in function is evaluated exactly where its value is rational and as a float
+/// otherwise.
+impl Numeric for Rational {
+    const NAME: &'static str = "rational";
+
+    /// a float is the shortest decimal that parses back to it, so 0.1 is 1/10.
+    fn from_f64(num: f64) -> Result<Self, MathError> {
+        if !num.is_finite() {
+            return Ok(Self::Float(num));
+        }
+        let decimal = num.to_string();
+        let (integer, fraction) = decimal.split_once('.').unwrap_or((&decimal, ""));
+        let numerator = format!("{integer}{fraction}")
+            .parse::<BigInt>()
+            .map_err(|error| MathError::Domain {
+                span: None,
+                message: format!("{num} is not a rational number: {error}"),
+            })?;
+        let denominator = Pow::pow(BigInt::from(10), fraction.len());
+        Ok(Self::Exact(BigRational::new(numerator, denominator)))
+    }
+
+    /// a literal is the exact decimal it was written as, however many digits it has.
+    fn from_literal(literal: &str, num: f64) -> Result<Self, MathError> {
+        let (digits, scale) = Decimal::parse(literal)?.0.as_bigint_and_exponent();
+        if scale.unsigned_abs() > MAX_EXACT_LITERAL_EXPONENT {
+            return Self::from_f64(num);
+        }
+        let power = Pow::pow(BigInt::from(10), scale.unsigned_abs());
+        Ok(Self::Exact(match scale < 0 {
+            true => BigRational::from_integer(digits * power),
+            false => BigRational::new(digits, power),
+        }))
+    }
+
+    /// only an integer constant like true is exact, an irrational one like pi is not.
+    fn from_constant(identity: &str, num: f64) -> Result<Self, MathError> {
+        match num.fract() == 0f64 {
+            true => Self::from_f64(num),
+            false => inexact(|| identity.to_string(), num),
+        }
+    }
+
+    fn from_numeric_value(value: &NumericValue) -> Option<Self> {
+        match value {
+            NumericValue::Rational(value) => Some(value.clone()),
+            _ => value.to_f64().and_then(|num| Self::from_f64(num).ok()),
+        }
+    }
+
+    fn to_f64(&self) -> Result<f64, MathError> {
+        Ok(self.as_float())
+    }
+
+    fn to_variable(&self) -> MathDefinition {
+        match self {
+            Self::Exact(_) => MathDefinition::NumericVariable(NumericValue::Rational(self.clone())),
+            Self::Float(value) => MathDefinition::Variable(*value),
+        }
+    }
+
+    fn try_add(&self, other: &Self) -> Result<Self, MathError> {
+        self.combine(
+            other,
+            |num1, num2| Ok(num1 + num2),
+            |num1, num2| num1 + num2,
+        )
+    }
+
+    fn try_sub(&self, other: &Self) -> Result<Self, MathError> {
+        self.combine(
+            other,
+            |num1, num2| Ok(num1 - num2),
+            |num1, num2| num1 - num2,
+        )
+    }
+
+    fn try_mul(&self, other: &Self) -> Result<Self, MathError> {
+        self.combine(
+            other,
+            |num1, num2| Ok(num1 * num2),
+            |num1, num2| num1 * num2,
+        )
+    }
+
+    fn try_div(&self, other: &Self) -> Result<Self, MathError> {
+        self.combine(
+            other,
+            |num1, num2| match num2.is_zero() {
+                true => Err(division_by_zero()),
+                false => Ok(num1 / num2),
+            },
+            |num1, num2| num1 / num2,
+        )
+    }
+
+    /// the remainder has the sign of the dividend, like the remainder of floats.
+    fn try_rem(&self, other: &Self) -> Result<Self, MathError> {
+        self.combine(
+            other,
+            |num1, num2| match num2.is_zero() {
+                true => Err(division_by_zero()),
+                false => Ok(num1 % num2),
+            },
+            |num1, num2| num1 % num2,
+        )
+    }
+
+    /// a power is exact for an integer exponent, and for a fractional exponent if the root is rational.
+    fn try_pow(&self, exponent: &Self) -> Result<Self, MathError> {
+        let (Self::Exact(base), Self::Exact(power)) = (self, exponent) else {
+            return Ok(Self::Float(self.as_float().powf(exponent.as_float())));
+        };
+
+        let numerator = power.numer().to_i32();
+        let root = power.denom().to_u32().and_then(|n| exact_root(base, n));
+        match (numerator, root) {
+            (Some(numerator), _) if base.is_zero() && numerator < 0 => Err(division_by_zero()),
+            (Some(numerator), Some(root)) => Ok(Self::Exact(Pow::pow(root, numerator))),
+            _ => inexact(
+                || format!("{self}^{exponent}"),
+                self.as_float().powf(exponent.as_float()),
+            ),
+        }
+    }
+
+    fn try_neg(&self) -> Result<Self, MathError> {
+        Ok(match self {
+            Self::Exact(value) => Self::Exact(-value),
+            Self::Float(value) => Self::Float(-value),
+        })
+    }
+
+    fn partial_compare(&self, other: &Self) -> Result<Option<Ordering>, MathError> {
+        Ok(match (self, other) {
+            (Self::Exact(num1), Self::Exact(num2)) => Some(num1.cmp(num2)),
+            _ => self.as_float().partial_cmp(&other.as_float()),
+        })
+    }
+
+    fn factorial(&self) -> Result<Self, MathError> {
+        match self.as_exact().and_then(|value| exact_factorial(value, 1)) {
+            Some(factorial) => Ok(Self::Exact(factorial)),
+            None => Self::from_f64(factorial(self.as_float())?),
+        }
+    }
+
+    fn double_factorial(&self) -> Result<Self, MathError> {
+        match self.as_exact().and_then(|value| exact_factorial(value, 2)) {
+            Some(double_factorial) => Ok(Self::Exact(double_factorial)),
+            None => Self::from_f64(double_factorial(self.as_float())?),
+        }
+    }
+
+    fn supports_built_in(identity: &str) -> bool {
+        identity != INTERVAL
+    }
+
+    fn evaluate_built_in(
+        identity: &str,
+        function: &BuiltInFunction,
+        args: &[Self],
+    ) -> Result<Self, MathError> {
+        if let Some(exact) = args
+            .iter()
+            .map(Rational::as_exact)
+            .collect::<Option<Vec<_>>>()
+        {
+            let x = exact[0];
+            let exact_value = match identity {
+                "pow" => return args[0].try_pow(&args[1]),
+                "factorial" => return args[0].factorial(),
+                "sqrt" => exact_root(x, 2),
+                "cbrt" => exact_root(x, 3),
+                "abs" => Some(x.abs()),
+                "re" | "conj" => Some(x.clone()),
+                "im" => Some(BigRational::zero()),
+                "arg" if !x.is_negative() => Some(BigRational::zero()),
+                "mean" => Some(
+                    exact
+                        .iter()
+                        .fold(BigRational::zero(), |sum, arg| sum + *arg)
+                        / BigInt::from(exact.len()),
+                ),
+                _ => None,
+            };
+            if let Some(value) = exact_value {
+                return Ok(Self::Exact(value));
+            }
+        }
+
+        let values = args.iter().map(Rational::as_float).collect::<Vec<f64>>();
+        let value = (function.function)(&values)?;
+        match args.iter().all(Rational::is_exact) {
+            true => inexact(
+                || {
+                    let args = args.iter().map(Rational::to_string).collect::<Vec<_>>();
+                    format!("{identity}({})", args.join(", "))
+                },
+                value,
+            ),
+            false => Ok(Self::Float(value)),
+        }
+    }
+}
+
+/// this will evaluate a string math expression or program over exact fractions, so "1/3 + 1/6" is 1/2.
+pub fn eval_str_rational(string: &str) -> Result<Rational, MathError> {
+    eval_str_numeric(string)
+}
+
+/// this will evaluate a syntax tree over exact fractions.
+pub fn eval_ast_rational(
+    ast: &Ast,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Rational, MathError> {
+    eval_ast_numeric(ast, math_definitions)
+}
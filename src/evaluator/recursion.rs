@@ -0,0 +1,95 @@
+use crate::*;
+use std::cell::Cell;
+
+/// defined functions can be nested in each other this deeply before evaluation errors, unless another limit is set.
+pub const DEFAULT_MAX_RECURSION_DEPTH: usize = 1_000;
+
+/// the deepest any limit lets defined functions be nested. each level keeps its stack frames, which can be tens of
+/// KiB in a debug build, so this keeps the stack of the deepest recursion to a few hundred MiB.
+pub const MAX_RECURSION_DEPTH_LIMIT: usize = 10_000;
+
+/// the deepest defined functions can be inlined in each other when differentiating, whatever the limit. every inlined
+/// function copies its arguments into its body, so the syntax tree grows with the square of the depth.
+pub const MAX_INLINING_DEPTH: usize = 256;
+
+/// a level of nesting that has less stack left than this continues on a new stack segment.
+const STACK_RED_ZONE: usize = 1024 * 1024;
+
+/// the size of each stack segment allocated when the stack runs low.
+const STACK_SEGMENT_SIZE: usize = 8 * 1024 * 1024;
+
+thread_local! {
+    static RECURSION_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static MAX_RECURSION_DEPTH: Cell<usize> = const { Cell::new(DEFAULT_MAX_RECURSION_DEPTH) };
+}
+
+/// the deepest defined functions can be nested in each other on this thread.
+pub fn max_recursion_depth() -> usize {
+    MAX_RECURSION_DEPTH.get()
+}
+
+/// this will set how deeply defined functions can be nested in each other on this thread, at most
+/// `MAX_RECURSION_DEPTH_LIMIT`.
+pub fn set_max_recursion_depth(max_depth: usize) {
+    MAX_RECURSION_DEPTH.set(max_depth.min(MAX_RECURSION_DEPTH_LIMIT));
+}
+
+/// this will run f with a different recursion depth limit, restoring the previous limit afterwards.
+pub fn with_max_recursion_depth<T>(max_depth: usize, f: impl FnOnce() -> T) -> T {
+    struct Restore(usize);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            MAX_RECURSION_DEPTH.set(self.0);
+        }
+    }
+
+    let _restore = Restore(MAX_RECURSION_DEPTH.replace(max_depth.min(MAX_RECURSION_DEPTH_LIMIT)));
+    f()
+}
+
+/// this will run f one level of defined function nesting deeper, erroring instead if that would go past the limit.
+/// when the stack runs low f continues on a new stack segment, so it is the limit that stops deep recursion rather
+/// than the stack overflowing.
+pub fn nested<T>(f: impl FnOnce() -> Result<T, MathError>) -> Result<T, MathError> {
+    let _guard = RecursionGuard::enter()?;
+    grow_stack(f)
+}
+
+/// this will run f one level of inlining deeper, like `nested` but limited to `MAX_INLINING_DEPTH` at most.
+pub fn inlined<T>(f: impl FnOnce() -> Result<T, MathError>) -> Result<T, MathError> {
+    let _guard = RecursionGuard::enter_within(max_recursion_depth().min(MAX_INLINING_DEPTH))?;
+    grow_stack(f)
+}
+
+/// this will run f, continuing on a new stack segment when the stack runs low. recursion over a syntax tree uses this
+/// when inlining can make the tree as deep as the recursion limit.
+pub fn grow_stack<T>(f: impl FnOnce() -> T) -> T {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, f)
+}
+
+/// one level of defined function nesting, the level is left again when the guard is dropped.
+#[derive(Debug)]
+pub struct RecursionGuard {
+    _private: (),
+}
+impl RecursionGuard {
+    /// this will enter another level of nesting, erroring instead if that would go past the limit.
+    pub fn enter() -> Result<Self, MathError> {
+        Self::enter_within(max_recursion_depth())
+    }
+
+    /// this will enter another level of nesting, erroring instead if that would go past the given limit.
+    pub fn enter_within(limit: usize) -> Result<Self, MathError> {
+        let depth = RECURSION_DEPTH.get() + 1;
+        if depth > limit {
+            return Err(MathError::RecursionLimit { span: None, limit });
+        }
+        RECURSION_DEPTH.set(depth);
+        Ok(Self { _private: () })
+    }
+}
+impl Drop for RecursionGuard {
+    fn drop(&mut self) {
+        RECURSION_DEPTH.set(RECURSION_DEPTH.get() - 1);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// roots are found to within this absolute tolerance.
+pub const ROOT_TOLERANCE: f64 = 1e-12;
+
+/// the most iterations any root finding method will take before giving up.
+pub const MAX_ROOT_ITERATIONS: usize = 200;
+
+fn not_converged(message: String) -> MathError {
+    MathError::NotConverged {
+        span: None,
+        message,
+    }
+}
+
+/// this will evaluate the function, erroring instead of returning a value that is not a number.
+fn checked(f: &impl Fn(f64) -> Result<f64, MathError>, x: f64) -> Result<f64, MathError> {
+    match f(x)? {
+        y if y.is_nan() => Err(MathError::Domain {
+            span: None,
+            message: format!("the function is not defined at {x}"),
+        }),
+        y => Ok(y),
+    }
+}
+
+fn bracket(
+    f: &impl Fn(f64) -> Result<f64, MathError>,
+    a: f64,
+    b: f64,
+) -> Result<(f64, f64), MathError> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(MathError::InvalidArgument {
+            span: None,
+            message: format!("the bracket [{a}, {b}] must be finite"),
+        });
+    }
+    let (fa, fb) = (checked(f, a)?, checked(f, b)?);
+    if fa != 0f64 && fb != 0f64 && fa.signum() == fb.signum() {
+        return Err(MathError::InvalidArgument {
+            span: None,
+            message: format!(
+                "the function has the same sign at {a} and {b}, so they do not bracket a root"
+            ),
+        });
+    }
+    Ok((fa, fb))
+}
+
+/// this will find a root of a continuous function between a and b by bisection, the function must change sign
+/// between them.
+pub fn bisection(
+    f: impl Fn(f64) -> Result<f64, MathError>,
+    a: f64,
+    b: f64,
+) -> Result<f64, MathError> {
+    let (fa, fb) = bracket(&f, a, b)?;
+    if fa == 0f64 {
+        return Ok(a);
+    }
+    if fb == 0f64 {
+        return Ok(b);
+    }
+
+    let (mut a, mut b, mut fa) = (a, b, fa);
+    for _ in 0..MAX_ROOT_ITERATIONS {
+        let mid = a + (b - a) / 2f64;
+        if (b - a).abs() <= ROOT_TOLERANCE || mid == a || mid == b {
+            return Ok(mid);
+        }
+        let f_mid = checked(&f, mid)?;
+        if f_mid == 0f64 {
+            return Ok(mid);
+        }
+        if f_mid.signum() == fa.signum() {
+            (a, fa) = (mid, f_mid);
+        } else {
+            b = mid;
+        }
+    }
+    Err(not_converged(format!(
+        "bisection did not narrow the bracket after {MAX_ROOT_ITERATIONS} iterations"
+    )))
+}
+
+/// this will find a root of a continuous function between a and b with brent's method, which mixes bisection with
+/// secant steps and inverse quadratic interpolation so it converges much faster than bisection alone. the function
+/// must change sign between a and b.
+pub fn brent(f: impl Fn(f64) -> Result<f64, MathError>, a: f64, b: f64) -> Result<f64, MathError> {
+    let (fa, fb) = bracket(&f, a, b)?;
+    if fa == 0f64 {
+        return Ok(a);
+    }
+
+    // b is the best estimate so far, c is the other side of the bracket and a is the previous estimate
+    let (mut a, mut b, mut fa, mut fb) = (a, b, fa, fb);
+    let (mut c, mut fc) = (b, fb);
+    let (mut step, mut previous_step) = (b - a, b - a);
+    for _ in 0..MAX_ROOT_ITERATIONS {
+        if fb.signum() == fc.signum() {
+            (c, fc) = (a, fa);
+            step = b - a;
+            previous_step = step;
+        }
+        if fc.abs() < fb.abs() {
+            (a, fa) = (b, fb);
+            (b, fb) = (c, fc);
+            (c, fc) = (a, fa);
+        }
+
+        let tolerance = 2f64 * f64::EPSILON * b.abs() + ROOT_TOLERANCE / 2f64;
+        let midpoint_step = (c - b) / 2f64;
+        if midpoint_step.abs() <= tolerance || fb == 0f64 {
+            return Ok(b);
+        }
+
+        if previous_step.abs() >= tolerance && fa.abs() > fb.abs() {
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                // secant step
+                (2f64 * midpoint_step * s, 1f64 - s)
+            } else {
+                // inverse quadratic interpolation
+                let (q, r) = (fa / fc, fb / fc);
+                (
+                    s * (2f64 * midpoint_step * q * (q - r) - (b - a) * (r - 1f64)),
+                    (q - 1f64) * (r - 1f64) * (s - 1f64),
+                )
+            };
+            if p > 0f64 {
+                q = -q;
+            } else {
+                p = -p;
+            }
+
+            // only interpolate if the step stays inside the bracket and shrinks fast enough
+            if 2f64 * p
+                < (3f64 * midpoint_step * q - (tolerance * q).abs()).min((previous_step * q).abs())
+            {
+                previous_step = step;
+                step = p / q;
+            } else {
+                step = midpoint_step;
+                previous_step = step;
+            }
+        } else {
+            step = midpoint_step;
+            previous_step = step;
+        }
+
+        (a, fa) = (b, fb);
+        b += if step.abs() > tolerance {
+            step
+        } else {
+            tolerance.copysign(midpoint_step)
+        };
+        fb = checked(&f, b)?;
+    }
+    Err(not_converged(format!(
+        "brent's method did not find a root after {MAX_ROOT_ITERATIONS} iterations"
+    )))
+}
+
+/// this will find a root of a differentiable function with newton's method starting from x0, given a function that
+/// returns the value and derivative at a point. newton's method is fast near a root but may wander off or stall
+/// from a poor starting point, in which case an error is returned.
+pub fn newton(f: impl Fn(f64) -> Result<(f64, f64), MathError>, x0: f64) -> Result<f64, MathError> {
+    let mut x = x0;
+    for _ in 0..MAX_ROOT_ITERATIONS {
+        let (y, dy) = f(x)?;
+        if y == 0f64 {
+            return Ok(x);
+        }
+        if dy == 0f64 || !dy.is_finite() || y.is_nan() {
+            return Err(not_converged(format!(
+                "newton's method stalled at {x} where the derivative is {dy}"
+            )));
+        }
+
+        let step = y / dy;
+        x -= step;
+        if !x.is_finite() {
+            return Err(not_converged(format!(
+                "newton's method diverged starting from {x0}"
+            )));
+        }
+        if step.abs() <= ROOT_TOLERANCE * x.abs().max(1f64) {
+            return Ok(x);
+        }
+    }
+    Err(not_converged(format!(
+        "newton's method did not find a root after {MAX_ROOT_ITERATIONS} iterations starting from {x0}"
+    )))
+}
+
+impl DefinedFunction {
+    fn check_single_variable(&self) -> Result<(), MathError> {
+        match self.arg_names.len() {
+            1 => Ok(()),
+            received => Err(MathError::ArityMismatch {
+                span: None,
+                expected: 1,
+                received,
+            }),
+        }
+    }
+
+    /// this will find a root of a function of one variable between a and b with brent's method.
+    pub fn root(
+        &self,
+        a: f64,
+        b: f64,
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<f64, MathError> {
+        self.check_single_variable()?;
+        brent(|x| self.evaluate(&[x], math_definitions), a, b)
+    }
+
+    /// this will find a root of a function of one variable with newton's method starting from x0, the derivative
+    /// is evaluated exactly with dual numbers.
+    pub fn root_newton(
+        &self,
+        x0: f64,
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<f64, MathError> {
+        self.check_single_variable()?;
+        newton(
+            |x| {
+                let result = self.gradient(&[x], math_definitions)?;
+                Ok((result.value, result.derivative(0)))
+            },
+            x0,
+        )
+    }
+}
@@ -0,0 +1,370 @@
+use crate::*;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+/// the factors of a product keyed by their printed base, each with the power it is raised to.
+type Product = BTreeMap<String, (Ast, f64)>;
+
+/// an expression as a constant plus a sum of products with coefficients, like terms share a key so they collect.
+#[derive(Clone, Debug, Default)]
+struct Sum {
+    constant: f64,
+    terms: BTreeMap<String, (Product, f64)>,
+}
+impl Sum {
+    fn constant(constant: f64) -> Self {
+        Self {
+            constant,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    /// a sum of a single factor that is not simplified any further, like an identity or a function invocation.
+    fn factor(ast: Ast) -> Self {
+        Self::monomial(1f64, Product::from([(ast.to_string(), (ast, 1f64))]))
+    }
+
+    fn monomial(coefficient: f64, product: Product) -> Self {
+        let mut sum = Self::constant(0f64);
+        sum.add_term(product, coefficient);
+        sum
+    }
+
+    fn add_term(&mut self, product: Product, coefficient: f64) {
+        if product.is_empty() {
+            self.constant += coefficient;
+            return;
+        }
+        let key = product_key(&product);
+        let coefficient = coefficient + self.terms.get(&key).map_or(0f64, |(_, c)| *c);
+        if coefficient == 0f64 {
+            self.terms.remove(&key);
+        } else {
+            self.terms.insert(key, (product, coefficient));
+        }
+    }
+
+    fn get_constant(&self) -> Option<f64> {
+        self.terms.is_empty().then_some(self.constant)
+    }
+
+    fn get_monomial(&self) -> Option<(f64, &Product)> {
+        match self.terms.values().next() {
+            Some((product, coefficient)) if self.terms.len() == 1 && self.constant == 0f64 => {
+                Some((*coefficient, product))
+            }
+            _ => None,
+        }
+    }
+
+    /// this will view the sum as a monomial, wrapping it up as a single factor if it has more than one term.
+    fn into_monomial(self) -> (f64, Product) {
+        match self.get_monomial() {
+            Some((coefficient, product)) => (coefficient, product.clone()),
+            None => match self.get_constant() {
+                Some(constant) => (constant, Product::new()),
+                None => (1f64, Product::from([factor_entry(self.to_ast(), 1f64)])),
+            },
+        }
+    }
+
+    fn add(mut self, other: Self) -> Self {
+        self.constant += other.constant;
+        for (product, coefficient) in other.terms.into_values() {
+            self.add_term(product, coefficient);
+        }
+        self
+    }
+
+    fn scale(self, scalar: f64) -> Self {
+        let mut sum = Self::constant(self.constant * scalar);
+        for (product, coefficient) in self.terms.into_values() {
+            sum.add_term(product, coefficient * scalar);
+        }
+        sum
+    }
+
+    fn multiply(self, other: Self) -> Self {
+        if let Some(constant) = self.get_constant() {
+            return other.scale(constant);
+        }
+        if let Some(constant) = other.get_constant() {
+            return self.scale(constant);
+        }
+
+        let (coefficient, mut product) = self.into_monomial();
+        let (other_coefficient, other_product) = other.into_monomial();
+        for (key, (base, exponent)) in other_product {
+            let exponent = exponent + product.get(&key).map_or(0f64, |(_, e)| *e);
+            if exponent == 0f64 {
+                product.remove(&key);
+            } else {
+                product.insert(key, (base, exponent));
+            }
+        }
+        Self::monomial(coefficient * other_coefficient, product)
+    }
+
+    fn divide(self, other: Self) -> Self {
+        match other.get_constant() {
+            Some(0f64) => Self::factor(Ast::binary_operation(
+                Operator::Division,
+                self.to_ast(),
+                Ast::number(0f64),
+            )),
+            Some(constant) => self.scale(1f64 / constant),
+            None => self.multiply(other.power(Self::constant(-1f64))),
+        }
+    }
+
+    fn power(self, exponent: Self) -> Self {
+        match (self.get_constant(), exponent.get_constant()) {
+            (_, Some(0f64)) => Self::constant(1f64),
+            (_, Some(1f64)) => self,
+            (Some(base), Some(exponent)) if base.powf(exponent).is_finite() => {
+                Self::constant(base.powf(exponent))
+            }
+            // (c x^a)^n = c^n x^(an) only holds for every x when n is an integer
+            (None, Some(exponent)) if exponent.fract() == 0f64 && self.get_monomial().is_some() => {
+                let (coefficient, product) = self.into_monomial();
+                Self::monomial(
+                    coefficient.powf(exponent),
+                    product
+                        .into_iter()
+                        .map(|(key, (base, e))| (key, (base, e * exponent)))
+                        .collect(),
+                )
+            }
+            (None, Some(exponent)) => {
+                Self::monomial(1f64, Product::from([factor_entry(self.to_ast(), exponent)]))
+            }
+            _ => Self::factor(Ast::binary_operation(
+                Operator::Exponentiation,
+                self.to_ast(),
+                exponent.to_ast(),
+            )),
+        }
+    }
+
+    /// this will build the canonical tree of the sum, terms of higher degree first and the constant last.
+    fn to_ast(&self) -> Ast {
+        let mut terms = self.terms.iter().collect::<Vec<_>>();
+        terms.sort_by(|(_, (a, _)), (_, (b, _))| compare_products(a, b));
+
+        let empty_product = Product::new();
+        let mut terms = terms
+            .into_iter()
+            .map(|(_, (product, coefficient))| (*coefficient, product))
+            .collect::<Vec<(f64, &Product)>>();
+        if self.constant != 0f64 || terms.is_empty() {
+            terms.push((self.constant, &empty_product));
+        }
+
+        let mut terms = terms.into_iter();
+        let first = match terms.next() {
+            Some((-1f64, product)) if !product.is_empty() => negate(term_ast(1f64, product)),
+            Some((coefficient, product)) => term_ast(coefficient, product),
+            None => number(0f64),
+        };
+        terms.fold(first, |sum, (coefficient, product)| {
+            let operator = if coefficient < 0f64 {
+                Operator::Subtraction
+            } else {
+                Operator::Addition
+            };
+            Ast::binary_operation(operator, sum, term_ast(coefficient.abs(), product))
+        })
+    }
+}
+
+/// this will simplify a syntax tree, folding constants, dropping identities like "x*1", "0+x" and "x^1", collecting
+/// like terms and putting terms and factors in a canonical order, so equivalent expressions simplify to equal trees.
+/// calls to built in functions with constant arguments are folded using the default math definitions.
+pub fn simplify(ast: &Ast) -> Ast {
+    simplify_with_definitions(ast, &MathDefinition::default_math_definitions())
+}
+
+/// this will simplify a syntax tree, folding calls to the built in functions of the given math definitions.
+pub fn simplify_with_definitions(
+    ast: &Ast,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Ast {
+    to_sum(ast, math_definitions).to_ast()
+}
+
+/// this will simplify the body of a defined function.
+pub fn simplify_function(function: &DefinedFunction) -> DefinedFunction {
+    DefinedFunction {
+        expression: simplify(&function.expression),
+        ..function.clone()
+    }
+}
+
+fn to_sum(ast: &Ast, math_definitions: &HashMap<String, MathDefinition>) -> Sum {
+    let sum_of = |ast: &Ast| to_sum(ast, math_definitions);
+
+    match &ast.kind {
+        AstKind::Number(num) => Sum::constant(num.value),
+        AstKind::Identity(_) => Sum::factor(ast.clone()),
+        AstKind::Grouping(inner) => sum_of(inner),
+        AstKind::Lambda { arg_names, body } => {
+            Sum::factor(Ast::lambda(arg_names.clone(), sum_of(body).to_ast()))
+        }
+        AstKind::UnaryOperation {
+            operator: Operator::Addition,
+            operand,
+        } => sum_of(operand),
+        AstKind::UnaryOperation {
+            operator: Operator::Subtraction,
+            operand,
+        } => sum_of(operand).scale(-1f64),
+        AstKind::UnaryOperation { operator, operand } => {
+            let operand = sum_of(operand);
+            operand
+                .get_constant()
+                .and_then(|constant| fold(operator.apply_operation_1_operand(constant)))
+                .unwrap_or_else(|| Sum::factor(Ast::unary_operation(*operator, operand.to_ast())))
+        }
+        AstKind::BinaryOperation {
+            operator,
+            left,
+            right,
+        } => {
+            let (left, right) = (sum_of(left), sum_of(right));
+            match operator {
+                Operator::Addition => left.add(right),
+                Operator::Subtraction => left.add(right.scale(-1f64)),
+                Operator::Multiplication => left.multiply(right),
+                Operator::Division => left.divide(right),
+                Operator::Exponentiation => left.power(right),
+                _ => left
+                    .get_constant()
+                    .zip(right.get_constant())
+                    .and_then(|(left, right)| {
+                        fold(operator.apply_operation_2_operands(left, right))
+                    })
+                    .unwrap_or_else(|| {
+                        Sum::factor(Ast::binary_operation(
+                            *operator,
+                            left.to_ast(),
+                            right.to_ast(),
+                        ))
+                    }),
+            }
+        }
+        AstKind::Call { identity, args } => {
+            let math_definition = math_definitions.get(identity.as_str());
+
+            // a special form simplifies to whatever it expands into, an if whose condition depends on a variable
+            // keeps both of its branches
+            if let Some(MathDefinition::SpecialForm(special_form)) = math_definition {
+                return match special_form.expand(args, ast.span, math_definitions) {
+                    Ok(expanded) => sum_of(&expanded),
+                    Err(_) if *special_form == SpecialForm::If => Sum::factor(Ast::call(
+                        identity,
+                        args.iter().map(|arg| sum_of(arg).to_ast()).collect(),
+                    )),
+                    Err(_) => Sum::factor(ast.clone()),
+                };
+            }
+
+            let args = spread_args(args, math_definitions)
+                .unwrap_or(std::borrow::Cow::Borrowed(args))
+                .iter()
+                .map(|arg| sum_of(arg).to_ast())
+                .collect::<Vec<Ast>>();
+            let constant_args = args.iter().map(Ast::get_num).collect::<Option<Vec<f64>>>();
+
+            match (math_definition, constant_args) {
+                (Some(MathDefinition::BuiltInFunction(function)), Some(constant_args)) => {
+                    fold(function.evaluate(&constant_args, math_definitions))
+                }
+                _ => None,
+            }
+            .unwrap_or_else(|| Sum::factor(Ast::call(identity, args)))
+        }
+    }
+}
+
+/// a folded constant is only kept if it is a finite number, otherwise the expression is left as it is.
+fn fold(result: Result<f64, MathError>) -> Option<Sum> {
+    result.ok().filter(|num| num.is_finite()).map(Sum::constant)
+}
+
+fn factor_entry(base: Ast, exponent: f64) -> (String, (Ast, f64)) {
+    (base.to_string(), (base, exponent))
+}
+
+fn product_key(product: &Product) -> String {
+    product
+        .iter()
+        .map(|(key, (_, exponent))| format!("{key}^{exponent}"))
+        .collect::<Vec<String>>()
+        .join("*")
+}
+
+fn degree(product: &Product) -> f64 {
+    product.values().map(|(_, exponent)| exponent).sum()
+}
+
+/// graded lexicographic order, products of higher degree come first and then products with higher powers of the
+/// first factor, so "x^2" comes before "x * y" which comes before "y^2".
+fn compare_products(a: &Product, b: &Product) -> Ordering {
+    fn factors(product: &Product) -> impl Iterator<Item = (&String, f64)> {
+        product.iter().map(|(key, (_, exponent))| (key, *exponent))
+    }
+
+    degree(b).total_cmp(&degree(a)).then_with(|| {
+        factors(a)
+            .zip(factors(b))
+            .map(|((a_key, a_exponent), (b_key, b_exponent))| {
+                a_key.cmp(b_key).then(b_exponent.total_cmp(&a_exponent))
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(a.len().cmp(&b.len()))
+    })
+}
+
+/// this will build a term like "3 * x^2 * y / z" from a coefficient and its product.
+fn term_ast(coefficient: f64, product: &Product) -> Ast {
+    let factors = |is_numerator: bool| {
+        product
+            .values()
+            .filter(|(_, exponent)| (*exponent > 0f64) == is_numerator)
+            .map(|(base, exponent)| match exponent.abs() {
+                1f64 => base.clone(),
+                exponent => {
+                    Ast::binary_operation(Operator::Exponentiation, base.clone(), number(exponent))
+                }
+            })
+            .collect::<Vec<Ast>>()
+    };
+    let multiply = |factors: Vec<Ast>| {
+        factors.into_iter().reduce(|product, factor| {
+            Ast::binary_operation(Operator::Multiplication, product, factor)
+        })
+    };
+
+    let mut numerator = factors(true);
+    if coefficient != 1f64 || numerator.is_empty() {
+        numerator.insert(0, number(coefficient));
+    }
+    let numerator = multiply(numerator).unwrap_or(number(1f64));
+
+    match multiply(factors(false)) {
+        Some(denominator) => Ast::binary_operation(Operator::Division, numerator, denominator),
+        None => numerator,
+    }
+}
+
+fn number(num: f64) -> Ast {
+    // adding zero turns a negative zero into zero
+    Ast::number(num + 0f64)
+}
+
+fn negate(ast: Ast) -> Ast {
+    match ast.get_num() {
+        Some(num) => number(-num),
+        None => Ast::unary_operation(Operator::Subtraction, ast),
+    }
+}
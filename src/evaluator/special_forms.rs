@@ -0,0 +1,433 @@
+use crate::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// a built in function that is given the syntax trees of its arguments rather than their values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecialForm {
+    /// "diff(expression, x)" is the derivative of the expression with respect to x, evaluated at the value of x.
+    /// the expression can also be the name of a function of one argument like "diff(sin, x)".
+    Diff,
+    /// "root(f, a, b)" is a root of the function f of one argument between a and b, found with brent's method.
+    /// "root(f, x0)" is a root found with newton's method starting from x0.
+    Root,
+    /// "integrate(f, a, b)" is the integral of f from a to b, "integrate(f, a, b, tolerance)" sets the error allowed.
+    Integrate,
+    /// "sum(f, m, n)" is f(m) + f(m + 1) + ... + f(n).
+    Sum,
+    /// "prod(f, m, n)" is f(m) * f(m + 1) * ... * f(n).
+    Prod,
+    /// "map(f, a, b, c)" is the values f(a), f(b) and f(c), which are spread into the arguments of the function it is
+    /// passed to, like "mean(map(f, a, b, c))".
+    Map,
+    /// "fold(f, init, a, b)" is f(f(init, a), b), the values can be spread from a map.
+    Fold,
+    /// "if(condition, a, b)" is a if the condition is true and b if it is false, only that branch is evaluated.
+    /// a conditional like "condition ? a : b" is parsed into it. "if(condition, a)" errors if the condition is
+    /// false, the cases of a piecewise function without an otherwise case end with one.
+    If,
+}
+impl SpecialForm {
+    pub fn get_special_forms_map() -> HashMap<&'static str, MathDefinition> {
+        HashMap::from([
+            ("diff", MathDefinition::SpecialForm(Self::Diff)),
+            ("root", MathDefinition::SpecialForm(Self::Root)),
+            ("integrate", MathDefinition::SpecialForm(Self::Integrate)),
+            ("sum", MathDefinition::SpecialForm(Self::Sum)),
+            ("prod", MathDefinition::SpecialForm(Self::Prod)),
+            ("map", MathDefinition::SpecialForm(Self::Map)),
+            ("fold", MathDefinition::SpecialForm(Self::Fold)),
+            ("if", MathDefinition::SpecialForm(Self::If)),
+        ])
+    }
+
+    /// this will expand the special form into the syntax trees of the values it stands for, only a map has more
+    /// than one. a name in scope is a variable rather than a function.
+    pub fn expand_values(
+        &self,
+        args: &[Ast],
+        span: Option<Span>,
+        math_definitions: &HashMap<String, MathDefinition>,
+        is_variable: &dyn Fn(&str) -> bool,
+    ) -> Result<Vec<Ast>, MathError> {
+        match self {
+            Self::Map => expand_map(args, span, math_definitions, is_variable),
+            _ => Ok(vec![self.expand_with(
+                args,
+                span,
+                math_definitions,
+                is_variable,
+            )?]),
+        }
+    }
+
+    /// this will expand the special form like `expand`, where the variables in scope are known. the derivative with
+    /// respect to a variable the expression does not use is 0 without evaluating the expression, so the identities
+    /// of the expression a diff differentiates are checked to be variables or math definitions. otherwise
+    /// "diff(x^2, y)" would be 0 where "diff(x^2, x)" errors when x is not defined.
+    pub fn expand_in_scope(
+        &self,
+        args: &[Ast],
+        span: Option<Span>,
+        math_definitions: &HashMap<String, MathDefinition>,
+        is_variable: impl Fn(&str) -> bool,
+    ) -> Result<Ast, MathError> {
+        let expanded = self.expand_with(args, span, math_definitions, &is_variable)?;
+        if let (Self::Diff, Some(expression)) = (self, args.first()) {
+            if let Some((identity, node)) =
+                expression
+                    .identity_nodes()
+                    .into_iter()
+                    .find(|(identity, _)| {
+                        !is_variable(identity) && !math_definitions.contains_key(identity.as_str())
+                    })
+            {
+                return Err(MathError::UnknownIdentifier {
+                    span: node.span,
+                    identifier: identity.clone(),
+                });
+            }
+        }
+        Ok(expanded)
+    }
+
+    /// this will expand the special form into the syntax tree it stands for. a form that stands for a number, like
+    /// a root or an integral, is evaluated and expands into that number, so its arguments can not depend on any
+    /// variables. an if expands into the branch its condition chooses, so its condition can not either.
+    pub fn expand(
+        &self,
+        args: &[Ast],
+        span: Option<Span>,
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Result<Ast, MathError> {
+        self.expand_with(args, span, math_definitions, &|_| false)
+    }
+
+    /// this will expand the special form like `expand`, where a name in scope is a variable rather than a function.
+    fn expand_with(
+        &self,
+        args: &[Ast],
+        span: Option<Span>,
+        math_definitions: &HashMap<String, MathDefinition>,
+        is_variable: &dyn Fn(&str) -> bool,
+    ) -> Result<Ast, MathError> {
+        match self {
+            Self::Diff => expand_diff(args, span, math_definitions),
+            Self::Fold => expand_fold(args, span, math_definitions, is_variable),
+            Self::If => {
+                let (condition, then, otherwise) = if_args(args, span)?;
+                if condition_holds(condition, |ast| eval_ast(ast, math_definitions))? {
+                    Ok(then.clone())
+                } else {
+                    otherwise.cloned().ok_or(no_case_applies(span))
+                }
+            }
+            Self::Map => match expand_map(args, span, math_definitions, is_variable)?.as_slice() {
+                [value] => Ok(value.clone()),
+                values => Err(MathError::InvalidArgument {
+                    span,
+                    message: format!(
+                        "map gives {} values, so it can only be passed as the arguments of a function",
+                        values.len()
+                    ),
+                }),
+            },
+            _ => Ok(Ast::number(self.evaluate(
+                args,
+                span,
+                math_definitions,
+                &HashMap::new(),
+            )?)),
+        }
+    }
+
+    /// this will evaluate the special form, its arguments are evaluated only as the form needs them. an argument that
+    /// is a function is passed as a function name or a lambda.
+    pub fn evaluate(
+        &self,
+        args: &[Ast],
+        span: Option<Span>,
+        math_definitions: &HashMap<String, MathDefinition>,
+        variables: &HashMap<&str, f64>,
+    ) -> Result<f64, MathError> {
+        match self {
+            Self::Diff | Self::Map | Self::Fold => {
+                let expanded = self.expand_in_scope(args, span, math_definitions, |identity| {
+                    variables.contains_key(identity)
+                })?;
+                eval_ast_with_variables(&expanded, math_definitions, variables)
+                    .map_err(|error| error.with_span(span))
+            }
+            Self::If => {
+                let eval = |ast: &Ast| eval_ast_with_variables(ast, math_definitions, variables);
+                let (condition, then, otherwise) = if_args(args, span)?;
+                if condition_holds(condition, eval)? {
+                    eval(then)
+                } else {
+                    eval(otherwise.ok_or(no_case_applies(span))?)
+                }
+            }
+            Self::Root => evaluate_root(args, span, math_definitions, variables),
+            Self::Integrate => evaluate_integrate(args, span, math_definitions, variables),
+            Self::Sum => {
+                let (function, from, to) = series_args(args, span, math_definitions, variables)?;
+                series_sum(|k| function.evaluate(&[k], math_definitions), from, to)
+                    .map_err(|error| error.with_span(span))
+            }
+            Self::Prod => {
+                let (function, from, to) = series_args(args, span, math_definitions, variables)?;
+                series_product(|k| function.evaluate(&[k], math_definitions), from, to)
+                    .map_err(|error| error.with_span(span))
+            }
+        }
+    }
+}
+
+/// this will split the arguments of an if into its condition and branches, an if with two arguments has no
+/// otherwise branch.
+pub fn if_args(args: &[Ast], span: Option<Span>) -> Result<(&Ast, &Ast, Option<&Ast>), MathError> {
+    match args {
+        [condition, then, otherwise] => Ok((condition, then, Some(otherwise))),
+        [condition, then] => Ok((condition, then, None)),
+        _ => Err(MathError::ArityMismatch {
+            span,
+            expected: 3,
+            received: args.len(),
+        }),
+    }
+}
+
+/// the error of an if without an otherwise branch whose condition is false, like a piecewise function evaluated
+/// where none of its cases apply.
+pub fn no_case_applies(span: Option<Span>) -> MathError {
+    MathError::Domain {
+        span,
+        message: "no case applies, the condition is false and there is no otherwise case"
+            .to_string(),
+    }
+}
+
+/// this will evaluate the condition of an if, which must be a boolean.
+pub fn condition_holds(
+    condition: &Ast,
+    eval: impl Fn(&Ast) -> Result<f64, MathError>,
+) -> Result<bool, MathError> {
+    as_boolean(eval(condition)?).map_err(|error| error.with_span(condition.span))
+}
+
+fn expand_diff(
+    args: &[Ast],
+    span: Option<Span>,
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Ast, MathError> {
+    let [expression, var] = args else {
+        return Err(MathError::ArityMismatch {
+            span,
+            expected: 2,
+            received: args.len(),
+        });
+    };
+
+    let var = var.get_identity().ok_or(MathError::InvalidArgument {
+        span: var.span,
+        message: "the variable to differentiate with respect to must be a name".to_string(),
+    })?;
+
+    // a bare function name is invoked with the variable
+    let expression = match (
+        expression.get_identity(),
+        expression
+            .get_identity()
+            .and_then(|identity| math_definitions.get(identity.as_str())),
+    ) {
+        (Some(identity), Some(math_definition)) if math_definition.is_function() => Ast::new(
+            AstKind::Call {
+                identity: identity.clone(),
+                args: vec![Ast::identity(var)],
+            },
+            expression.span,
+        ),
+        _ => expression.clone(),
+    };
+
+    derivative_ast(&expression, var, math_definitions)
+}
+
+fn evaluate_root(
+    args: &[Ast],
+    span: Option<Span>,
+    math_definitions: &HashMap<String, MathDefinition>,
+    variables: &HashMap<&str, f64>,
+) -> Result<f64, MathError> {
+    let eval = |ast: &Ast| eval_ast_with_variables(ast, math_definitions, variables);
+    let function_value = |ast: &Ast| FunctionValue::from_ast(ast, math_definitions, variables, 1);
+
+    match args {
+        [function, a, b] => {
+            let function = function_value(function)?;
+            let f = |x| function.evaluate(&[x], math_definitions);
+            brent(f, eval(a)?, eval(b)?)
+        }
+        [function, x0] => {
+            let function = function_value(function)?;
+            let newton_step = |x| {
+                let result =
+                    function.evaluate_dual(&[Dual::variable(x, 0, 1)], math_definitions)?;
+                Ok((result.value, result.derivative(0)))
+            };
+            newton(newton_step, eval(x0)?)
+        }
+        _ => Err(MathError::ArityMismatch {
+            span,
+            expected: 3,
+            received: args.len(),
+        }),
+    }
+    .map_err(|error| error.with_span(span))
+}
+
+fn evaluate_integrate(
+    args: &[Ast],
+    span: Option<Span>,
+    math_definitions: &HashMap<String, MathDefinition>,
+    variables: &HashMap<&str, f64>,
+) -> Result<f64, MathError> {
+    let eval = |ast: &Ast| eval_ast_with_variables(ast, math_definitions, variables);
+
+    let (function, a, b, tolerance) = match args {
+        [function, a, b] => (function, a, b, DEFAULT_INTEGRATION_TOLERANCE),
+        [function, a, b, tolerance] => (function, a, b, eval(tolerance)?),
+        _ => {
+            return Err(MathError::ArityMismatch {
+                span,
+                expected: 3,
+                received: args.len(),
+            })
+        }
+    };
+
+    let function = FunctionValue::from_ast(function, math_definitions, variables, 1)?;
+    integrate(
+        |x| function.evaluate(&[x], math_definitions),
+        eval(a)?,
+        eval(b)?,
+        tolerance,
+    )
+    .map_err(|error| error.with_span(span))
+}
+
+/// this will resolve the function and the bounds of a sum or product like "sum(f, m, n)".
+fn series_args(
+    args: &[Ast],
+    span: Option<Span>,
+    math_definitions: &HashMap<String, MathDefinition>,
+    variables: &HashMap<&str, f64>,
+) -> Result<(FunctionValue, f64, f64), MathError> {
+    let eval = |ast: &Ast| eval_ast_with_variables(ast, math_definitions, variables);
+
+    let [function, from, to] = args else {
+        return Err(MathError::ArityMismatch {
+            span,
+            expected: 3,
+            received: args.len(),
+        });
+    };
+
+    Ok((
+        FunctionValue::from_ast(function, math_definitions, variables, 1)?,
+        eval(from)?,
+        eval(to)?,
+    ))
+}
+
+/// this will expand every argument that invokes a special form with more than one value, like map, into those values
+/// so "mean(map(f, 1, 2))" has the same arguments as "mean(f(1), f(2))".
+pub fn spread_args<'a>(
+    args: &'a [Ast],
+    math_definitions: &HashMap<String, MathDefinition>,
+) -> Result<Cow<'a, [Ast]>, MathError> {
+    spread_args_in_scope(args, math_definitions, &|_| false)
+}
+
+/// this will expand the arguments like `spread_args`, where a name in scope is a variable rather than a function.
+pub fn spread_args_in_scope<'a>(
+    args: &'a [Ast],
+    math_definitions: &HashMap<String, MathDefinition>,
+    is_variable: &dyn Fn(&str) -> bool,
+) -> Result<Cow<'a, [Ast]>, MathError> {
+    fn spread_form<'a>(
+        arg: &'a Ast,
+        math_definitions: &HashMap<String, MathDefinition>,
+    ) -> Option<(SpecialForm, &'a [Ast])> {
+        match &arg.kind {
+            AstKind::Call { identity, args } => match math_definitions.get(identity.as_str()) {
+                Some(MathDefinition::SpecialForm(special_form @ SpecialForm::Map)) => {
+                    Some((*special_form, args))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    if !args
+        .iter()
+        .any(|arg| spread_form(arg, math_definitions).is_some())
+    {
+        return Ok(Cow::Borrowed(args));
+    }
+
+    let mut spread = vec![];
+    for arg in args {
+        match spread_form(arg, math_definitions) {
+            Some((special_form, form_args)) => spread.extend(special_form.expand_values(
+                form_args,
+                arg.span,
+                math_definitions,
+                is_variable,
+            )?),
+            None => spread.push(arg.clone()),
+        }
+    }
+    Ok(Cow::Owned(spread))
+}
+
+fn expand_map(
+    args: &[Ast],
+    span: Option<Span>,
+    math_definitions: &HashMap<String, MathDefinition>,
+    is_variable: &dyn Fn(&str) -> bool,
+) -> Result<Vec<Ast>, MathError> {
+    let [function, values @ ..] = args else {
+        return Err(MathError::MissingArguments { span });
+    };
+
+    let function = FunctionValue::from_ast_in_scope(function, math_definitions, is_variable, 1)?;
+    Ok(spread_args_in_scope(values, math_definitions, is_variable)?
+        .iter()
+        .map(|value| function.apply_ast(vec![value.clone()], value.span))
+        .collect())
+}
+
+fn expand_fold(
+    args: &[Ast],
+    span: Option<Span>,
+    math_definitions: &HashMap<String, MathDefinition>,
+    is_variable: &dyn Fn(&str) -> bool,
+) -> Result<Ast, MathError> {
+    let [function, init, values @ ..] = args else {
+        return Err(MathError::ArityMismatch {
+            span,
+            expected: 2,
+            received: args.len(),
+        });
+    };
+
+    let function = FunctionValue::from_ast_in_scope(function, math_definitions, is_variable, 2)?;
+    Ok(spread_args_in_scope(values, math_definitions, is_variable)?
+        .iter()
+        .fold(init.clone(), |accumulator, value| {
+            let span = Span::join(accumulator.span, value.span);
+            function.apply_ast(vec![accumulator, value.clone()], span)
+        }))
+}
@@ -0,0 +1,195 @@
+use crate::MathError;
+use ::factorial::{DoubleFactorial, Factorial};
+use std::f64::consts;
+
+/// lanczos approximation coefficients for g = 7, n = 9.
+const LANCZOS_G: f64 = 7f64;
+const LANCZOS_COEFFICIENTS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// the bernoulli numbers b2, b4, ... b16 used by the asymptotic series of the polygamma function.
+const BERNOULLI_NUMBERS: [f64; 8] = [
+    1f64 / 6f64,
+    -1f64 / 30f64,
+    1f64 / 42f64,
+    -1f64 / 30f64,
+    5f64 / 66f64,
+    -691f64 / 2730f64,
+    7f64 / 6f64,
+    -3617f64 / 510f64,
+];
+
+/// the polygamma function is shifted up to at least this plus its order before the asymptotic series is used.
+const POLYGAMMA_ASYMPTOTIC_THRESHOLD: f64 = 10f64;
+
+/// the largest n whose factorial fits in a u128.
+const MAX_EXACT_FACTORIAL: f64 = 34f64;
+
+/// gamma overflows an f64 just above 171.62, and past this the lanczos series would be inf times e^-t which is 0.
+const MAX_FINITE_GAMMA: f64 = 171.7f64;
+
+/// every double factorial above this overflows an f64.
+const MAX_FINITE_DOUBLE_FACTORIAL: f64 = 300f64;
+
+fn is_non_positive_integer(x: f64) -> bool {
+    x <= 0f64 && x.fract() == 0f64
+}
+
+fn pole_error(function_name: &str) -> MathError {
+    MathError::Domain {
+        span: None,
+        message: format!("{function_name} is undefined at zero and negative integers"),
+    }
+}
+
+/// the lanczos series for x >= 0.5, returning (t, series) where gamma(x) = sqrt(2pi) * t^(x - 0.5) * e^-t * series.
+fn lanczos(x: f64) -> (f64, f64) {
+    let x = x - 1f64;
+    let series = LANCZOS_COEFFICIENTS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(LANCZOS_COEFFICIENTS[0], |acc, (index, coefficient)| {
+            acc + coefficient / (x + index as f64)
+        });
+    (x + LANCZOS_G + 0.5f64, series)
+}
+
+/// the gamma function, extending the factorial so that gamma(n) = (n - 1)!.
+pub fn gamma(x: f64) -> Result<f64, MathError> {
+    if is_non_positive_integer(x) {
+        return Err(pole_error("gamma"));
+    }
+
+    if x < 0.5f64 {
+        // reflection formula
+        return Ok(consts::PI / ((consts::PI * x).sin() * gamma(1f64 - x)?));
+    }
+
+    if x > MAX_FINITE_GAMMA {
+        return Ok(f64::INFINITY);
+    }
+
+    // the power is split in two so it does not overflow before e^-t brings it back down
+    let (t, series) = lanczos(x);
+    let half_power = t.powf((x - 0.5f64) / 2f64);
+    Ok((2f64 * consts::PI).sqrt() * half_power * (half_power * (-t).exp()) * series)
+}
+
+/// the natural log of the absolute value of the gamma function, for arguments where gamma itself overflows.
+pub fn ln_gamma(x: f64) -> Result<f64, MathError> {
+    if is_non_positive_integer(x) {
+        return Err(pole_error("lgamma"));
+    }
+
+    if x < 0.5f64 {
+        // reflection formula
+        return Ok((consts::PI / (consts::PI * x).sin().abs()).ln() - ln_gamma(1f64 - x)?);
+    }
+
+    let (t, series) = lanczos(x);
+    Ok(0.5f64 * (2f64 * consts::PI).ln() + (x - 0.5f64) * t.ln() - t + series.ln())
+}
+
+/// n!, exact for integers that fit and gamma(n + 1) for everything else.
+pub fn factorial(x: f64) -> Result<f64, MathError> {
+    if x < 0f64 && x.fract() == 0f64 {
+        return Err(MathError::Domain {
+            span: None,
+            message: "factorial is undefined for negative integers".to_string(),
+        });
+    }
+
+    match Some(x)
+        .filter(|x| x.fract() == 0f64 && *x <= MAX_EXACT_FACTORIAL)
+        .and_then(|x| (x as u128).checked_factorial())
+    {
+        Some(factorial) => Ok(factorial as f64),
+        None => gamma(x + 1f64),
+    }
+}
+
+/// n!!, the product of every integer from n down to 1 that has the same parity as n.
+pub fn double_factorial(x: f64) -> Result<f64, MathError> {
+    if x.fract() != 0f64 || x < -1f64 {
+        return Err(MathError::Domain {
+            span: None,
+            message: "double factorial is only defined for integers of at least -1".to_string(),
+        });
+    }
+
+    if x < 0f64 {
+        return Ok(1f64);
+    }
+
+    if x > MAX_FINITE_DOUBLE_FACTORIAL {
+        return Ok(f64::INFINITY);
+    }
+
+    match (x as u128).checked_double_factorial() {
+        Some(double_factorial) => Ok(double_factorial as f64),
+        None => Ok((1..=x as u64)
+            .rev()
+            .step_by(2)
+            .fold(1f64, |acc, n| acc * n as f64)),
+    }
+}
+
+/// the polygamma function of order n, the (n + 1)th derivative of ln(gamma(x)).
+/// polygamma(0, x) is the digamma function and polygamma(1, x) the trigamma function.
+pub fn polygamma(n: f64, x: f64) -> Result<f64, MathError> {
+    if n < 0f64 || n.fract() != 0f64 {
+        return Err(MathError::Domain {
+            span: None,
+            message: "polygamma is only defined for orders that are non-negative integers"
+                .to_string(),
+        });
+    }
+    if is_non_positive_integer(x) {
+        return Err(pole_error("polygamma"));
+    }
+
+    // (-1)^(n + 1)
+    let sign = if n % 2f64 == 0f64 { -1f64 } else { 1f64 };
+    let n_factorial = factorial(n)?;
+
+    // the recurrence polygamma(n, x) = polygamma(n, x + 1) + (-1)^(n + 1) n! / x^(n + 1)
+    let mut x = x;
+    let mut shifted = 0f64;
+    while x < POLYGAMMA_ASYMPTOTIC_THRESHOLD + n {
+        shifted += sign * n_factorial / x.powf(n + 1f64);
+        x += 1f64;
+    }
+
+    let asymptotic = if n == 0f64 {
+        BERNOULLI_NUMBERS
+            .iter()
+            .enumerate()
+            .fold(x.ln() - 0.5f64 / x, |acc, (index, bernoulli)| {
+                let k = 2f64 * (index + 1) as f64;
+                acc - bernoulli / (k * x.powf(k))
+            })
+    } else {
+        let series = BERNOULLI_NUMBERS.iter().enumerate().try_fold(
+            factorial(n - 1f64)? / x.powf(n) + n_factorial / (2f64 * x.powf(n + 1f64)),
+            |acc, (index, bernoulli)| {
+                let k = 2f64 * (index + 1) as f64;
+                Ok::<f64, MathError>(
+                    acc + bernoulli * factorial(k + n - 1f64)? / (factorial(k)? * x.powf(k + n)),
+                )
+            },
+        )?;
+        sign * series
+    };
+
+    Ok(shifted + asymptotic)
+}
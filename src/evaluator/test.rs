@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod evaluator_tests {
-    use crate::eval_str;
+    use crate::*;
+    use std::collections::HashMap;
     use std::f64::consts;
 
     #[test]
@@ -33,4 +34,1712 @@ mod evaluator_tests {
     fn eval_constant_expression() {
         assert_eq!(consts::TAU * consts::PI, eval_str("tau * pi").unwrap())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn eval_operator_precedence() {
+        assert_eq!(2f64 + 3f64 * 4f64 - 8f64 / 2f64, eval_str("2 + 3 * 4 - 8 / 2").unwrap());
+        assert_eq!(-(2f64.powf(2f64)), eval_str("-2^2").unwrap());
+        assert_eq!(2f64.powf(9f64), eval_str("2^3^2").unwrap());
+        assert_eq!(2f64.powf(-1f64), eval_str("2^-1").unwrap());
+    }
+
+    #[test]
+    fn eval_nested_sub_expressions() {
+        assert_eq!((1f64 + 2f64) * (3f64 - (4f64 + 5f64)), eval_str("(1 + 2) * (3 - (4 + 5))").unwrap())
+    }
+
+    #[test]
+    fn eval_unbalanced_parens_is_an_error() {
+        assert!(eval_str("(1 + 2").is_err());
+        assert!(eval_str("1 + 2)").is_err());
+        assert!(eval_str("cos(5").is_err());
+    }
+
+    #[test]
+    fn parse_ast_tree_shape() {
+        assert_eq!(
+            Ast::binary_operation(
+                Operator::Addition,
+                Ast::number(1f64),
+                Ast::binary_operation(
+                    Operator::Multiplication,
+                    Ast::identity("x"),
+                    Ast::call("pow", vec![Ast::number(2f64), Ast::grouping(Ast::number(3f64))]),
+                ),
+            ),
+            parse_ast_str("1 + x * pow(2, (3))").unwrap()
+        )
+    }
+
+    #[test]
+    fn eval_ast_reused_with_different_variables() {
+        let ast = parse_ast_str("x^2 + 1").unwrap();
+        let math_definitions = MathDefinition::default_math_definitions();
+        for x in [0f64, 1f64, 2.5f64] {
+            assert_eq!(
+                x.powf(2f64) + 1f64,
+                eval_ast_with_variables(&ast, &math_definitions, &[("x", x)].into()).unwrap()
+            )
+        }
+    }
+
+    #[test]
+    fn eval_defined_function() {
+        let function = DefinedFunction::parse_str("f(x,y):x*y+x").unwrap();
+        assert_eq!(
+            3f64 * 4f64 + 3f64,
+            function.evaluate(&[3f64, 4f64], &MathDefinition::default_math_definitions()).unwrap()
+        )
+    }
+
+    #[test]
+    fn eval_compiled_expression_with_different_variables() {
+        let compiled_expression = CompiledExpression::parse_str(
+            "x^2 + sin(y) * pi",
+            &["x", "y"],
+            &MathDefinition::default_math_definitions(),
+        )
+        .unwrap();
+        for (x, y) in [(0f64, 0f64), (1f64, 2f64), (-3.5f64, 0.25f64)] {
+            assert_eq!(
+                x.powf(2f64) + y.sin() * consts::PI,
+                compiled_expression.eval(&[x, y]).unwrap()
+            )
+        }
+        assert!(compiled_expression.eval(&[1f64]).is_err());
+    }
+
+    #[test]
+    fn eval_compiled_expression_inlines_defined_functions() {
+        let mut math_definitions = MathDefinition::default_math_definitions();
+        math_definitions.insert(
+            "f".to_string(),
+            MathDefinition::DefinedFunction(DefinedFunction::parse_str("f(x,y):x*y+x").unwrap()),
+        );
+        let compiled_expression =
+            CompiledExpression::parse_str("f(x + 1, 2) - x", &["x"], &math_definitions).unwrap();
+        assert_eq!(
+            (4f64 + 1f64) * 2f64 + (4f64 + 1f64) - 4f64,
+            compiled_expression.eval(&[4f64]).unwrap()
+        )
+    }
+
+    #[test]
+    fn eval_compiled_expression_inlines_nested_invocations_once() {
+        let mut math_definitions = MathDefinition::default_math_definitions();
+        for definition in ["f(x): x*x + x", "g(x): x + x"] {
+            let function = DefinedFunction::parse_str(definition).unwrap();
+            math_definitions.insert(function.signature.clone().unwrap(), MathDefinition::DefinedFunction(function));
+        }
+
+        let compiled_expression = CompiledExpression::parse_str("f(f(f(x)))", &["x"], &math_definitions).unwrap();
+        assert_eq!(1806f64, compiled_expression.eval(&[2f64]).unwrap());
+
+        // each argument is evaluated once into a slot, copying it into the body would take 2^40 nodes
+        let nested = (0..40).fold("x".to_string(), |inner, _| format!("g({inner})"));
+        let compiled_expression = CompiledExpression::parse_str(&nested, &["x"], &math_definitions).unwrap();
+        assert_eq!(2f64.powi(40) * 3f64, compiled_expression.eval(&[3f64]).unwrap());
+    }
+
+    #[test]
+    fn compile_unknown_identity_is_an_error() {
+        let math_definitions = MathDefinition::default_math_definitions();
+        assert!(CompiledExpression::parse_str("x + z", &["x"], &math_definitions).is_err());
+        assert!(CompiledExpression::parse_str("pow(x)", &["x"], &math_definitions).is_err());
+    }
+
+    #[test]
+    fn eval_errors_carry_spans() {
+        assert_eq!(
+            MathError::UnknownIdentifier {
+                span: Some(Span::new(4, 7)),
+                identifier: "foo".to_string()
+            },
+            eval_str("1 + foo * 2").unwrap_err()
+        );
+        assert_eq!(
+            MathError::UnbalancedParens { span: Some(Span::new(4, 5)) },
+            eval_str("2 * (3 + 4").unwrap_err()
+        );
+        assert_eq!(
+            MathError::ArityMismatch {
+                span: Some(Span::new(0, 7)),
+                expected: 2,
+                received: 1
+            },
+            eval_str("pow(2 )  + 1").unwrap_err()
+        );
+        assert_eq!(
+            MathError::Lex {
+                span: Some(Span::new(2, 3)),
+                message: "unexpected character \"#\"".to_string()
+            },
+            eval_str("1 # 2").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn eval_nested_argument_errors_are_propagated() {
+        assert_eq!(
+            MathError::UnknownIdentifier {
+                span: Some(Span::new(12, 13)),
+                identifier: "y".to_string()
+            },
+            eval_str("mean(1, cos(y))").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn render_error_with_caret() {
+        let input = "2 * sqrt(4, 5)";
+        assert_eq!(
+            "error : expected 1 args but recieved : 2\n2 * sqrt(4, 5)\n    ^^^^^^^^^^",
+            eval_str(input).unwrap_err().render(input)
+        );
+    }
+
+    #[test]
+    fn defined_function_errors_point_at_the_definition() {
+        assert_eq!(
+            MathError::UnknownIdentifier {
+                span: Some(Span::new(13, 14)),
+                identifier: "z".to_string()
+            },
+            DefinedFunction::parse_str("f(x, y): x + z").unwrap_err()
+        );
+        assert!(matches!(
+            DefinedFunction::parse_str("f(x y): x").unwrap_err(),
+            MathError::InvalidDefinition { span: Some(Span { start: 4, end: 5 }), .. }
+        ));
+    }
+
+    #[test]
+    fn parse_spanned_tokens_keep_original_positions() {
+        let input = "  2 *\tπ  + log (x)";
+        let spanned_tokens = parse_spanned_str(input).unwrap();
+        assert_eq!(
+            vec!["2", "*", "π", "+", "log", "(", "x", ")"],
+            spanned_tokens
+                .iter()
+                .map(|spanned_token| spanned_token.text(input).unwrap())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(
+            SpannedToken {
+                token: Token::Identity("π".to_string()),
+                span: Some(Span::new(6, 8))
+            },
+            spanned_tokens[2]
+        );
+        assert_eq!(Some(Span::new(12, 15)), spanned_tokens[4].span);
+    }
+
+    #[test]
+    fn parse_spanned_ast_spans_cover_their_tokens() {
+        let input = "1 + cos( 2 ) * 3";
+        let ast = parse_spanned_ast(&parse_spanned_str(input).unwrap()).unwrap();
+        assert_eq!(Some(Span::new(0, 16)), ast.span);
+        if let AstKind::BinaryOperation { right, .. } = &ast.kind {
+            assert_eq!("cos( 2 ) * 3", &input[right.span.unwrap().start..right.span.unwrap().end]);
+        } else {
+            panic!("expected a binary operation");
+        }
+    }
+
+    #[test]
+    fn eval_postfix_factorial() {
+        assert_eq!(120f64, eval_str("5!").unwrap());
+        assert_eq!(3f64, eval_str("3!!").unwrap());
+        assert_eq!(48f64, eval_str("6!!").unwrap());
+        assert_eq!(2f64.powf(6f64), eval_str("2^3!").unwrap());
+        assert_eq!(36f64, eval_str("3!^2").unwrap());
+        assert_eq!(-6f64, eval_str("-3!").unwrap());
+        assert_eq!(720f64, eval_str("(3!)!").unwrap());
+
+        let function = DefinedFunction::parse_str("f(n):(n+1)!").unwrap();
+        assert_eq!(
+            24f64,
+            function.evaluate(&[3f64], &MathDefinition::default_math_definitions()).unwrap()
+        );
+    }
+
+    #[test]
+    fn eval_factorial_of_negative_integer_is_a_domain_error() {
+        assert!(matches!(
+            eval_str("1 + (0-2)!").unwrap_err(),
+            MathError::Domain { span: Some(Span { start: 4, end: 10 }), .. }
+        ));
+    }
+
+    #[test]
+    fn eval_gamma_builtin_functions() {
+        let close = |expected: f64, actual: f64| (expected - actual).abs() <= 1e-9 * expected.abs().max(1f64);
+        assert!(close(24f64, eval_str("gamma(5)").unwrap()));
+        assert!(close(consts::PI.sqrt(), eval_str("gamma(0.5)").unwrap()));
+        assert!(close(-2f64 * consts::PI.sqrt(), eval_str("gamma(-0.5)").unwrap()));
+        assert!(close(consts::PI.sqrt() / 2f64, eval_str("factorial(0.5)").unwrap()));
+        assert!(close(consts::PI.sqrt() / 2f64, eval_str("0.5!").unwrap()));
+        assert!(close(359.134_205_369_575_4, eval_str("lgamma(100)").unwrap()));
+        assert!(close(9.332_621_544_394_41e155, eval_str("gamma(100)").unwrap()));
+        assert_eq!(3628800f64, eval_str("factorial(10)").unwrap());
+        assert!(eval_str("gamma(0)").is_err());
+
+        // past where gamma overflows it is infinite, and its reflection for negative numbers vanishes
+        assert!(close(7.257_415_615_307_994e306, eval_str("170!").unwrap()));
+        assert_eq!(f64::INFINITY, eval_str("171!").unwrap());
+        assert_eq!(f64::INFINITY, eval_str("gamma(171.7)").unwrap());
+        assert_eq!(f64::INFINITY, eval_str("gamma(800)").unwrap());
+        assert_eq!(f64::INFINITY, eval_str("1000000000!").unwrap());
+        assert_eq!(f64::INFINITY, eval_str("171.5!").unwrap());
+        assert_eq!(0f64, eval_str("gamma(-800.5)").unwrap());
+        assert_eq!(0f64, eval_str("gamma(-1000000.5)").unwrap());
+        assert!(close(4_545.266_118_970_38, eval_str("lgamma(800)").unwrap()));
+    }
+
+    #[test]
+    fn parse_alternate_number_literals() {
+        assert_eq!(1e-9, eval_str("1e-9").unwrap());
+        assert_eq!(6.02e23, eval_str("6.02E23").unwrap());
+        assert_eq!(1.5e3, eval_str("1.5e+3").unwrap());
+        assert_eq!(31f64, eval_str("0x1F").unwrap());
+        assert_eq!(10f64, eval_str("0b1010").unwrap());
+        assert_eq!(493f64, eval_str("0o755").unwrap());
+        assert_eq!(1_000_000f64, eval_str("1_000_000").unwrap());
+        assert_eq!(65535f64, eval_str("0xFF_FF").unwrap());
+        assert_eq!(0.5f64, eval_str(".5").unwrap());
+        assert_eq!(2f64 * 1e2 + 0x10 as f64, eval_str("2*1e2 + 0x10").unwrap());
+    }
+
+    #[test]
+    fn parse_exponent_without_digits_is_not_part_of_the_number() {
+        assert_eq!(
+            vec![
+                Token::Number(2f64.into()),
+                Token::Identity("e".to_string()),
+                Token::Operator(Operator::Addition),
+                Token::Identity("x".to_string()),
+            ],
+            parse_str("2e+x").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_malformed_number_literals_are_errors() {
+        assert_eq!(
+            MathError::Lex {
+                span: Some(Span::new(4, 9)),
+                message: "invalid number literal \"1.2.3\"".to_string()
+            },
+            eval_str("2 + 1.2.3").unwrap_err()
+        );
+        for malformed_literal in ["0x", "0b102", "0o78", "0x1G", "1..2"] {
+            assert!(
+                matches!(parse_str(malformed_literal), Err(MathError::Lex { .. })),
+                "{malformed_literal} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_identities_with_digits_and_underscores() {
+        assert_eq!(
+            vec![
+                Token::Identity("x2".to_string()),
+                Token::Operator(Operator::Addition),
+                Token::Identity("_y".to_string()),
+                Token::Operator(Operator::Multiplication),
+                Token::Identity("f_1".to_string()),
+                Token::Operator(Operator::OpenParen),
+                Token::Identity("τ".to_string()),
+                Token::Operator(Operator::CloseParen),
+            ],
+            parse_str("x2 + _y * f_1(τ)").unwrap()
+        );
+        assert_eq!(
+            vec![Token::Number(2f64.into()), Token::Identity("x".to_string())],
+            parse_str("2x").unwrap()
+        );
+    }
+
+    #[test]
+    fn eval_every_built_in_name_is_reachable() {
+        assert_eq!(2f64, eval_str("log10(100)").unwrap());
+        assert_eq!(3f64, eval_str("log2(8)").unwrap());
+
+        for (identity, math_definition) in MathDefinition::default_math_definitions() {
+            let input = match &math_definition {
+                MathDefinition::BuiltInFunction(function) => format!(
+                    "{identity}({})",
+                    vec!["1"; function.arg_count.unwrap_or(3)].join(", ")
+                ),
+                MathDefinition::SpecialForm(SpecialForm::Diff) => format!("{identity}(1, x)"),
+                MathDefinition::SpecialForm(SpecialForm::Root) => format!("{identity}(cos, 0, 2)"),
+                MathDefinition::SpecialForm(SpecialForm::Map) => format!("{identity}(x -> x, 1)"),
+                MathDefinition::SpecialForm(SpecialForm::Fold) => {
+                    format!("{identity}((a, b) -> a + b, 1, 2)")
+                }
+                MathDefinition::SpecialForm(SpecialForm::If) => format!("{identity}(1, 2, 3)"),
+                MathDefinition::SpecialForm(_) => format!("{identity}(x -> x, 1, 2)"),
+                _ => identity.to_string(),
+            };
+            if identity == INTERVAL {
+                assert!(eval_str_interval(&input).is_ok(), "{input} should evaluate over intervals");
+                continue;
+            }
+            assert!(eval_str_complex(&input).is_ok(), "{input} should evaluate over complex numbers");
+            if !matches!(math_definition, MathDefinition::ComplexConstant(_)) {
+                assert!(eval_str(&input).is_ok(), "{input} should evaluate");
+            }
+        }
+    }
+
+    #[test]
+    fn eval_defined_function_with_digits_and_underscores() {
+        let function = DefinedFunction::parse_str("f_1(x2, y_2): x2 * y_2").unwrap();
+        assert_eq!(Some("f_1".to_string()), function.signature);
+        assert_eq!(
+            6f64,
+            function.evaluate(&[2f64, 3f64], &MathDefinition::default_math_definitions()).unwrap()
+        );
+    }
+
+    #[test]
+    fn eval_implicit_multiplication() {
+        let options = ParseOptions {
+            implicit_multiplication: true,
+            ..Default::default()
+        };
+        assert_eq!(2f64 * consts::PI, eval_str_with_options("2pi", &options).unwrap());
+        assert_eq!(8f64, eval_str_with_options("2(3 + 1)", &options).unwrap());
+        assert_eq!(-5f64, eval_str_with_options("(2 + 3)(2 - 3)", &options).unwrap());
+        assert_eq!(
+            2f64 * (consts::PI / 6f64).sin(),
+            eval_str_with_options("2 sin(pi / 6)", &options).unwrap()
+        );
+        assert_eq!(12f64, eval_str_with_options("3!2", &options).unwrap());
+        assert_eq!(18f64, eval_str_with_options("2(3)^2", &options).unwrap());
+        assert!(eval_str_with_options("2 3", &options).is_err());
+
+        // implicit multiplication binds tighter than division
+        assert_eq!(0.125f64, eval_str_with_options("1/2(4)", &options).unwrap());
+        assert_eq!(2f64, eval_str_with_options("8/2(2)", &options).unwrap());
+
+        // the default parse mode does not multiply
+        assert!(eval_str("2pi").is_err());
+    }
+
+    #[test]
+    fn parse_implicit_multiplication_against_function_calls() {
+        let mut math_definitions = MathDefinition::default_math_definitions();
+        math_definitions.insert(
+            "f".to_string(),
+            MathDefinition::DefinedFunction(DefinedFunction::parse_str("f(x): x + 1").unwrap()),
+        );
+        let options = ParseOptions {
+            implicit_multiplication: true,
+            math_definitions: Some(&math_definitions),
+        };
+        let variables = HashMap::from([("x", 3f64), ("y", 5f64)]);
+        let eval = |input: &str| {
+            eval_ast_with_variables(
+                &parse_ast_str_with_options(input, &options).unwrap(),
+                &math_definitions,
+                &variables,
+            )
+        };
+
+        assert_eq!(
+            Ast::binary_operation(
+                Operator::Division,
+                Ast::number(1f64),
+                Ast::binary_operation(Operator::Multiplication, Ast::number(2f64), Ast::identity("x"))
+            ),
+            parse_ast_str_with_options("1/2x", &options).unwrap()
+        );
+        assert_eq!(
+            parse_ast_str("2 * x^2").unwrap(),
+            parse_ast_str_with_options("2x^2", &options).unwrap()
+        );
+        assert_eq!(15f64, eval("x y").unwrap());
+        assert_eq!(6f64, eval("x(2)").unwrap());
+        assert_eq!(8f64, eval("2f(x)").unwrap());
+        assert_eq!(-6f64, eval("-2x").unwrap());
+
+        // without definitions an identity followed by an open paren is always a call
+        assert_eq!(
+            parse_ast_str("x(2)").unwrap(),
+            Ast::parse_str_with_options(
+                "x(2)",
+                &ParseOptions {
+                    implicit_multiplication: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn context_keeps_definitions_between_evaluations() {
+        let mut context = Context::new();
+        assert_eq!(None, context.ans());
+
+        assert_eq!("f", context.define_function("f(x): x^2").unwrap());
+        context.set_variable("y", 3f64).unwrap();
+        assert_eq!(10f64, context.eval("f(y) + 1").unwrap());
+        assert_eq!(Some(10f64), context.ans());
+        assert_eq!(20f64, context.eval("ans * 2").unwrap());
+
+        // variables can be reassigned and removed
+        context.set_variable("y", 4f64).unwrap();
+        assert_eq!(16f64, context.eval("f(y)").unwrap());
+        assert!(context.remove("y").is_some_and(|definition| definition.is_variable()));
+        assert!(matches!(
+            context.eval("f(y)"),
+            Err(MathError::UnknownIdentifier { identifier, .. }) if identifier == "y"
+        ));
+
+        // a failed evaluation keeps the last answer
+        assert_eq!(Some(16f64), context.ans());
+    }
+
+    #[test]
+    fn context_protects_built_in_definitions() {
+        let mut context = Context::new();
+
+        assert!(matches!(
+            context.set_variable("pi", 3f64),
+            Err(MathError::InvalidDefinition { .. })
+        ));
+        assert!(matches!(
+            context.define_function("sin(x): x"),
+            Err(MathError::InvalidDefinition { span: Some(span), .. }) if span == Span::new(0, 3)
+        ));
+        assert!(matches!(
+            context.set_variable("2x", 3f64),
+            Err(MathError::InvalidDefinition { .. })
+        ));
+        assert!(context.remove("sqrt").is_none());
+        assert_eq!(2f64, context.eval("sqrt(4)").unwrap());
+    }
+
+    #[test]
+    fn eval_assignments_and_statements() {
+        assert_eq!(25f64 * consts::PI, eval_str("r = 5; area = pi*r^2; area").unwrap());
+        assert_eq!(6f64, eval_str("x = 2\ny = x + 1\n\nx * y\n").unwrap());
+        assert_eq!(3f64, eval_str("f(x): x + 1; f(2)").unwrap());
+        assert_eq!(2f64, eval_str("x = 2;").unwrap());
+
+        // newlines inside parentheses or after an operator do not end the statement
+        assert_eq!(3f64, eval_str("(1\n+ 2)").unwrap());
+        assert_eq!(3f64, eval_str("1 +\n2").unwrap());
+
+        assert!(matches!(eval_str("f(x): x"), Err(MathError::MissingValue { .. })));
+        assert!(matches!(eval_str(""), Err(MathError::MissingValue { .. })));
+    }
+
+    #[test]
+    fn eval_assignment_errors() {
+        assert!(matches!(
+            eval_str("pi = 3"),
+            Err(MathError::InvalidDefinition { span: Some(span), .. }) if span == Span::new(0, 2)
+        ));
+        assert!(matches!(
+            eval_str("2 = 3"),
+            Err(MathError::InvalidDefinition { span: Some(span), .. }) if span == Span::new(2, 3)
+        ));
+        assert!(matches!(
+            eval_str("x = ; x"),
+            Err(MathError::UnexpectedEnd { span: Some(span) }) if span == Span::new(3, 3)
+        ));
+
+        // statements before an error keep what they defined
+        let mut context = Context::new();
+        assert!(context.eval("x = 4; y = z").is_err());
+        assert_eq!(2f64, context.eval("sqrt(x)").unwrap());
+        assert_eq!(Some(2f64), context.ans());
+    }
+
+    #[test]
+    fn derivative_of_defined_function() {
+        let function = DefinedFunction::parse_str("f(x, y): x^3 + 2*x*y").unwrap();
+
+        let df_dx = derivative(&function, "x").unwrap();
+        assert_eq!("df_dx(x, y): 3 * x^2 + 2 * y", df_dx.to_string());
+        let df_dy = derivative(&function, "y").unwrap();
+        assert_eq!("df_dy(x, y): 2 * x", df_dy.to_string());
+
+        let function = DefinedFunction::parse_str("f(x): sin(x) / x").unwrap();
+        assert_eq!(
+            "df_dx(x): (cos(x) * x - sin(x)) / x^2",
+            derivative(&function, "x").unwrap().to_string()
+        );
+
+        assert!(matches!(
+            derivative(&function, "z"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn derivative_of_every_built_in_function() {
+        let math_definitions = MathDefinition::default_math_definitions();
+        let x = 0.7f64;
+        let h = 1e-6f64;
+
+        for (identity, math_definition) in &math_definitions {
+            let MathDefinition::BuiltInFunction(function) = math_definition else {
+                continue;
+            };
+            // the inner function checks the chain rule is applied as well
+            let input = match (identity.as_str(), function.arg_count) {
+                ("polygamma", _) => "polygamma(1, x^2)".to_string(),
+                ("pow", _) => "pow(x^2, x)".to_string(),
+                // an interval is only a value when evaluating intervals
+                ("interval", _) => continue,
+                (_, None) => format!("{identity}(x^2, 2 * x, 3)"),
+                _ => format!("{identity}(x^2)"),
+            };
+            let ast = parse_ast_str(&input).unwrap();
+            let derived = derivative_ast(&ast, "x", &math_definitions)
+                .unwrap_or_else(|error| panic!("{input} should have a derivative : {error}"));
+
+            let eval_at = |ast: &Ast, x: f64| {
+                eval_ast_with_variables(ast, &math_definitions, &HashMap::from([("x", x)])).unwrap()
+            };
+            let expected = (eval_at(&ast, x + h) - eval_at(&ast, x - h)) / (2f64 * h);
+            let received = eval_at(&derived, x);
+            assert!(
+                (expected - received).abs() < 1e-5 * expected.abs().max(1f64),
+                "the derivative of {input} is {derived}, expected {expected} but received {received}"
+            );
+        }
+    }
+
+    #[test]
+    fn eval_diff() {
+        assert_eq!(12f64, eval_str("x = 2; diff(x^3, x)").unwrap());
+        assert_eq!(1f64, eval_str("x = 0; diff(sin, x)").unwrap());
+        assert_eq!(1f64, eval_str("g(x): diff(x * sin(x), x); g(pi / 2)").unwrap().round());
+        assert_eq!(2f64, eval_str("t = 5; diff(diff(t^2, t), t)").unwrap());
+        assert_eq!(0f64, eval_str("x = 2; y = 3; diff(x^2, y)").unwrap());
+        assert_eq!(0f64, eval_str("g(x, y): diff(x^2, y); g(2, 3)").unwrap());
+
+        // an identity that is not defined is an error whichever variable the expression is differentiated by
+        for input in ["diff(x^2, x)", "diff(x^2, y)", "y = 1; diff(x^2, y)", "diff(sin(x) + y, y)"] {
+            assert!(
+                matches!(eval_str(input), Err(MathError::UnknownIdentifier { .. })),
+                "{input} should be an unknown identifier"
+            );
+        }
+        assert!(matches!(
+            eval_str_complex("y = 1; diff(x^2, y)"),
+            Err(MathError::UnknownIdentifier { span: Some(Span { start: 12, end: 13 }), .. })
+        ));
+        assert!(matches!(
+            CompiledExpression::parse_str("diff(z^2, x)", &["x"], &MathDefinition::default_math_definitions()),
+            Err(MathError::UnknownIdentifier { .. })
+        ));
+        assert!(matches!(
+            eval_gradient(
+                &parse_ast_str("diff(z^2, x)").unwrap(),
+                &MathDefinition::default_math_definitions(),
+                &HashMap::from([("x", 2f64)]),
+                &["x"]
+            ),
+            Err(MathError::UnknownIdentifier { .. })
+        ));
+
+        let compiled_expression = CompiledExpression::parse_str(
+            "diff(x^2 + y, x)",
+            &["x", "y"],
+            &MathDefinition::default_math_definitions(),
+        )
+        .unwrap();
+        assert_eq!(6f64, compiled_expression.eval(&[3f64, 1f64]).unwrap());
+
+        assert!(matches!(
+            eval_str("diff(x^2, 2)"),
+            Err(MathError::InvalidArgument { span: Some(span), .. }) if span == Span::new(10, 11)
+        ));
+        assert!(matches!(
+            eval_str("diff(x^2)"),
+            Err(MathError::ArityMismatch { expected: 2, received: 1, .. })
+        ));
+        assert!(matches!(
+            eval_str("diff = 2"),
+            Err(MathError::InvalidDefinition { .. })
+        ));
+    }
+
+    #[test]
+    fn display_ast_round_trips() {
+        for input in [
+            "1 + 2 * 3",
+            "(1 + 2) * 3",
+            "a - (b - c)",
+            "a / (b * c)",
+            "-2^x",
+            "(-2)^x",
+            "2^-x",
+            "2^3^4",
+            "(2^3)^4",
+            "(3!)!",
+            "3!!",
+            "(x + 1)!",
+            "f(x, y + 1) % 2",
+        ] {
+            let ast = parse_ast_str(input).unwrap();
+            assert_eq!(input, ast.to_string());
+            assert_eq!(ast, parse_ast_str(&ast.to_string()).unwrap());
+        }
+    }
+
+    #[test]
+    fn simplify_expressions() {
+        let simplified = |input: &str| simplify(&parse_ast_str(input).unwrap()).to_string();
+
+        assert_eq!("x", simplified("x*1"));
+        assert_eq!("x", simplified("0+x"));
+        assert_eq!("x", simplified("x^1"));
+        assert_eq!("x", simplified("--x"));
+        assert_eq!("1", simplified("x^0"));
+        assert_eq!("0", simplified("x - x"));
+        assert_eq!("7", simplified("1 + 2 * 3"));
+        assert_eq!("2", simplified("sqrt(4)"));
+        assert_eq!("5 * x", simplified("2*x + 3*x"));
+        assert_eq!("x^3", simplified("x^2 * x"));
+        assert_eq!("2 * x + 2", simplified("2*(x + 1)"));
+        assert_eq!("x^2 - 2 * x * y + 1", simplified("1 + x*x - y*x - x*y"));
+        assert_eq!("x / y", simplified("x * y^-1"));
+        assert_eq!("-x", simplified("0 - x"));
+        assert_eq!("cos(x)^2 + sin(x)^2", simplified("sin(x)^2 * 1 + cos(x)^2"));
+        assert_eq!("(x + 1)^2", simplified("(x + 1) * (1 + x)"));
+        assert_eq!("x / 0", simplified("x / 0"));
+        assert_eq!("2 * x", simplified("diff(x^2, x)"));
+    }
+
+    #[test]
+    fn simplify_to_canonical_form() {
+        let simplified = |input: &str| simplify(&parse_ast_str(input).unwrap());
+
+        for (first, second) in [
+            ("a + b", "b + a"),
+            ("a * b * c", "c * (b * a)"),
+            ("x + x + y", "2 * x + y"),
+            ("(a + b) / 2", "0.5 * b + a / 2"),
+            ("x^2 * y / x", "y * x"),
+            ("sin(x + 1)", "sin(1 + x)"),
+        ] {
+            assert_eq!(simplified(first), simplified(second), "{first} should equal {second}");
+        }
+        assert_ne!(simplified("a - b"), simplified("b - a"));
+
+        let function = DefinedFunction::parse_str("f(x): x^3 * 1 + 0").unwrap();
+        let df_dx = simplify_function(&derivative(&function, "x").unwrap());
+        assert_eq!("df_dx(x): 3 * x^2", df_dx.to_string());
+    }
+
+    #[test]
+    fn dual_gradients() {
+        let math_definitions = MathDefinition::default_math_definitions();
+        let gradient = |input: &str, variables: &[(&str, f64)], with_respect_to: &[&str]| {
+            eval_gradient(
+                &parse_ast_str(input).unwrap(),
+                &math_definitions,
+                &variables.iter().copied().collect(),
+                with_respect_to,
+            )
+        };
+
+        let result = gradient("x^2 * y + sin(y)", &[("x", 3f64), ("y", 2f64)], &["x", "y"]).unwrap();
+        assert_eq!(18f64 + 2f64.sin(), result.value);
+        assert_eq!(12f64, result.derivative(0));
+        assert_eq!(9f64 + 2f64.cos(), result.derivative(1));
+
+        // only the chosen variables get derivatives
+        let result = gradient("x * y", &[("x", 3f64), ("y", 2f64)], &["y"]).unwrap();
+        assert_eq!(vec![3f64], result.derivatives);
+
+        // a constant argument is never differentiated, so there is no NaN from the log of a negative base
+        let result = gradient("(-2)^x + x!! + polygamma(1, x)", &[("x", 2f64)], &[]).unwrap();
+        assert!(result.derivatives.is_empty());
+
+        assert!(matches!(
+            gradient("x!!", &[("x", 3f64)], &["x"]),
+            Err(MathError::Domain { .. })
+        ));
+        assert!(matches!(
+            gradient("x", &[], &["x"]),
+            Err(MathError::InvalidArgument { .. })
+        ));
+
+        // special forms are expanded, so a second derivative works
+        let result = gradient("diff(x^3, x)", &[("x", 2f64)], &["x"]).unwrap();
+        assert_eq!(12f64, result.value);
+        assert_eq!(12f64, result.derivative(0));
+
+        let function = DefinedFunction::parse_str("f(x, y): x * y^3 + x / y").unwrap();
+        let result = function.gradient(&[2f64, 1f64], &math_definitions).unwrap();
+        assert_eq!(4f64, result.value);
+        assert_eq!(vec![2f64, 4f64], result.derivatives);
+    }
+
+    #[test]
+    fn dual_gradients_match_symbolic_derivatives() {
+        let math_definitions = MathDefinition::default_math_definitions();
+
+        for (name, math_definition) in &math_definitions {
+            let MathDefinition::BuiltInFunction(function) = math_definition else {
+                continue;
+            };
+            let input = match (name.as_str(), function.arg_count) {
+                ("polygamma", _) => "polygamma(1, x^2)".to_string(),
+                ("interval", _) => continue,
+                (_, Some(2)) => format!("{name}(x, x^2)"),
+                (_, Some(_)) => format!("{name}(x^2)"),
+                (_, None) => format!("{name}(x, x^2, 3)"),
+            };
+            let ast = parse_ast_str(&input).unwrap();
+            let symbolic = eval_ast_with_variables(
+                &derivative_ast(&ast, "x", &math_definitions).unwrap(),
+                &math_definitions,
+                &HashMap::from([("x", 0.7f64)]),
+            )
+            .unwrap();
+            let dual = eval_gradient(&ast, &math_definitions, &HashMap::from([("x", 0.7f64)]), &["x"]).unwrap();
+
+            assert!(
+                (symbolic - dual.derivative(0)).abs() < 1e-9 * symbolic.abs().max(1f64),
+                "{input} : {symbolic} != {}",
+                dual.derivative(0)
+            );
+        }
+    }
+
+    #[test]
+    fn find_roots() {
+        let f = |x: f64| Ok(x * x - 2f64);
+        let df = |x: f64| Ok((x * x - 2f64, 2f64 * x));
+
+        assert!((bisection(f, 0f64, 10f64).unwrap() - consts::SQRT_2).abs() < 1e-12);
+        assert!((brent(f, 0f64, 10f64).unwrap() - consts::SQRT_2).abs() < 1e-12);
+        assert!((newton(df, 1f64).unwrap() - consts::SQRT_2).abs() < 1e-12);
+        assert_eq!(-consts::SQRT_2, brent(f, -consts::SQRT_2, 0f64).unwrap());
+
+        assert!(matches!(
+            brent(f, 2f64, 3f64),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            newton(|x| Ok((x * x + 1f64, 2f64 * x)), 0f64),
+            Err(MathError::NotConverged { .. })
+        ));
+        assert!(matches!(
+            newton(|x| Ok((x * x + 1f64, 2f64 * x)), 1f64),
+            Err(MathError::NotConverged { .. })
+        ));
+
+        let math_definitions = MathDefinition::default_math_definitions();
+        let function = DefinedFunction::parse_str("f(x): cos(x) - x").unwrap();
+        let root = function.root(0f64, 1f64, &math_definitions).unwrap();
+        assert!(function.evaluate(&[root], &math_definitions).unwrap().abs() < 1e-12);
+        let root = function.root_newton(0f64, &math_definitions).unwrap();
+        assert!(function.evaluate(&[root], &math_definitions).unwrap().abs() < 1e-12);
+    }
+
+    #[test]
+    fn eval_root_special_form() {
+        let mut context = Context::new();
+        context.define_function("f(x): x^2 - 2").unwrap();
+
+        assert!((context.eval("root(f, 0, 10)").unwrap() - consts::SQRT_2).abs() < 1e-12);
+        assert!((context.eval("root(f, -1)").unwrap() + consts::SQRT_2).abs() < 1e-12);
+        assert!((context.eval("root(cos, 0, 3)").unwrap() - consts::FRAC_PI_2).abs() < 1e-12);
+        assert!((context.eval("a = 3; root(f, a - 2, a)").unwrap() - consts::SQRT_2).abs() < 1e-12);
+
+        let input = "1 + root(f, 2, 3)";
+        let error = context.eval(input).unwrap_err();
+        assert!(matches!(error, MathError::InvalidArgument { .. }));
+        assert_eq!(Some(Span::new(4, 17)), error.span());
+        assert!(matches!(
+            context.eval("root(f + 1, 0, 10)"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            context.eval("root(pi, 0, 10)"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            context.eval("root(f, 0)"),
+            Err(MathError::NotConverged { .. })
+        ));
+        assert!(matches!(
+            context.eval("root(f)"),
+            Err(MathError::ArityMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_lambdas() {
+        assert_eq!(
+            Ast::lambda(
+                vec!["x".to_string()],
+                Ast::binary_operation(Operator::Addition, Ast::identity("x"), Ast::number(1f64))
+            ),
+            parse_ast_str("x -> x + 1").unwrap()
+        );
+
+        for input in [
+            "x -> x^2",
+            "(x, y) -> x * y",
+            "integrate(t -> t * a, 0, 1)",
+            "sum((k) -> 1 / k, 1, 10)",
+            "(x -> x)",
+        ] {
+            let ast = parse_ast_str(input).unwrap();
+            assert_eq!(ast, parse_ast_str(&ast.to_string()).unwrap());
+        }
+        assert_eq!("x -> x", parse_ast_str("(x) -> x").unwrap().to_string());
+
+        assert!(parse_ast_str("1 + x -> x").is_err());
+        assert!(parse_ast_str("(x, 1) -> x").is_err());
+        assert!(matches!(
+            eval_str("x -> x"),
+            Err(MathError::NotAValue { .. })
+        ));
+    }
+
+    #[test]
+    fn eval_integrals_sums_and_products() {
+        let close = |expected: f64, input: &str| {
+            let result = eval_str(input).unwrap();
+            assert!((expected - result).abs() < 1e-9, "{input} : {result} != {expected}");
+        };
+
+        close(2f64, "integrate(sin, 0, pi)");
+        close(9f64, "integrate(x -> x^2, 0, 3)");
+        close(-9f64, "integrate(x -> x^2, 3, 0)");
+        let singular = eval_str("integrate(x -> 1 / sqrt(x), 0, 1, 1e-6)").unwrap();
+        assert!((singular - 2f64).abs() < 1e-5);
+        close(5050f64, "sum(k -> k, 1, 100)");
+        close(3628800f64, "prod(k -> k, 1, 10)");
+        close(0f64, "sum(k -> k, 1, 0)");
+        close(1f64, "prod(k -> k, 1, 0)");
+        close(consts::PI.powi(2) / 6f64 - 1e-3 + 5e-7, "sum(k -> 1 / k^2, 1, 1000)");
+        close(consts::SQRT_2, "root(x -> x^2 - 2, 0, 10)");
+
+        // a lambda captures the arguments of the function it is in
+        let mut context = Context::new();
+        context.define_function("f(x): x^3").unwrap();
+        context.define_function("g(a): integrate(t -> a * t, 0, 1)").unwrap();
+        assert!((context.eval("g(4)").unwrap() - 2f64).abs() < 1e-12);
+        assert!((context.eval("integrate(f, 0, 2)").unwrap() - 4f64).abs() < 1e-12);
+        assert!((context.eval("n = 3; sum(k -> k^n, 1, 2)").unwrap() - 9f64).abs() < 1e-12);
+
+        assert!(matches!(
+            eval_str("integrate(x -> 1 / x, -1, 1)"),
+            Err(MathError::Domain { .. })
+        ));
+        // a point near zero is printed in scientific notation rather than as hundreds of digits
+        let Err(MathError::Domain { message, .. }) = eval_str("integrate(x -> 1 / 0, 0, 1e-300)") else {
+            panic!("a singular integrand should be a domain error");
+        };
+        assert!(message.starts_with("the integrand is inf at ") && message.ends_with("e-303"), "{message}");
+        assert!(matches!(
+            eval_str("integrate((x, y) -> x, 0, 1)"),
+            Err(MathError::ArityMismatch { .. })
+        ));
+        assert!(matches!(
+            eval_str("integrate(x -> x, 0, 1, 0)"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            eval_str("sum(k -> k, 1, 2.5)"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            eval_str("sum(1 + 2, 1, 2)"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            eval_str("sum(pi, 1, 2)"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+
+        let function = DefinedFunction::parse_str("f(x): x^2").unwrap();
+        let integral = function
+            .integrate(0f64, 1f64, &MathDefinition::default_math_definitions())
+            .unwrap();
+        assert!((integral - 1f64 / 3f64).abs() < 1e-12);
+        let integral = integrate(|x| Ok(x.exp()), 0f64, 1f64, 1e-12).unwrap();
+        assert!((integral - (consts::E - 1f64)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn eval_special_forms_with_variables() {
+        let mut context = Context::new();
+        context.define_function("f(a): sum(k -> k * a, 1, 3)").unwrap();
+        context.define_function("sq(t): t^2").unwrap();
+        let math_definitions = context.math_definitions();
+
+        // a lambda passed to a special form can use the variables of every evaluator
+        let eval_everywhere = |input: &str, expected: f64| {
+            let ast = parse_ast_str(input).unwrap();
+            let variables = HashMap::from([("x", 2f64)]);
+            let evaluated = eval_ast_with_variables(&ast, math_definitions, &variables).unwrap();
+            let compiled = CompiledExpression::compile(&ast, &["x"], math_definitions).unwrap().eval(&[2f64]).unwrap();
+            let gradient = eval_gradient(&ast, math_definitions, &variables, &["x"]).unwrap();
+            for result in [evaluated, compiled, gradient.value] {
+                assert!((expected - result).abs() < 1e-12, "{input} : {result} != {expected}");
+            }
+            gradient.derivative(0)
+        };
+
+        assert_eq!(6f64, eval_everywhere("sum(k -> k * x, 1, 3)", 12f64));
+        assert_eq!(7f64, eval_everywhere("prod(k -> k + x, 1, 2)", 12f64));
+        assert_eq!(0f64, eval_everywhere("sum(k -> k, x, 3)", 5f64));
+        assert_eq!(7f64, eval_everywhere("sum(j -> sum(k -> j * k * x, 1, j), 1, 2)", 14f64));
+        assert_eq!(6f64, eval_everywhere("f(x)", 12f64));
+        assert_eq!(6f64, eval_everywhere("sum(k -> k * x, 1, 3) + sum(k -> k, 1, 2) - 3", 12f64));
+        eval_everywhere("integrate(t -> t * x, 0, 1)", 1f64);
+        eval_everywhere("integrate(t -> t * x, 0, x, 1e-6)", 4f64);
+        eval_everywhere("integrate(sin, 0, x)", 1f64 - 2f64.cos());
+        assert_eq!(0f64, eval_everywhere("sum(sq, 1, x)", 5f64));
+        eval_everywhere("root(t -> t - x, 0, 10)", 2f64);
+        eval_everywhere("root(t -> t^2 - x, 1)", consts::SQRT_2);
+        eval_everywhere("root(t -> t^3 - x * t, x - 1, x + 1) * x", 2f64 * consts::SQRT_2);
+        eval_everywhere("root(cos, x - 1, x)", consts::FRAC_PI_2);
+        assert_eq!(1.5f64, eval_everywhere("mean(map(t -> t * x, 1, 2))", 3f64));
+        assert_eq!(3f64, eval_everywhere("fold((p, q) -> p + q * x, 0, 1, 2)", 6f64));
+        assert_eq!(3f64, eval_everywhere("sum(k -> mean(map(t -> t * k * x, 0, 1)), 1, 3)", 6f64));
+
+        // a variable is not a function even if a function has its name
+        let shadowed = HashMap::from([("sq", 2f64)]);
+        for input in ["mean(map(sq, 1))", "fold(sq, 0, 1)", "sum(sq, 1, 2)", "root(sq, 0, 1)"] {
+            let ast = parse_ast_str(input).unwrap();
+            let not_a_function = |result: Result<f64, MathError>| matches!(result, Err(MathError::InvalidArgument { .. }));
+            assert!(not_a_function(eval_ast_with_variables(&ast, math_definitions, &shadowed)), "{input}");
+            assert!(not_a_function(CompiledExpression::compile(&ast, &["sq"], math_definitions).and_then(|compiled| compiled.eval(&[2f64]))), "{input}");
+            assert!(not_a_function(eval_gradient(&ast, math_definitions, &shadowed, &["sq"]).map(|dual| dual.value)), "{input}");
+        }
+
+        assert!(matches!(
+            CompiledExpression::parse_str("sum(k -> k * x, 1, 2.5)", &["x"], context.math_definitions()).unwrap().eval(&[2f64]),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            CompiledExpression::parse_str("sum(k -> k * x, 1)", &["x"], context.math_definitions()),
+            Err(MathError::ArityMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn eval_higher_order_functions() {
+        let mut context = Context::new();
+        let mut eval = |input: &str| context.eval(input);
+
+        assert_eq!(14f64 / 3f64, eval("mean(map(x -> x^2, 1, 2, 3))").unwrap());
+        assert_eq!(24f64, eval("fold((a, b) -> a * b, 1, 2, 3, 4)").unwrap());
+        assert_eq!(5f64, eval("fold((a, b) -> a * b, 5)").unwrap());
+        assert_eq!(4f64, eval("map(x -> x^2, 2)").unwrap());
+        assert_eq!(2f64, eval("max = 3; mean(map(x -> x * max, 0, 1, map(x -> x + 1, 0)))").unwrap());
+
+        // functions are values that can be given names
+        assert_eq!(None, context.execute("sq = x -> x^2; s = sin; p = pow").unwrap());
+        assert_eq!(14f64, context.eval("fold((a, b) -> a + b, 0, map(sq, 1, 2, 3))").unwrap());
+        assert_eq!(0f64, context.eval("s(0)").unwrap());
+        assert_eq!(8f64, context.eval("p(2, 3)").unwrap());
+        let MathDefinition::DefinedFunction(p) = &context.math_definitions()["p"] else {
+            panic!("p should be a defined function");
+        };
+        assert_eq!("p(x1, x2): pow(x1, x2)", p.to_string());
+        assert!(matches!(
+            context.execute("m = mean"),
+            Err(MathError::InvalidDefinition { .. })
+        ));
+        assert!(matches!(
+            context.execute("sin = x -> x"),
+            Err(MathError::InvalidDefinition { .. })
+        ));
+
+        // the arity of a function value is checked when it is passed
+        let input = "map((a, b) -> a, 1, 2)";
+        let error = context.eval(input).unwrap_err();
+        assert!(matches!(error, MathError::ArityMismatch { expected: 2, received: 1, .. }));
+        assert_eq!(Some(Span::new(4, 15)), error.span());
+        assert!(matches!(
+            context.eval("integrate(pow, 0, 1)"),
+            Err(MathError::ArityMismatch { .. })
+        ));
+        assert!(matches!(
+            context.eval("fold(sin, 0, 1)"),
+            Err(MathError::ArityMismatch { .. })
+        ));
+        assert!(matches!(
+            context.eval("map(x -> x, 1, 2)"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+
+        // a map expands into its values, so it can be differentiated and compiled
+        assert_eq!(2f64, context.eval("x = 5; diff(mean(map(t -> t * x, 1, 2, 3)), x)").unwrap());
+        let ast = parse_ast_str("fold((a, b) -> a * b, 1, map(sq, x, x))").unwrap();
+        let result = eval_gradient(
+            &ast,
+            context.math_definitions(),
+            &HashMap::from([("x", 3f64)]),
+            &["x"],
+        )
+        .unwrap();
+        assert_eq!(81f64, result.value);
+        assert_eq!(108f64, result.derivative(0));
+    }
+
+    #[test]
+    fn eval_recursive_functions() {
+        let mut context = Context::new();
+
+        // a sum over an empty range is the base case
+        context.execute("f(n): sum(k -> 1 + f(k - 1), 1, n)").unwrap();
+        assert_eq!(1023f64, context.eval("f(10)").unwrap());
+
+        // functions defined in the same program can invoke each other
+        assert_eq!(81f64, context.eval("g(n): 1 + sum(k -> h(k - 1), 1, n); h(n): 2 * g(n); g(4)").unwrap());
+
+        // bodies are validated against the definitions of the context
+        assert_eq!(7f64, context.eval("a = 2; m(x): a * x + f(0); m2(x): m(x) + 1; m2(3)").unwrap());
+        assert!(matches!(
+            context.execute("u(x): x + y"),
+            Err(MathError::UnknownIdentifier { identifier, .. }) if identifier == "y"
+        ));
+        assert!(matches!(
+            context.execute("u(x): a(x)"),
+            Err(MathError::NotAFunction { identifier, .. }) if identifier == "a"
+        ));
+        assert!(matches!(
+            context.execute("u(x): v(x)"),
+            Err(MathError::UnknownIdentifier { identifier, .. }) if identifier == "v"
+        ));
+        assert!(DefinedFunction::parse_str("f(x): pi * f(x - 1)").is_ok());
+        assert!(DefinedFunction::parse_str("f(x): g(x)").is_err());
+
+        // recursion that never stops errors instead of overflowing the stack
+        let input = "loop(x): loop(x) + 1; 2 * loop(1)";
+        let error = context.eval(input).unwrap_err();
+        assert_eq!(MathError::RecursionLimit { span: Some(Span::new(26, 33)), limit: DEFAULT_MAX_RECURSION_DEPTH }, error);
+        assert!(matches!(
+            CompiledExpression::parse_str("loop(x)", &["x"], context.math_definitions()).unwrap().eval(&[1f64]),
+            Err(MathError::RecursionLimit { .. })
+        ));
+        assert!(matches!(
+            context.eval("diff(loop(x), x)"),
+            Err(MathError::RecursionLimit { .. })
+        ));
+        assert!(matches!(
+            context.eval("root(x -> loop(x), 0, 1)"),
+            Err(MathError::RecursionLimit { .. })
+        ));
+        assert_eq!(1023f64, context.eval("f(10)").unwrap());
+
+        // the limit can be changed
+        let mut context = context.with_max_recursion_depth(8);
+        assert_eq!(8, context.max_recursion_depth());
+        assert_eq!(7f64, context.eval("f(3)").unwrap());
+        assert!(matches!(
+            context.eval("f(10)"),
+            Err(MathError::RecursionLimit { limit: 8, .. })
+        ));
+        assert_eq!(DEFAULT_MAX_RECURSION_DEPTH, max_recursion_depth());
+        with_max_recursion_depth(2, || {
+            assert!(matches!(
+                eval_ast(&parse_ast_str("loop(1)").unwrap(), context.math_definitions()),
+                Err(MathError::RecursionLimit { limit: 2, .. })
+            ));
+        });
+        assert_eq!(DEFAULT_MAX_RECURSION_DEPTH, max_recursion_depth());
+
+        // deep recursion grows the stack instead of overflowing it, up to a limit that can not be raised further
+        let mut context = Context::new();
+        context.execute("fact(n): if(n < 1, 1, n * fact(n - 1))").unwrap();
+        let product = (1..=100).fold(1f64, |acc, n| n as f64 * acc);
+        assert_eq!(product, context.eval("fact(100)").unwrap());
+        assert!(matches!(
+            context.eval("diff(fact(x), x)"),
+            Err(MathError::RecursionLimit { limit: MAX_INLINING_DEPTH, .. })
+        ));
+        let mut context = context.with_max_recursion_depth(700);
+        assert!(matches!(
+            context.eval("fact(100000)"),
+            Err(MathError::RecursionLimit { limit: 700, .. })
+        ));
+        let mut context = context.with_max_recursion_depth(usize::MAX);
+        assert_eq!(MAX_RECURSION_DEPTH_LIMIT, context.max_recursion_depth());
+        assert!(matches!(
+            context.eval("fact(100000)"),
+            Err(MathError::RecursionLimit { limit: MAX_RECURSION_DEPTH_LIMIT, .. })
+        ));
+        assert_eq!(f64::INFINITY, context.eval("fact(5000)").unwrap());
+        with_max_recursion_depth(usize::MAX, || {
+            assert_eq!(MAX_RECURSION_DEPTH_LIMIT, max_recursion_depth());
+        });
+    }
+
+    #[test]
+    fn eval_compiled_recursive_functions() {
+        let mut context = Context::new();
+        context.execute("fib(n): if(n < 2, n, fib(n - 1) + fib(n - 2)); fact(n): n <= 1 ? 1 : n * fact(n - 1)").unwrap();
+        context.execute("even(n): n == 0 ? 1 : odd(n - 1); odd(n): n == 0 ? 0 : even(n - 1)").unwrap();
+        let compile = |input: &str| CompiledExpression::parse_str(input, &["x"], context.math_definitions()).unwrap();
+
+        // a recursive function is compiled once and invoked when the expression is evaluated
+        let fib = compile("fib(x)");
+        assert_eq!(6765f64, fib.eval(&[20f64]).unwrap());
+        assert_eq!(eval_ast(&parse_ast_str("fib(15)").unwrap(), context.math_definitions()).unwrap(), fib.eval(&[15f64]).unwrap());
+        let fact = compile("fact(x)");
+        let product = (1..=100).fold(1f64, |acc, n| n as f64 * acc);
+        assert_eq!(product, fact.eval(&[100f64]).unwrap());
+        assert_eq!(10f64, compile("fact(x) / fact(x - 1)").eval(&[10f64]).unwrap());
+        assert_eq!(2f64 * 6765f64 + 1f64, compile("fib(x) + fib(20) + 1").eval(&[20f64]).unwrap());
+
+        // functions that invoke each other
+        let even = compile("even(x)");
+        assert_eq!(1f64, even.eval(&[10f64]).unwrap());
+        assert_eq!(0f64, even.eval(&[7f64]).unwrap());
+
+        // recursion is limited when the expression is evaluated
+        assert!(matches!(
+            fact.eval(&[5000f64]),
+            Err(MathError::RecursionLimit { limit: DEFAULT_MAX_RECURSION_DEPTH, .. })
+        ));
+        with_max_recursion_depth(8, || {
+            assert_eq!(5040f64, fact.eval(&[7f64]).unwrap());
+            assert!(matches!(fact.eval(&[20f64]), Err(MathError::RecursionLimit { limit: 8, .. })));
+        });
+        with_max_recursion_depth(MAX_RECURSION_DEPTH_LIMIT, || {
+            assert_eq!(f64::INFINITY, fact.eval(&[5000f64]).unwrap());
+        });
+    }
+
+    #[test]
+    fn eval_comparisons_and_conditionals() {
+        let eval = |input: &str| eval_str(input).unwrap();
+
+        // comparisons bind looser than arithmetic, && binds tighter than || and ! is a prefix like a sign
+        assert_eq!(1f64, eval("1 + 1 == 2"));
+        assert_eq!(1f64, eval("2 < 3 && 3 <= 3 && 4 >= 5 - 1 && 5 > -1"));
+        assert_eq!(0f64, eval("2 != 2"));
+        assert_eq!(1f64, eval("1 || 0 && 0"));
+        assert_eq!(1f64, eval("!0 && !!1 && !(1 && 0)"));
+        assert_eq!(1f64, eval("3! == 6 && true && !false"));
+        assert_eq!(1f64, eval("1 < 2 == 3 < 4"));
+
+        // "!=" is always lexed as not equal, even after an operand that could take a factorial
+        assert_eq!(1f64, eval("5!=120"));
+        assert_eq!(eval("5 != 120"), eval("5!=120"));
+        assert_eq!(1f64, eval("5! == 120"));
+        assert_eq!(0f64, eval("(5!)!=120"));
+        let options = ParseOptions {
+            implicit_multiplication: true,
+            ..Default::default()
+        };
+        assert_eq!(1f64, eval_str_with_options("!0 && 2pi > 6 && 3!2 == 12 && ! !1", &options).unwrap());
+
+        // only the chosen branch and the operands that decide a logical operator are evaluated
+        assert_eq!(3f64, eval("x = -3; x > 0 ? x : -x"));
+        assert_eq!(-1f64, eval("x = -2; x > 0 ? 1 : x < 0 ? -1 : 0"));
+        assert_eq!(30f64, eval("sum(k -> k % 2 == 0 ? k : 0, 1, 10)"));
+        assert_eq!(2f64, eval("if(0, unknown, 2)"));
+        assert_eq!(0f64, eval("0 && unknown"));
+        assert_eq!(1f64, eval("1 || unknown"));
+        assert_eq!(610f64, eval("fib(n): if(n < 2, n, fib(n - 1) + fib(n - 2)); fib(15)"));
+        assert_eq!(120f64, eval("fact(n): n <= 1 ? 1 : n * fact(n - 1); fact(5)"));
+
+        // a condition must be a boolean
+        assert!(matches!(
+            eval_str("if(2, 1, 0)"),
+            Err(MathError::InvalidArgument { span: Some(span), .. }) if span == Span::new(3, 4)
+        ));
+        assert!(matches!(eval_str("2 && 1"), Err(MathError::InvalidArgument { .. })));
+        assert!(matches!(eval_str("!3"), Err(MathError::InvalidArgument { .. })));
+        assert!(matches!(
+            eval_str("if(1)"),
+            Err(MathError::ArityMismatch { expected: 3, received: 1, .. })
+        ));
+
+        // comparisons can not be chained and a conditional needs both branches
+        assert!(matches!(
+            parse_ast_str("1 < 2 < 3"),
+            Err(MathError::UnexpectedToken { span: Some(span), .. }) if span == Span::new(6, 7)
+        ));
+        assert!(matches!(parse_ast_str("1 ? 2"), Err(MathError::UnexpectedEnd { .. })));
+        assert!(matches!(parse_ast_str("1 ? 2 , 3"), Err(MathError::UnexpectedToken { .. })));
+
+        let chained = Ast::binary_operation(
+            Operator::LessThan,
+            Ast::binary_operation(Operator::LessThan, Ast::identity("a"), Ast::identity("b")),
+            Ast::identity("c"),
+        );
+        assert_eq!("(a < b) < c", chained.to_string());
+        for input in ["!(a && b) || c", "a == b < c", "-!a", "x > 0 ? 1 : 0"] {
+            let ast = parse_ast_str(input).unwrap();
+            assert_eq!(ast, parse_ast_str(&ast.to_string()).unwrap(), "{input}");
+        }
+
+        // conditionals can be differentiated, compiled and simplified
+        assert_eq!(4f64, eval("x = 2; diff(x > 0 ? x^2 : -x, x)"));
+        let math_definitions = MathDefinition::default_math_definitions();
+        let result = eval_gradient(
+            &parse_ast_str("if(x > 1, x^2, x)").unwrap(),
+            &math_definitions,
+            &HashMap::from([("x", 3f64)]),
+            &["x"],
+        )
+        .unwrap();
+        assert_eq!(6f64, result.derivative(0));
+        let compiled = CompiledExpression::parse_str("x > 0 ? x : -x", &["x"], &math_definitions).unwrap();
+        assert_eq!(3f64, compiled.eval(&[-3f64]).unwrap());
+        assert_eq!(2f64, compiled.eval(&[2f64]).unwrap());
+        assert_eq!("if(x > 0, 2 * x, 0)", simplify(&parse_ast_str("if(x > 0, x + x, 0)").unwrap()).to_string());
+        assert_eq!("2 * x", simplify(&parse_ast_str("1 < 2 ? x + x : y").unwrap()).to_string());
+    }
+
+    #[test]
+    fn eval_piecewise_functions() {
+        let math_definitions = MathDefinition::default_math_definitions();
+        let function =
+            DefinedFunction::parse(parse_str("f(x): x^2 when x < 0; sqrt(x) otherwise").unwrap()).unwrap();
+        assert_eq!(9f64, function.evaluate(&[-3f64], &math_definitions).unwrap());
+        assert_eq!(2f64, function.evaluate(&[4f64], &math_definitions).unwrap());
+        assert_eq!("f(x): if(x < 0, x^2, sqrt(x))", function.to_string());
+
+        // the cases are checked in order and can be separated by newlines
+        let mut context = Context::new();
+        assert_eq!(
+            4f64,
+            context
+                .eval("h(x): 1 when x > 0; 2 when x > 1; 3 otherwise\nh(5) + h(-5)")
+                .unwrap()
+        );
+        assert_eq!(
+            -1f64,
+            context
+                .eval("s(x): -1 when x < 0\n  0 when x == 0\n  1 otherwise\ns(-5) + s(0) * s(7)")
+                .unwrap()
+        );
+        assert_eq!(720f64, context.eval("fact(n): 1 when n <= 1; n * fact(n - 1) otherwise; fact(6)").unwrap());
+        context.add_function(function).unwrap();
+        assert_eq!(-6f64, context.eval("x = -3; diff(f(x), x)").unwrap());
+
+        // a function without an otherwise case errors where none of its cases apply
+        context.execute("g(x): -1 when x < 0; 1 when x > 0").unwrap();
+        assert_eq!(1f64, context.eval("g(2)").unwrap());
+        assert_eq!(2f64, eval_str("if(1, 2)").unwrap());
+        assert!(matches!(eval_str("if(0, 2)"), Err(MathError::Domain { .. })));
+        assert!(matches!(
+            context.eval("1 + g(0)"),
+            Err(MathError::Domain { span: Some(span), .. }) if span == Span::new(4, 8)
+        ));
+
+        assert!(matches!(
+            DefinedFunction::parse_str("f(x): 1 otherwise; 2 when x > 0"),
+            Err(MathError::InvalidDefinition { span: Some(span), .. }) if span == Span::new(8, 17)
+        ));
+        assert!(matches!(
+            DefinedFunction::parse_str("f(x): 1 when x > 0; 2"),
+            Err(MathError::InvalidDefinition { span: Some(span), .. }) if span == Span::new(20, 21)
+        ));
+        assert!(matches!(
+            DefinedFunction::parse_str("f(x): when x > 0"),
+            Err(MathError::UnexpectedEnd { .. })
+        ));
+    }
+
+    #[test]
+    fn eval_complex_numbers() {
+        // real evaluation is unchanged, i only has a value when evaluating complex numbers
+        assert!(eval_str("sqrt(-1)").unwrap().is_nan());
+        assert!(matches!(eval_str("2 + i"), Err(MathError::Domain { span: Some(span), .. }) if span == Span::new(4, 5)));
+        assert!(matches!(Context::new().set_variable("i", 1f64), Err(MathError::InvalidDefinition { .. })));
+
+        assert_eq!(Complex::new(0f64, 1f64), eval_str_complex("sqrt(-1)").unwrap());
+        assert_eq!(Complex::new(0f64, 2f64), eval_str_complex("sqrt(-4)").unwrap());
+        assert_eq!(Complex::new(2f64.ln(), std::f64::consts::PI), eval_str_complex("ln(-2)").unwrap());
+        assert_eq!(Complex::real(-1f64), eval_str_complex("i^2").unwrap());
+        assert_eq!(Complex::new(-0.5f64, 1f64), eval_str_complex("(3 + 4i) / (2 - 4i)").unwrap());
+        assert_eq!(Complex::real(2f64), eval_str_complex("3 - 1").unwrap());
+        assert_eq!(Complex::real(eval_str("sin(pi / 6)").unwrap()), eval_str_complex("sin(pi / 6)").unwrap());
+        assert!(eval_str_complex("e^(i * pi) + 1").unwrap().abs() < 1e-15);
+
+        // the parts of a complex number
+        assert_eq!(Complex::real(3f64), eval_str_complex("re(3 - 4i)").unwrap());
+        assert_eq!(Complex::real(-4f64), eval_str_complex("im(3 - 4i)").unwrap());
+        assert_eq!(Complex::new(3f64, 4f64), eval_str_complex("conj(3 - 4i)").unwrap());
+        assert_eq!(Complex::real(5f64), eval_str_complex("abs(3 - 4i)").unwrap());
+        assert_eq!(Complex::real(std::f64::consts::FRAC_PI_2), eval_str_complex("arg(2i)").unwrap());
+        assert_eq!(0f64, eval_str("im(-3)").unwrap());
+        assert_eq!(std::f64::consts::PI, eval_str("arg(-3)").unwrap());
+
+        // complex variables and functions last for the session, but only have values when evaluating complex numbers
+        let mut context = Context::new();
+        assert_eq!(Complex::new(1f64, 1f64), context.eval_complex("z = 1 + i; f(w): w * conj(w); z").unwrap());
+        assert_eq!(Complex::real(2f64), context.eval_complex("f(z)").unwrap());
+        assert_eq!(Some(Complex::real(2f64)), context.complex_ans());
+        assert_eq!(2f64, context.eval("ans").unwrap());
+        assert!(matches!(context.eval("z + 1"), Err(MathError::Domain { .. })));
+        assert_eq!(Complex::new(0f64, 2f64), context.eval_complex("z^2").unwrap());
+        assert_eq!(Complex::new(0f64, 1f64), context.eval_complex("sum(k -> i^k, 0, 2)").unwrap());
+        assert_eq!(Complex::new(0f64, 2f64), context.eval_complex("im(z) > 0 ? 2i : -2i").unwrap());
+
+        // ordering is only defined for real numbers
+        assert!(matches!(eval_str_complex("i < 1"), Err(MathError::Domain { .. })));
+        assert!(matches!(eval_str_complex("gamma(i)"), Err(MathError::Domain { .. })));
+        assert_eq!(Complex::real(1f64), eval_str_complex("i == sqrt(-1)").unwrap());
+
+        assert_eq!("2 - 0.5i", Complex::new(2f64, -0.5f64).to_string());
+        assert_eq!("-i", Complex::new(0f64, -1f64).to_string());
+        assert_eq!("3", Complex::real(3f64).to_string());
+        assert_eq!("-1 + 1.2246467991473532e-16i", eval_str_complex("e^(i*pi)").unwrap().to_string());
+        assert_eq!("1e20 - 2.5e-7i", Complex::new(1e20f64, -2.5e-7f64).to_string());
+    }
+
+    /// whole numbers, a backend that only supports the built in functions that keep a whole number whole.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Integer(i64);
+    impl std::fmt::Display for Integer {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+    impl Integer {
+        fn checked(result: Option<i64>) -> Result<Self, MathError> {
+            result.map(Self).ok_or(MathError::Domain {
+                span: None,
+                message: "not a whole number".to_string(),
+            })
+        }
+    }
+    impl Numeric for Integer {
+        const NAME: &'static str = "integer";
+
+        fn from_f64(num: f64) -> Result<Self, MathError> {
+            Self::checked(Some(num as i64).filter(|_| num.fract() == 0f64))
+        }
+        fn to_f64(&self) -> Result<f64, MathError> {
+            Ok(self.0 as f64)
+        }
+        fn to_variable(&self) -> MathDefinition {
+            MathDefinition::Variable(self.0 as f64)
+        }
+        fn try_add(&self, other: &Self) -> Result<Self, MathError> {
+            Self::checked(self.0.checked_add(other.0))
+        }
+        fn try_sub(&self, other: &Self) -> Result<Self, MathError> {
+            Self::checked(self.0.checked_sub(other.0))
+        }
+        fn try_mul(&self, other: &Self) -> Result<Self, MathError> {
+            Self::checked(self.0.checked_mul(other.0))
+        }
+        fn try_div(&self, other: &Self) -> Result<Self, MathError> {
+            Self::checked(self.0.checked_rem(other.0).filter(|rem| *rem == 0).and(self.0.checked_div(other.0)))
+        }
+        fn try_rem(&self, other: &Self) -> Result<Self, MathError> {
+            Self::checked(self.0.checked_rem(other.0))
+        }
+        fn try_pow(&self, exponent: &Self) -> Result<Self, MathError> {
+            Self::checked(u32::try_from(exponent.0).ok().and_then(|exponent| self.0.checked_pow(exponent)))
+        }
+        fn try_neg(&self) -> Result<Self, MathError> {
+            Self::checked(self.0.checked_neg())
+        }
+        fn partial_compare(&self, other: &Self) -> Result<Option<std::cmp::Ordering>, MathError> {
+            Ok(Some(self.0.cmp(&other.0)))
+        }
+        fn supports_built_in(identity: &str) -> bool {
+            identity == "abs"
+        }
+        fn evaluate_built_in(_identity: &str, _function: &BuiltInFunction, args: &[Self]) -> Result<Self, MathError> {
+            Self::checked(args[0].0.checked_abs())
+        }
+    }
+
+    #[test]
+    fn eval_numeric_backends() {
+        // the same tree can be evaluated over any backend
+        let ast = parse_ast_str("sum(k -> k^2, 1, 3) / 3").unwrap();
+        let math_definitions = MathDefinition::default_math_definitions();
+        assert_eq!(14f64 / 3f64, eval_ast_numeric::<f64>(&ast, &math_definitions).unwrap());
+        assert_eq!(14f32 / 3f32, eval_ast_numeric::<f32>(&ast, &math_definitions).unwrap());
+        assert_eq!(Complex::real(14f64 / 3f64), eval_ast_numeric::<Complex>(&ast, &math_definitions).unwrap());
+        assert!(matches!(eval_ast_numeric::<Integer>(&ast, &math_definitions), Err(MathError::Domain { .. })));
+
+        assert_eq!(0.1f32 + 0.2f32, eval_str_numeric::<f32>("0.1 + 0.2").unwrap());
+        assert_eq!(eval_str("x = 2; root(t -> t^2 - x, 0, 2)").unwrap(), eval_str_numeric::<f64>("x = 2; root(t -> t^2 - x, 0, 2)").unwrap());
+        assert_eq!(2f32.sqrt(), eval_str_numeric::<f32>("sqrt(2)").unwrap());
+        assert_eq!(Integer(42), eval_str_numeric::<Integer>("abs(6 * -7)").unwrap());
+        assert_eq!(Integer(720), eval_str_numeric::<Integer>("fact(n): n <= 1 ? 1 : n * fact(n - 1); fact(6)").unwrap());
+
+        // special forms see the arguments of the function they are in over every backend
+        fn captured<N: Numeric>(input: &str) -> f64 {
+            eval_str_numeric::<N>(input).unwrap().to_f64().unwrap()
+        }
+        let input = "g(a): integrate(x -> a * x, 0, 1) + root(x -> x - a, 0, 10) + sum(k -> a * k, 1, 2); g(2)";
+        for value in [captured::<f64>(input), captured::<Complex>(input), captured::<Rational>(input), captured::<Decimal>(input), captured::<Interval>(input)] {
+            assert!((value - 9f64).abs() < 1e-9, "{value}");
+        }
+        assert!((captured::<Complex>("h(a): integrate(x -> a, 0, 1); h(2 + 0i)") - 2f64).abs() < 1e-9);
+        let error = eval_str_complex("h(a): root(x -> x - a, 0, 10); h(2 + 3i)").unwrap_err();
+        assert_eq!(Some(Span::new(31, 40)), error.span());
+        assert_eq!("expected a real number but found 2 + 3i", error.to_string());
+
+        // a backend declares the built in functions it supports
+        let error = eval_str_numeric::<Integer>("1 + sqrt(4)").unwrap_err();
+        assert_eq!(Some(Span::new(4, 11)), error.span());
+        assert_eq!("\"sqrt\" is not supported when evaluating integer numbers", error.to_string());
+
+        // the variables a program assigns are stored by the backend
+        let mut context = Context::new();
+        assert_eq!(1f32 / 3f32, context.eval_numeric::<f32>("third = 1 / 3").unwrap());
+        assert_eq!((1f32 / 3f32) as f64, context.eval("third").unwrap());
+        assert_eq!(Integer(12), context.eval_numeric::<Integer>("n = 3; n * 4").unwrap());
+        assert_eq!(Some(12f64), context.ans());
+    }
+
+    #[test]
+    fn eval_rational_numbers() {
+        let rational = |input: &str| eval_str_rational(input).unwrap().to_string();
+
+        // fractions stay exact through + - * / and integer powers
+        assert_eq!("1/2", rational("1/3 + 1/6"));
+        assert_eq!("3/10", rational("0.1 + 0.2"));
+        assert_eq!("1", rational("0.1 + 0.2 == 0.3"));
+        assert_eq!("1/4", rational("2^-2"));
+        assert_eq!("-8/27", rational("(-2/3)^3"));
+        assert_eq!("1267650600228229401496703205376", rational("2^100"));
+        assert_eq!("123456789012345678901", rational("123456789012345678901"));
+        assert_eq!("12345678901234567890123/1000", rational("12345678901234567890.123"));
+        assert_eq!("3/2000", rational("1.5e-3"));
+        assert_eq!("1", rational("1e400 / 10^400"));
+        assert_eq!("18446744073709551617", rational("0x1_0000_0000_0000_0001"));
+        assert_eq!("123456789012345678902", rational("f(x): x + 1; f(123456789012345678901)"));
+        assert_eq!("1/3", rational("-5/3 % 2 + 2"));
+        assert_eq!("120", rational("5!"));
+        assert_eq!("1", rational("1/2 < 2/3 && true"));
+        assert!(matches!(eval_str_rational("1 / (1/2 - 0.5)"), Err(MathError::Domain { .. })));
+
+        // a rational root is exact, an irrational value falls back to a float
+        assert_eq!("2/3", rational("sqrt(4/9)"));
+        assert_eq!("4", rational("8^(2/3)"));
+        assert_eq!("-1/2", rational("cbrt(-1/8)"));
+        assert_eq!("5/2", rational("mean(1, 2, 3, 4)"));
+        assert_eq!(Rational::Float(1f64.sin()), eval_str_rational("sin(1)").unwrap());
+        assert_eq!(Rational::Float(2f64.sqrt() + 1f64), eval_str_rational("sqrt(2) + 1").unwrap());
+        assert_eq!(Rational::Float(std::f64::consts::PI / 2f64), eval_str_rational("pi / 2").unwrap());
+        assert_eq!("5/2", rational("sum(k -> k / 2, 0, 2) + 1"));
+
+        // or errors when the fallback is to error
+        with_rational_fallback(RationalFallback::Error, || {
+            assert!(matches!(eval_str_rational("sin(1)"), Err(MathError::Domain { .. })));
+            assert!(matches!(eval_str_rational("2^(1/2)"), Err(MathError::Domain { .. })));
+            assert!(matches!(eval_str_rational("pi"), Err(MathError::Domain { .. })));
+            assert_eq!("1/2", rational("sqrt(1/4)"));
+        });
+        assert_eq!(RationalFallback::Float, rational_fallback());
+
+        // a context keeps its own fallback, whatever the fallback of the thread
+        let mut strict = Context::new().with_rational_fallback(RationalFallback::Error);
+        let mut lenient = Context::new();
+        assert_eq!(RationalFallback::Error, strict.rational_fallback());
+        assert!(matches!(strict.eval_numeric::<Rational>("sin(1)"), Err(MathError::Domain { .. })));
+        assert_eq!(Rational::Float(1f64.sin()), lenient.eval_numeric::<Rational>("sin(1)").unwrap());
+        assert_eq!(RationalFallback::Float, rational_fallback());
+        with_rational_fallback(RationalFallback::Error, || {
+            assert_eq!(Rational::Float(1f64.sin()), lenient.eval_numeric::<Rational>("sin(1)").unwrap());
+        });
+
+        // exact variables last for the session, and are the nearest float when evaluating real numbers
+        let mut context = Context::new();
+        assert_eq!(Rational::fraction(1, 3), context.eval_numeric::<Rational>("third = 1/3; third").unwrap());
+        assert_eq!(Rational::integer(1), context.eval_numeric::<Rational>("third * 3").unwrap());
+        assert_eq!(1f64 / 3f64, context.eval("third").unwrap());
+        assert_eq!(Rational::fraction(1, 3), context.eval_numeric::<Rational>("f(x): x - 1; f(4/3)").unwrap());
+        assert!(matches!(context.math_definitions().get(ANS), Some(MathDefinition::NumericVariable(_))));
+    }
+
+    #[test]
+    fn eval_decimal_numbers() {
+        let decimal = |input: &str| eval_str_decimal(input).unwrap().to_string();
+
+        // decimal literals are exact, unlike floats
+        assert_eq!("0.3", decimal("0.1 + 0.2"));
+        assert_eq!("1", decimal("0.1 + 0.2 == 0.3"));
+        assert_eq!(0f64, eval_str("0.1 + 0.2 == 0.3").unwrap());
+        assert_eq!("19.99", decimal("3 * 6.66 + 0.01"));
+        assert_eq!("1", decimal("-7 % 3 + 2"));
+        assert_eq!("0.25", decimal("2^-2"));
+        assert_eq!("15511210043330985984000000", decimal("25!"));
+        assert!(matches!(eval_str_decimal("1 / (0.3 - 0.1 - 0.2)"), Err(MathError::Domain { .. })));
+        assert!(matches!(eval_str_decimal("sqrt(-1)"), Err(MathError::Domain { .. })));
+
+        // results are rounded to the precision, 28 significant digits unless another is set
+        assert_eq!("0.3333333333333333333333333333", decimal("1/3"));
+        assert_eq!("0.6666666666666666666666666667", decimal("2/3"));
+        assert_eq!("1.414213562373095048801688724", decimal("sqrt(2)"));
+        assert_eq!("0.5", decimal("sqrt(0.25)"));
+        assert_eq!("1.5", decimal("mean(1, 2)"));
+
+        // a literal keeps every digit it is written with, and a fractional power is computed to the precision
+        assert_eq!("12345678901234567890.123", decimal("12345678901234567890.123"));
+        assert_eq!("0.123", decimal("12345678901234567890.123 - 12345678901234567890"));
+        assert_eq!(decimal("sqrt(2)"), decimal("2^0.5"));
+        assert_eq!(decimal("sqrt(10)"), decimal("10^0.5"));
+        assert_eq!(decimal("sqrt(2e-301)"), decimal("2e-301^0.5"));
+        assert_eq!("1.000000000000000000000000023E-100", decimal("1e-300^(1/3)"));
+        assert_eq!("1.1", decimal("1.21^0.5"));
+        assert_eq!("0.5", decimal("4^-0.5"));
+        assert_eq!("0", decimal("0^0.5"));
+        assert!(matches!(eval_str_decimal("0^-0.5"), Err(MathError::Domain { .. })));
+        assert!(matches!(eval_str_decimal("(-8)^0.5"), Err(MathError::Domain { .. })));
+        assert_eq!("3162277660168379331998893544e+9999999999973", decimal("10^(1e13 + 0.5)"));
+        assert!(matches!(eval_str_decimal("10^(1e15 + 0.5)"), Err(MathError::Domain { .. })));
+
+        let rounded = |rounding: DecimalRounding, input: &str| {
+            with_decimal_context(DecimalContext::new(3, rounding).unwrap(), || decimal(input))
+        };
+        assert_eq!("0.667", rounded(DecimalRounding::HalfEven, "2/3"));
+        assert_eq!("0.666", rounded(DecimalRounding::Floor, "2/3"));
+        assert_eq!("-0.667", rounded(DecimalRounding::Floor, "-2/3"));
+        assert_eq!("0.334", rounded(DecimalRounding::Ceiling, "1/3"));
+        assert_eq!("-0.333", rounded(DecimalRounding::Ceiling, "-1/3"));
+        assert_eq!("1.42", rounded(DecimalRounding::Ceiling, "sqrt(2)"));
+        assert_eq!("1.41", rounded(DecimalRounding::Floor, "sqrt(2)"));
+        assert_eq!("1.42", rounded(DecimalRounding::Ceiling, "2^0.5"));
+        assert_eq!("1.41", rounded(DecimalRounding::Floor, "2^0.5"));
+        assert_eq!("0.1", rounded(DecimalRounding::Floor, "0.01^0.5"));
+        assert_eq!("1E-25", rounded(DecimalRounding::Ceiling, "1e-50^0.5"));
+        assert_eq!("0.2", rounded(DecimalRounding::Ceiling, "0.0016^0.25"));
+        assert_eq!("0.5", rounded(DecimalRounding::Floor, "16^-0.25"));
+        let precise = DecimalContext::new(60, DecimalRounding::HalfEven).unwrap();
+        assert_eq!(
+            with_decimal_context(precise, || decimal("sqrt(3)")),
+            with_decimal_context(precise, || decimal("3^0.5"))
+        );
+        assert_eq!("0.841", rounded(DecimalRounding::HalfUp, "sin(1)"));
+        // a tie is only rounded differently by the half modes
+        assert_eq!("1", rounded(DecimalRounding::HalfEven, "1.005 * 1"));
+        assert_eq!("1.01", rounded(DecimalRounding::HalfUp, "1.005 * 1"));
+        assert_eq!("0.125", rounded(DecimalRounding::HalfEven, "1/8"));
+        assert_eq!("0.0124", rounded(DecimalRounding::HalfEven, "0.0249 / 2"));
+        assert_eq!("0.0125", rounded(DecimalRounding::HalfUp, "0.0249 / 2"));
+        assert_eq!("0.0125", rounded(DecimalRounding::HalfEven, "0.02490001 / 2"));
+        assert_eq!(DecimalContext::default(), decimal_context());
+
+        // decimal variables last for the session, and are the nearest float when evaluating real numbers
+        let mut context = Context::new();
+        assert_eq!("0.3", context.eval_numeric::<Decimal>("price = 0.1; price * 3").unwrap().to_string());
+        assert_eq!("0.7", context.eval_numeric::<Decimal>("f(x): 1 - x; f(ans)").unwrap().to_string());
+        assert_eq!(Some(0.7f64), context.ans());
+        assert_eq!(0.1f64, context.eval("price").unwrap());
+
+        // every context has its own precision and rounding
+        let mut precise = Context::new().with_decimal_context(DecimalContext::new(40, DecimalRounding::HalfEven).unwrap());
+        let mut rough = Context::new().with_decimal_context(DecimalContext::new(3, DecimalRounding::Floor).unwrap());
+        assert_eq!("0.6666666666666666666666666666666666666667", precise.eval_numeric::<Decimal>("2/3").unwrap().to_string());
+        assert_eq!("0.666", rough.eval_numeric::<Decimal>("2/3").unwrap().to_string());
+        assert_eq!("0.6666666666666666666666666667", context.eval_numeric::<Decimal>("2/3").unwrap().to_string());
+        assert_eq!((40, DecimalRounding::HalfEven), (precise.decimal_context().precision.get(), precise.decimal_context().rounding));
+        assert_eq!(DecimalContext::default(), decimal_context());
+        with_decimal_context(DecimalContext::new(3, DecimalRounding::HalfEven).unwrap(), || {
+            assert_eq!("0.6666666666666666666666666667", context.eval_numeric::<Decimal>("2/3").unwrap().to_string());
+        });
+    }
+
+    #[test]
+    fn eval_interval_numbers() {
+        let interval = |input: &str| eval_str_interval(input).unwrap();
+        let encloses = |input: &str, lower: f64, upper: f64| {
+            let result = interval(input);
+            assert!(
+                result.lower <= lower && upper <= result.upper && result.width() < (upper - lower) + 1e-12,
+                "{input} is {result}, which should tightly enclose [{lower}, {upper}]"
+            );
+        };
+
+        // every bound is rounded outwards so the result is guaranteed to hold the exact one
+        encloses("[1.9, 2.1] * 3", 5.7, 6.3);
+        assert!(interval("[1.9, 2.1] * 3").contains(5.7) && !interval("[1.9, 2.1] * 3").is_point());
+        encloses("0.1 + 0.2", 0.3, 0.3);
+        assert!(interval("0.1 + 0.2").lower < 0.30000000000000004);
+        assert_eq!(Interval::point(6f64), interval("2 * 3"));
+        assert_eq!(Interval::point(0.5f64), interval("1 / 2"));
+        encloses("2 ± 0.1", 1.9, 2.1);
+        encloses("2 ± 0.1 - (2 ± 0.1)", -0.2, 0.2);
+        encloses("[-2, 3]^2", 0f64, 9f64);
+        encloses("[-2, 3]^3", -8f64, 27f64);
+        encloses("[2, 4]^-1", 0.25, 0.5);
+        encloses("[1, 4]^0.5", 1f64, 2f64);
+        encloses("abs([-3, 2])", 0f64, 3f64);
+        encloses("mean([1, 2], 4)", 2.5, 3f64);
+        encloses("interval([1, 2], [3, 4])", 1f64, 4f64);
+        assert_eq!(Interval::point(120f64), interval("5!"));
+
+        // division by an interval holding zero is unbounded
+        assert_eq!(Interval::new(0.5, f64::INFINITY).unwrap(), interval("[1, 2] / [0, 2]"));
+        assert_eq!(Interval::new(f64::NEG_INFINITY, -0.5).unwrap(), interval("[1, 2] / [-2, 0]"));
+        assert_eq!(Interval::ENTIRE, interval("[1, 2] / [-1, 1]"));
+        assert_eq!(Interval::ENTIRE, interval("[-1, 2] / [0, 1]"));
+        assert!(matches!(eval_str_interval("1 / [0, 0]"), Err(MathError::Domain { .. })));
+
+        // the built in functions enclose every value they take in an interval
+        encloses("sin([0, pi])", 0f64, 1f64);
+        encloses("sin([1, 2])", 1f64.sin(), 1f64);
+        encloses("cos([-1, 1])", 1f64.cos(), 1f64);
+        encloses("cos([3, 4])", -1f64, 4f64.cos());
+        encloses("ln([1, e])", 0f64, 1f64);
+        encloses("log([10, 100])", 1f64, 2f64);
+        encloses("sqrt([4, 9])", 2f64, 3f64);
+        encloses("sqrt([-1, 4])", 0f64, 2f64);
+        assert_eq!(Interval::ENTIRE, interval("tan([1, 2])"));
+        assert!(interval("sin(pi)").contains(0f64));
+        assert!(matches!(eval_str_interval("ln([-2, -1])"), Err(MathError::Domain { .. })));
+        assert!(matches!(eval_str_interval("gamma([1, 2])"), Err(MathError::Unsupported { .. })));
+
+        // bounds print in the shortest form that parses back to them, far from 1 in scientific notation
+        assert_eq!("[0.0, 5e-324]", Interval::new(0f64, 5e-324).unwrap().to_string());
+        assert_eq!("[-1.5, 1e300]", Interval::new(-1.5, 1e300).unwrap().to_string());
+        assert_eq!("0.1", Interval::point(0.1).to_string());
+
+        // intervals are only ordered if they do not overlap
+        assert_eq!(Interval::point(1f64), interval("[1, 2] < [3, 4]"));
+        assert_eq!(Interval::point(0f64), interval("[1, 2] > 3"));
+        assert!(matches!(eval_str_interval("[1, 3] < [2, 4]"), Err(MathError::Domain { .. })));
+        assert!(matches!(eval_str_interval("[1, 2] % 3"), Err(MathError::Domain { .. })));
+        assert!(matches!(eval_str_interval("[2, 1]"), Err(MathError::Domain { .. })));
+
+        // an interval is not supported when evaluating any other kind of number, nor folded when simplifying
+        let error = eval_str("2 ± 0.1").unwrap_err();
+        assert_eq!("\"interval\" is not supported when evaluating f64 numbers", error.to_string());
+        assert_eq!(Some(Span::new(0, 8)), error.span());
+        assert!(matches!(eval_str("x = [1, 3]"), Err(MathError::Unsupported { .. })));
+        assert!(matches!(eval_str_numeric::<f32>("[1, 3]"), Err(MathError::Unsupported { .. })));
+        assert!(matches!(eval_str_complex("[1, 3] * i"), Err(MathError::Unsupported { .. })));
+        assert!(matches!(eval_str_rational("[1, 2]"), Err(MathError::Unsupported { .. })));
+        assert!(matches!(eval_str_decimal("1.5 ± 0.5"), Err(MathError::Unsupported { .. })));
+        assert!(matches!(
+            CompiledExpression::parse_str("x ± 1", &["x"], &MathDefinition::default_math_definitions()).unwrap().eval(&[1f64]),
+            Err(MathError::Unsupported { .. })
+        ));
+        assert!(matches!(eval_str("diff(x ± 1, x)"), Err(MathError::Domain { .. })));
+        assert_eq!("2 * interval(1, 3)", simplify(&parse_ast_str("[1, 3] * 2").unwrap()).to_string());
+        assert!(matches!(eval_str("[1, 2"), Err(MathError::UnbalancedParens { .. })));
+        assert!(matches!(eval_str("[1, 2)"), Err(MathError::UnexpectedToken { .. })));
+
+        // interval variables last for the session, and are only a real number if they are a single number
+        let mut context = Context::new();
+        let length = context.eval_numeric::<Interval>("length = 2 ± 0.1; length^2").unwrap();
+        assert!(length.contains(3.61) && length.contains(4.41) && length.width() < 0.81);
+        assert!(context.eval("length").is_err());
+        assert_eq!(2f64, context.eval_numeric::<Interval>("width = [2, 2]").unwrap().lower);
+        assert_eq!(2f64, context.eval("width").unwrap());
+        assert_eq!(interval("2 ± 0.1"), context.eval_numeric::<Interval>("length").unwrap());
+    }
+}
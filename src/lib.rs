@@ -7,4 +7,13 @@ pub use parser::*;
 pub mod token;
 pub use token::*;
 
+pub mod ast;
+pub use ast::*;
+
+pub mod statement;
+pub use statement::*;
+
+pub mod error;
+pub use error::*;
+
 pub type Expression = Vec<Token>;
\ No newline at end of file
@@ -1,10 +1,27 @@
+mod repl;
+
 use math_parser::*;
+use repl::Repl;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    // with no expression to evaluate start an interactive session instead
+    if std::env::args().len() <= 1 {
+        return Repl::new().run();
+    }
 
-fn main() -> Result<(), String> {
-    println!(
-        "{:?}",
-        eval_str(std::env::args().skip(1).collect::<String>().as_str())?
-    );
+    let input = std::env::args().skip(1).collect::<String>();
+
+    match eval_str(input.as_str()) {
+        Ok(result) => {
+            println!("{result:?}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}", error.render(input.as_str()));
+            ExitCode::FAILURE
+        }
+    }
 
     // testing dont commit me please.
     // if you need to commit something make a test.
@@ -16,6 +33,4 @@ fn main() -> Result<(), String> {
     // println!("{:?}", penis_function.evaluate(&[2f64]));
 
     // println!("{:?}", math_definitions());
-
-    Ok(())
 }
@@ -1,39 +1,220 @@
+use crate::error::*;
 use crate::token::*;
+use crate::{MathDefinition, IMAGINARY_UNIT};
+use std::collections::HashMap;
+
+/// opt-in behaviour of the parser. the default parses exactly what is written.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions<'a> {
+    /// this will insert a multiplication between juxtaposed numbers, identities and parenthesized groups
+    /// so "2x", "2 sin(x)" and "(a+b)(a-b)" are products.
+    pub implicit_multiplication: bool,
+    /// an identity followed by an open paren is only a function call if it is a function in these definitions,
+    /// otherwise "x(2)" is a product. without definitions every identity followed by an open paren is a call.
+    pub math_definitions: Option<&'a HashMap<String, MathDefinition>>,
+}
 
 #[derive(Clone, Debug)]
 struct ParseState<'a> {
+    /// byte index into the original input
     pub index: usize,
     pub input: &'a str,
-    pub tokens: Vec<Token>,
+    pub tokens: Vec<SpannedToken>,
 }
 impl ParseState<'_> {
     pub fn new<'a>(input: &'a str) -> ParseState<'a> {
         ParseState::<'a> {
             index: 0,
-            input: input,
+            input,
             tokens: vec![],
         }
     }
 
+    pub fn remaining_input(&self) -> &str {
+        &self.input[self.index..]
+    }
+
     pub fn push_token(self, token: Token, index_advance_amount: usize) -> Self {
         let mut tokens = self.tokens;
-        tokens.push(token);
+        tokens.push(SpannedToken {
+            token,
+            span: Some(Span::new(self.index, self.index + index_advance_amount)),
+        });
         Self {
             index: self.index + index_advance_amount,
             input: self.input,
-            tokens: tokens,
+            tokens,
+        }
+    }
+
+    /// this will skip whitespace, ending the current statement if the whitespace has a newline that could end it.
+    pub fn skip_whitespace(self) -> Self {
+        let whitespace_length = self.remaining_input().len()
+            - self
+                .remaining_input()
+                .trim_start_matches(char::is_whitespace)
+                .len();
+
+        match self.remaining_input()[..whitespace_length].find('\n') {
+            Some(newline_index) if self.newline_ends_statement() => {
+                let whitespace_end = self.index + whitespace_length;
+                let parse_state = Self {
+                    index: self.index + newline_index,
+                    ..self
+                };
+                Self {
+                    index: whitespace_end,
+                    ..parse_state.push_token(Token::Operator(Operator::StatementSeparator), 1)
+                }
+            }
+            _ => Self {
+                index: self.index + whitespace_length,
+                ..self
+            },
+        }
+    }
+
+    /// a newline ends a statement unless it is inside parentheses or brackets or follows an operator that is missing
+    /// its operand.
+    fn newline_ends_statement(&self) -> bool {
+        let paren_depth =
+            self.tokens
+                .iter()
+                .fold(0isize, |depth, spanned_token| match &spanned_token.token {
+                    Token::Operator(Operator::OpenParen | Operator::OpenBracket) => depth + 1,
+                    Token::Operator(Operator::CloseParen | Operator::CloseBracket) => depth - 1,
+                    _ => depth,
+                });
+
+        match self.tokens.last() {
+            None => false,
+            Some(spanned_token) => {
+                paren_depth <= 0
+                    && !spanned_token
+                        .token
+                        .get_operator()
+                        .is_some_and(Operator::expects_operand)
+            }
         }
     }
 }
 
-pub fn parse_str(input: &str) -> Result<Vec<Token>, String> {
-    let cleaned_input = input.to_string().replace(" ", "");
-    let parse_state = ParseState::new(cleaned_input.as_str());
+pub fn parse_str(input: &str) -> Result<Vec<Token>, MathError> {
+    Ok(parse_spanned_str(input)?
+        .into_iter()
+        .map(|spanned_token| spanned_token.token)
+        .collect())
+}
+
+pub fn parse_str_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<Vec<Token>, MathError> {
+    Ok(parse_spanned_str_with_options(input, options)?
+        .into_iter()
+        .map(|spanned_token| spanned_token.token)
+        .collect())
+}
+
+/// this will tokenize a string, keeping the span of the input each token was parsed from.
+/// whitespace separates tokens but is never part of a span.
+pub fn parse_spanned_str(input: &str) -> Result<Vec<SpannedToken>, MathError> {
+    let parse_state = ParseState::new(input).skip_whitespace();
+    if parse_state.index >= parse_state.input.len() {
+        return Ok(parse_state.tokens);
+    }
     parse(parse_state)
 }
 
-fn parse(parse_state: ParseState<'_>) -> Result<Vec<Token>, String> {
+/// this will tokenize a string like `parse_spanned_str` using the given parse options.
+pub fn parse_spanned_str_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<Vec<SpannedToken>, MathError> {
+    let tokens = parse_spanned_str(input)?;
+    if options.implicit_multiplication {
+        Ok(insert_implicit_multiplication(tokens, options))
+    } else {
+        Ok(tokens)
+    }
+}
+
+/// this will insert an implicit multiplication token between every pair of juxtaposed operands.
+///
+/// an operand ends with a number, an identity that is not a known function, a close paren or bracket or a postfix
+/// operator and starts with an identity or an open paren or bracket. a number can only start an operand after a close
+/// paren or bracket or a postfix operator, "2 3" and "x 2" are more likely typos than products.
+fn insert_implicit_multiplication(
+    tokens: Vec<SpannedToken>,
+    options: &ParseOptions,
+) -> Vec<SpannedToken> {
+    let is_function = |identity: &str| {
+        options.math_definitions.is_some_and(|math_definitions| {
+            math_definitions
+                .get(identity)
+                .is_some_and(MathDefinition::is_function)
+        })
+    };
+
+    let is_juxtaposed = |previous: &Token, next: &Token| match (previous, next) {
+        (Token::Identity(identity), Token::Operator(Operator::OpenParen)) => {
+            options.math_definitions.is_some() && !is_function(identity)
+        }
+        (Token::Identity(identity), Token::Identity(_)) => !is_function(identity),
+        (
+            Token::Number(_),
+            Token::Identity(_) | Token::Operator(Operator::OpenParen | Operator::OpenBracket),
+        ) => true,
+        (
+            Token::Operator(
+                Operator::CloseParen
+                | Operator::CloseBracket
+                | Operator::Factorial
+                | Operator::DoubleFactorial,
+            ),
+            Token::Number(_)
+            | Token::Identity(_)
+            | Token::Operator(Operator::OpenParen | Operator::OpenBracket),
+        ) => true,
+        _ => false,
+    };
+
+    // a "!" only ends an operand if it is a factorial, one that does not follow an operand is a prefix not
+    fn ends_operand(tokens: &[SpannedToken]) -> bool {
+        match tokens.split_last() {
+            Some((last, previous_tokens)) => match last.token {
+                Token::Operator(Operator::Factorial | Operator::DoubleFactorial) => {
+                    ends_operand(previous_tokens)
+                }
+                Token::Operator(Operator::CloseParen | Operator::CloseBracket)
+                | Token::Number(_)
+                | Token::Identity(_) => true,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    let mut result: Vec<SpannedToken> = Vec::with_capacity(tokens.len());
+    for spanned_token in tokens {
+        if let Some(previous) = result.last() {
+            if is_juxtaposed(&previous.token, &spanned_token.token) && ends_operand(&result) {
+                result.push(SpannedToken {
+                    token: Token::Operator(Operator::ImplicitMultiplication),
+                    span: spanned_token
+                        .span
+                        .map(|span| Span::new(span.start, span.start)),
+                });
+            }
+        }
+        result.push(spanned_token);
+    }
+    result
+}
+
+fn parse(parse_state: ParseState<'_>) -> Result<Vec<SpannedToken>, MathError> {
     let parse_control = |parse_state: ParseState<'_>| {
+        let parse_state = parse_state.skip_whitespace();
         if parse_state.index >= parse_state.input.len() {
             Ok(parse_state.tokens)
         } else {
@@ -48,6 +229,9 @@ fn parse(parse_state: ParseState<'_>) -> Result<Vec<Token>, String> {
 
     let parse_state = match parse_number_token(parse_state) {
         Ok(parse_state) => return parse_control(parse_state),
+        Err((error, parse_state)) if starts_number_literal(parse_state.remaining_input()) => {
+            return Err(error)
+        }
         Err((_, parse_state)) => parse_state,
     };
 
@@ -59,16 +243,37 @@ fn parse(parse_state: ParseState<'_>) -> Result<Vec<Token>, String> {
 
 fn try_parse_operator_token(
     parse_state: ParseState<'_>,
-) -> Result<ParseState, (String, ParseState)> {
-    let char_to_parse = parse_state.input.chars().nth(parse_state.index);
+) -> Result<ParseState<'_>, (MathError, ParseState<'_>)> {
+    let char_to_parse = parse_state.remaining_input().chars().next();
 
     if char_to_parse.is_none() {
         return Err((
-            "error : parse_operator : nothing to parse out of characters".to_string(),
+            MathError::Lex {
+                span: Some(Span::new(parse_state.index, parse_state.index)),
+                message: "nothing to parse out of characters".to_string(),
+            },
             parse_state,
         ));
     }
 
+    // operators of two characters are lexed first so "<=" is not "<" followed by "="
+    const TWO_CHARACTER_OPERATORS: [Operator; 8] = [
+        Operator::DoubleFactorial,
+        Operator::Lambda,
+        Operator::LessThanOrEqual,
+        Operator::GreaterThanOrEqual,
+        Operator::Equal,
+        Operator::NotEqual,
+        Operator::And,
+        Operator::Or,
+    ];
+    if let Some(operator) = TWO_CHARACTER_OPERATORS
+        .iter()
+        .find(|operator| parse_state.remaining_input().starts_with(operator.symbol()))
+    {
+        return Ok(parse_state.push_token(Token::Operator(*operator), 2));
+    }
+
     let token = match char_to_parse.unwrap() {
         '+' => Token::Operator(Operator::Addition),
         '-' => Token::Operator(Operator::Subtraction),
@@ -76,74 +281,220 @@ fn try_parse_operator_token(
         '/' => Token::Operator(Operator::Division),
         '^' => Token::Operator(Operator::Exponentiation),
         '%' => Token::Operator(Operator::Modulus),
+        '!' => Token::Operator(Operator::Factorial),
         '(' => Token::Operator(Operator::OpenParen),
         ')' => Token::Operator(Operator::CloseParen),
         ',' => Token::Operator(Operator::ArgumentSeparator),
         ':' => Token::Operator(Operator::FunctionAssignment),
-        _ => {
+        '=' => Token::Operator(Operator::Assignment),
+        ';' => Token::Operator(Operator::StatementSeparator),
+        '<' => Token::Operator(Operator::LessThan),
+        '>' => Token::Operator(Operator::GreaterThan),
+        '?' => Token::Operator(Operator::Conditional),
+        '[' => Token::Operator(Operator::OpenBracket),
+        ']' => Token::Operator(Operator::CloseBracket),
+        '±' => Token::Operator(Operator::PlusMinus),
+        ch => {
             return Err((
-                "error : parse_operator : could not parse char".to_string(),
+                MathError::Lex {
+                    span: Some(Span::new(
+                        parse_state.index,
+                        parse_state.index + ch.len_utf8(),
+                    )),
+                    message: format!("could not parse {ch:?} as an operator"),
+                },
                 parse_state,
             ))
         }
     };
 
-    // advance one for char size
-    Ok(parse_state.push_token(token, 1))
+    let char_length = char_to_parse.map_or(1, char::len_utf8);
+    Ok(parse_state.push_token(token, char_length))
 }
 
-fn parse_number_token(parse_state: ParseState) -> Result<ParseState, (String, ParseState)> {
-    let mut end_number_index = parse_state.index;
-    for ch in parse_state.input[parse_state.index..].chars() {
-        if ch.is_numeric() || ch == '.' {
-            end_number_index += 1
-        } else {
-            break;
-        }
+/// a number literal starts with a digit, or with a decimal point followed by a digit like ".5".
+fn starts_number_literal(input: &str) -> bool {
+    let mut chars = input.chars();
+    match chars.next() {
+        Some(ch) if ch.is_ascii_digit() => true,
+        Some('.') => chars.next().is_some_and(|ch| ch.is_ascii_digit()),
+        _ => false,
     }
+}
 
-    let f64_parse_result = parse_state.input[parse_state.index..end_number_index].parse::<f64>();
+/// number literals can be written as :
+/// - decimals with an optional fraction and exponent like "12", "1.5", ".5", "1e-9" or "6.02E23"
+/// - hex, binary or octal integers like "0x1F", "0b1010" or "0o755"
+///
+/// and any of them can separate their digits with underscores like "1_000_000". a decimal followed directly by an
+/// "i" like "2i" or "0.5i" is an imaginary literal, which is lexed as the number times i.
+fn parse_number_token(
+    parse_state: ParseState<'_>,
+) -> Result<ParseState<'_>, (MathError, ParseState<'_>)> {
+    let input = parse_state.remaining_input();
 
-    if f64_parse_result.is_err() {
+    if !starts_number_literal(input) {
         return Err((
-            "error : parse_number : float parse error".to_string(),
+            MathError::Lex {
+                span: Some(Span::new(parse_state.index, parse_state.index)),
+                message: "expected a number".to_string(),
+            },
             parse_state,
         ));
     }
 
-    let index_advance_amount = end_number_index - parse_state.index;
+    let literal_result = match input.get(..2) {
+        Some("0x" | "0X") => parse_radix_literal(input, 16),
+        Some("0b" | "0B") => parse_radix_literal(input, 2),
+        Some("0o" | "0O") => parse_radix_literal(input, 8),
+        _ => parse_decimal_literal(input),
+    };
 
-    Ok(parse_state.push_token(
-        Token::Number(f64_parse_result.unwrap()),
-        index_advance_amount,
-    ))
+    match literal_result {
+        Ok((num, number_length)) => {
+            let parse_state = parse_state.push_token(Token::Number(num), number_length);
+            let is_imaginary = parse_state
+                .remaining_input()
+                .strip_prefix(IMAGINARY_UNIT)
+                .is_some_and(|rest| !rest.starts_with(is_identity_continue));
+            if !is_imaginary {
+                return Ok(parse_state);
+            }
+            Ok(parse_state
+                .push_token(Token::Operator(Operator::ImplicitMultiplication), 0)
+                .push_token(
+                    Token::Identity(IMAGINARY_UNIT.to_string()),
+                    IMAGINARY_UNIT.len(),
+                ))
+        }
+        Err(literal_length) => Err((
+            MathError::Lex {
+                span: Some(Span::new(
+                    parse_state.index,
+                    parse_state.index + literal_length,
+                )),
+                message: format!("invalid number literal {:?}", &input[..literal_length]),
+            },
+            parse_state,
+        )),
+    }
 }
 
-fn parse_identity_token(parse_state: ParseState) -> Result<ParseState, (String, ParseState)> {
-    if !parse_state
-        .input
-        .chars()
-        .nth(parse_state.index)
-        .is_some_and(|ch| ch.is_alphabetic())
-    {
-        return Err((
-            "error : parse_identity_token : coulnd't parse identity".to_string(),
-            parse_state,
-        ));
+/// this will find the length of a run of digits, where single underscores may separate the digits.
+fn digits_length(input: &str, radix: u32) -> usize {
+    let mut length = 0;
+    for (index, ch) in input.char_indices() {
+        if ch.is_digit(radix) {
+            length = index + 1
+        } else if ch != '_'
+            || length == 0
+            || !input[index + 1..].starts_with(|ch: char| ch.is_digit(radix))
+        {
+            break;
+        }
+    }
+    length
+}
+
+/// this will find the length of everything that looks like it belongs to a malformed literal.
+fn malformed_literal_length(input: &str) -> usize {
+    input.len()
+        - input
+            .trim_start_matches(|ch: char| ch.is_ascii_alphanumeric() || ch == '.' || ch == '_')
+            .len()
+}
+
+/// this will parse a literal like "0x1F", returning its value and length or the length of the malformed literal.
+fn parse_radix_literal(input: &str, radix: u32) -> Result<(Number, usize), usize> {
+    let literal_length = 2 + digits_length(&input[2..], radix);
+
+    if literal_length == 2 || malformed_literal_length(&input[literal_length..]) > 0 {
+        return Err(malformed_literal_length(input));
     }
 
-    let mut end_identity_index = parse_state.index;
-    for ch in parse_state.input[parse_state.index..].chars() {
-        if ch.is_alphabetic() {
-            end_identity_index += 1
+    u128::from_str_radix(&input[2..literal_length].replace('_', ""), radix)
+        .map(|num| (Number::new(num as f64, &num.to_string()), literal_length))
+        .map_err(|_| literal_length)
+}
+
+/// this will parse a literal like "1_000.5e-3", returning its value and length or the length of the malformed literal.
+fn parse_decimal_literal(input: &str) -> Result<(Number, usize), usize> {
+    let mut literal_length = digits_length(input, 10);
+
+    if input[literal_length..].starts_with('.') {
+        literal_length += 1 + digits_length(&input[literal_length + 1..], 10);
+    }
+
+    // the exponent is only part of the literal if it has digits, otherwise "2e" is a number then an identity
+    if let Some(exponent) = input[literal_length..].strip_prefix(['e', 'E']) {
+        let sign_length = if exponent.starts_with(['+', '-']) {
+            1
         } else {
-            break;
+            0
+        };
+        let exponent_digits_length = digits_length(&exponent[sign_length..], 10);
+        if exponent_digits_length > 0 {
+            literal_length += 1 + sign_length + exponent_digits_length;
         }
     }
 
-    let identity_string = parse_state.input[parse_state.index..end_identity_index].to_string();
+    // a literal can not continue with another decimal point like "1.2.3"
+    if input[literal_length..].starts_with('.') {
+        return Err(malformed_literal_length(input));
+    }
+
+    let literal = input[..literal_length].replace('_', "");
+    literal
+        .parse::<f64>()
+        .map(|num| (Number::new(num, &literal), literal_length))
+        .map_err(|_| literal_length)
+}
+
+pub fn is_identity_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+pub fn is_identity_continue(ch: char) -> bool {
+    ch.is_alphabetic() || ch.is_ascii_digit() || ch == '_'
+}
+
+/// an identity starts with a letter or an underscore, followed by any number of letters, digits and underscores.
+/// letters are any unicode letter so names like "π" and "τ" are identities, like "x2", "f_1" and "log10".
+fn parse_identity_token(
+    parse_state: ParseState<'_>,
+) -> Result<ParseState<'_>, (MathError, ParseState<'_>)> {
+    let identity_length = if parse_state.remaining_input().starts_with(is_identity_start) {
+        parse_state.remaining_input().len()
+            - parse_state
+                .remaining_input()
+                .trim_start_matches(is_identity_continue)
+                .len()
+    } else {
+        0
+    };
+
+    if identity_length == 0 {
+        let unexpected_char_length = parse_state
+            .remaining_input()
+            .chars()
+            .next()
+            .map_or(0, char::len_utf8);
+        return Err((
+            MathError::Lex {
+                span: Some(Span::new(
+                    parse_state.index,
+                    parse_state.index + unexpected_char_length,
+                )),
+                message: format!(
+                    "unexpected character {:?}",
+                    &parse_state.remaining_input()[..unexpected_char_length]
+                ),
+            },
+            parse_state,
+        ));
+    }
 
-    let index_advance_amount = end_identity_index - parse_state.index;
+    let identity_string = parse_state.remaining_input()[..identity_length].to_string();
 
-    Ok(parse_state.push_token(Token::Identity(identity_string), index_advance_amount))
+    Ok(parse_state.push_token(Token::Identity(identity_string), identity_length))
 }
@@ -0,0 +1,444 @@
+use math_parser::*;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+const HISTORY_FILE_NAME: &str = ".math_parser_history";
+
+const HELP: &str = "\
+enter an expression to evaluate it, like \"2 * sin(pi / 4)\".
+assign a variable with \"r = 5\" and define a function with \"f(x, y): x^2 + y\", both last for the session.
+comparisons and \"&&\", \"||\" and \"!\" give 1 or 0, pick a value with \"x < 0 ? -x : x\" or \"if(x < 0, -x, x)\".
+\"!=\" is always not equal, so \"5!=120\" is \"5 != 120\" and a factorial is compared with \"5! == 120\".
+functions can invoke themselves, like \"fact(n): n <= 1 ? 1 : n * fact(n - 1)\".
+a function can have cases checked in order, like \"f(x): -x when x < 0; x otherwise\".
+a lambda like \"x -> x^2\" can be named with \"sq = x -> x^2\" or passed to a function like \"map\" or \"integrate\".
+separate statements with \";\" or newlines, the value of the last statement is printed and kept as \"ans\".
+an input with unclosed parentheses or ending in \"\\\" continues on the next line.
+
+commands :
+  :help      show this message
+  :defs      list every constant and function that is defined
+  :simplify  print an expression in its simplest form like \":simplify x*1 + x\"
+  :diff      differentiate a function like \":diff f x\", defining the derivative as \"df_dx\"
+  :mode      switch what numbers are evaluated like \":mode complex\", one of
+             real, complex where \"sqrt(-1)\" is \"i\", rational where \"1/3 + 1/6\" is \"1/2\"
+             decimal where \"0.1 + 0.2\" is exactly \"0.3\" or interval where \"[1.9, 2.1] * 3\"
+             and \"2 ± 0.1\" are bounds that are guaranteed to hold the result
+  :decimal   set the significant digits and rounding of decimal numbers like \":decimal 10 half-up\",
+             rounding half-even, half-up, floor or ceiling
+  :quit      leave the repl, as does ctrl-d";
+
+/// the kind of numbers the repl evaluates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Real,
+    Complex,
+    Rational,
+    Decimal,
+    Interval,
+}
+impl Mode {
+    const NAMES: [(&'static str, Mode); 5] = [
+        ("real", Mode::Real),
+        ("complex", Mode::Complex),
+        ("rational", Mode::Rational),
+        ("decimal", Mode::Decimal),
+        ("interval", Mode::Interval),
+    ];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(mode_name, _)| *mode_name == name)
+            .map(|(_, mode)| *mode)
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, mode)| mode == self)
+            .map_or("real", |(name, _)| *name)
+    }
+}
+
+/// an interactive session that keeps the functions defined in it until it exits.
+pub struct Repl {
+    context: Context,
+    mode: Mode,
+}
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            context: Context::new(),
+            mode: Mode::default(),
+        }
+    }
+
+    /// this will read and evaluate lines until the user quits, saving the line history on the way out.
+    pub fn run(&mut self) -> ExitCode {
+        let mut editor = match DefaultEditor::new() {
+            Ok(editor) => editor,
+            Err(error) => {
+                eprintln!("error : could not start the repl : {error}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let history_path = history_path();
+        if let Some(history_path) = &history_path {
+            // there is no history the first time the repl is run
+            let _ = editor.load_history(history_path);
+        }
+
+        println!("math_parser repl, enter :help for help");
+
+        loop {
+            let input = match read_input(&mut editor) {
+                Ok(input) => input,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(error) => {
+                    eprintln!("error : {error}");
+                    break;
+                }
+            };
+
+            if input.trim().is_empty() {
+                continue;
+            }
+            let _ = editor.add_history_entry(input.as_str());
+
+            match input.trim() {
+                ":quit" | ":exit" => break,
+                ":help" => println!("{HELP}"),
+                ":defs" => println!("{}", self.list_definitions()),
+                command if command.starts_with(":mode") => {
+                    match self.set_mode(command[":mode".len()..].trim()) {
+                        Ok(mode) => println!("evaluating {} numbers", mode.name()),
+                        Err(error) => eprintln!("{}", error.render(command)),
+                    }
+                }
+                command if command.starts_with(":decimal") => {
+                    match self.set_decimal(command[":decimal".len()..].trim()) {
+                        Ok(context) => println!(
+                            "decimal numbers have {} significant digits, rounding {}",
+                            context.precision, context.rounding
+                        ),
+                        Err(error) => eprintln!("{}", error.render(command)),
+                    }
+                }
+                command if command.starts_with(":simplify") => {
+                    let expression = &command[":simplify".len()..];
+                    match parse_ast_str(expression) {
+                        Ok(ast) => println!(
+                            "{}",
+                            simplify_with_definitions(&ast, self.context.math_definitions())
+                        ),
+                        Err(error) => eprintln!("{}", error.render(expression)),
+                    }
+                }
+                command if command.starts_with(":diff") => match self.diff(command) {
+                    Ok(output) => println!("{output}"),
+                    Err(error) => eprintln!("{}", error.render(command)),
+                },
+                command if command.starts_with(':') => {
+                    eprintln!("error : unknown command {command:?}, enter :help for help")
+                }
+                _ => match self.eval(&input) {
+                    Ok(Some(value)) => println!("{value}"),
+                    Ok(None) => {}
+                    Err(error) => eprintln!("{}", error.render(&input)),
+                },
+            }
+        }
+
+        if let Some(history_path) = &history_path {
+            if let Err(error) = editor.save_history(history_path) {
+                eprintln!("error : could not save history : {error}");
+            }
+        }
+        ExitCode::SUCCESS
+    }
+
+    /// this will run the statements of an input, returning the value of the last statement if it has one.
+    pub fn eval(&mut self, input: &str) -> Result<Option<String>, MathError> {
+        match self.mode {
+            Mode::Real => self.execute::<f64>(input),
+            Mode::Complex => self.execute::<Complex>(input),
+            Mode::Rational => self.execute::<Rational>(input),
+            Mode::Decimal => self.execute::<Decimal>(input),
+            Mode::Interval => self.execute::<Interval>(input),
+        }
+    }
+
+    fn execute<N: Numeric>(&mut self, input: &str) -> Result<Option<String>, MathError> {
+        Ok(self
+            .context
+            .execute_numeric::<N>(input)?
+            .map(|value| value.to_string()))
+    }
+
+    /// this will switch the mode for a command like ":mode complex", returning the new mode.
+    pub fn set_mode(&mut self, name: &str) -> Result<Mode, MathError> {
+        self.mode = Mode::parse(name).ok_or_else(|| {
+            let names = Mode::NAMES.map(|(name, _)| name);
+            MathError::InvalidArgument {
+                span: None,
+                message: format!(
+                    "expected a mode like \":mode complex\", one of {}",
+                    names.join(", ")
+                ),
+            }
+        })?;
+        Ok(self.mode)
+    }
+
+    /// this will set the decimal context for a command like ":decimal 10 half-up", keeping the rounding if it is
+    /// left out.
+    pub fn set_decimal(&mut self, args: &str) -> Result<DecimalContext, MathError> {
+        let invalid_command = || MathError::InvalidArgument {
+            span: None,
+            message: "expected the significant digits and rounding like \":decimal 10 half-up\""
+                .to_string(),
+        };
+
+        let (precision, rounding) = match args.split_whitespace().collect::<Vec<&str>>()[..] {
+            [precision] => (precision, None),
+            [precision, rounding] => (precision, Some(rounding)),
+            _ => return Err(invalid_command()),
+        };
+        let rounding = match rounding {
+            Some(rounding) => DecimalRounding::from_name(rounding).ok_or_else(invalid_command)?,
+            None => self.context.decimal_context().rounding,
+        };
+        let context = precision
+            .parse::<u64>()
+            .ok()
+            .and_then(|precision| DecimalContext::new(precision, rounding))
+            .ok_or_else(invalid_command)?;
+        self.context = std::mem::take(&mut self.context).with_decimal_context(context);
+        Ok(context)
+    }
+
+    /// this will differentiate a defined function for a command like ":diff f x", defining the derivative.
+    pub fn diff(&mut self, command: &str) -> Result<String, MathError> {
+        let invalid_command = || MathError::InvalidArgument {
+            span: None,
+            message: "expected a function and a variable like \":diff f x\"".to_string(),
+        };
+
+        let [_, name, var] = command.split_whitespace().collect::<Vec<&str>>()[..] else {
+            return Err(invalid_command());
+        };
+        let Some(MathDefinition::DefinedFunction(function)) =
+            self.context.math_definitions().get(name)
+        else {
+            return Err(MathError::InvalidArgument {
+                span: None,
+                message: format!("{name:?} is not a defined function"),
+            });
+        };
+
+        let derivative = simplify_function(&derivative_with_definitions(
+            function,
+            var,
+            self.context.math_definitions(),
+        )?);
+        let output = derivative.to_string();
+        self.context.add_function(derivative)?;
+        Ok(output)
+    }
+
+    /// this will list every definition sorted by name, one per line.
+    pub fn list_definitions(&self) -> String {
+        let mut definitions = self.context.math_definitions().iter().collect::<Vec<_>>();
+        definitions.sort_by_key(|(identity, _)| *identity);
+
+        definitions
+            .into_iter()
+            .map(|(identity, math_definition)| match math_definition {
+                MathDefinition::Constant(value) | MathDefinition::Variable(value) => {
+                    format!("{identity} = {value}")
+                }
+                MathDefinition::ComplexConstant(value) => format!("{identity} = {value}"),
+                MathDefinition::NumericVariable(value) => format!("{identity} = {value}"),
+                MathDefinition::BuiltInFunction(function) => match function.arg_count {
+                    Some(1) => format!("{identity} : built in function of 1 argument"),
+                    Some(arg_count) => {
+                        format!("{identity} : built in function of {arg_count} arguments")
+                    }
+                    None => format!("{identity} : built in function of any number of arguments"),
+                },
+                MathDefinition::SpecialForm(_) => format!("{identity} : built in special form"),
+                MathDefinition::DefinedFunction(function) => {
+                    format!(
+                        "{} : defined function",
+                        describe_defined_function(identity, function)
+                    )
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+fn describe_defined_function(identity: &str, defined_function: &DefinedFunction) -> String {
+    format!("{identity}({})", defined_function.arg_names.join(", "))
+}
+
+/// the history is kept in the home directory so it is shared by every session of the user.
+fn history_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home_dir| home_dir.join(HISTORY_FILE_NAME))
+}
+
+/// this will read one input, reading more lines while the input is incomplete.
+fn read_input(editor: &mut DefaultEditor) -> Result<String, ReadlineError> {
+    let mut input = editor.readline(PROMPT)?;
+    while needs_continuation(&input) {
+        if let Some(line) = input.trim_end().strip_suffix('\\') {
+            input.truncate(line.len());
+        }
+        input.push('\n');
+        input.push_str(&editor.readline(CONTINUATION_PROMPT)?);
+    }
+    Ok(input)
+}
+
+/// an input continues on the next line while it has unclosed parentheses or brackets or ends in a backslash.
+fn needs_continuation(input: &str) -> bool {
+    let open_parens = input.matches(['(', '[']).count();
+    let close_parens = input.matches([')', ']']).count();
+    input.trim_end().ends_with('\\') || open_parens > close_parens
+}
+
+#[cfg(test)]
+mod repl_tests {
+    use super::*;
+
+    #[test]
+    fn continues_unclosed_input() {
+        assert!(needs_continuation("f(x): (x +"));
+        assert!(needs_continuation("[1.9,"));
+        assert!(needs_continuation("1 + \\"));
+        assert!(needs_continuation("1 + \\  "));
+        assert!(!needs_continuation("f(x): (x + 1)"));
+        assert!(!needs_continuation("[1.9, 2.1] * 3"));
+        assert!(!needs_continuation("1)"));
+    }
+
+    #[test]
+    fn set_mode() {
+        let mut repl = Repl::new();
+        assert_eq!(Some("1".to_string()), repl.eval("1/3 + 2/3").unwrap());
+        assert_eq!(Mode::Rational, repl.set_mode("rational").unwrap());
+        assert_eq!(Some("1/2".to_string()), repl.eval("1/3 + 1/6").unwrap());
+        assert_eq!(Mode::Complex, repl.set_mode("complex").unwrap());
+        assert_eq!(Some("i".to_string()), repl.eval("sqrt(-1)").unwrap());
+        assert_eq!("complex", repl.mode.name());
+
+        assert!(matches!(
+            repl.set_mode("imaginary"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            repl.set_mode(""),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert_eq!(Mode::Complex, repl.mode);
+    }
+
+    #[test]
+    fn set_decimal() {
+        let mut repl = Repl::new();
+        repl.set_mode("decimal").unwrap();
+
+        let context = repl.set_decimal("3 floor").unwrap();
+        assert_eq!(
+            (3, DecimalRounding::Floor),
+            (context.precision.get(), context.rounding)
+        );
+        assert_eq!(Some("0.666".to_string()), repl.eval("2/3").unwrap());
+        // the rounding is kept when only the precision is given
+        let context = repl.set_decimal("5").unwrap();
+        assert_eq!(
+            (5, DecimalRounding::Floor),
+            (context.precision.get(), context.rounding)
+        );
+        assert_eq!(context, repl.context.decimal_context());
+
+        for args in ["", "0", "-3", "ten", "3 sideways", "3 floor extra"] {
+            assert!(
+                matches!(
+                    repl.set_decimal(args),
+                    Err(MathError::InvalidArgument { .. })
+                ),
+                "{args:?} should be an invalid command"
+            );
+        }
+        assert_eq!(context, repl.context.decimal_context());
+        // the precision belongs to the session rather than the thread
+        assert_eq!(DecimalContext::default(), decimal_context());
+        assert_eq!(
+            Some("0.6666666666666666666666666667".to_string()),
+            Repl::new().execute::<Decimal>("2/3").unwrap()
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let mut repl = Repl::new();
+        assert_eq!(None, repl.eval("f(x, y): x^2 * y").unwrap());
+        assert_eq!("df_dx(x, y): 2 * x * y", repl.diff(":diff f x").unwrap());
+        assert_eq!(Some("12".to_string()), repl.eval("df_dx(2, 3)").unwrap());
+
+        assert!(matches!(
+            repl.diff(":diff f"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            repl.diff(":diff sin x"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            repl.diff(":diff g x"),
+            Err(MathError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn list_definitions() {
+        let mut repl = Repl::new();
+        repl.eval("r = 5; f(x, y): x + y").unwrap();
+        repl.set_mode("rational").unwrap();
+        repl.eval("third = 1/3").unwrap();
+
+        let definitions = repl.list_definitions();
+        let lines = definitions.lines().collect::<Vec<&str>>();
+        for line in [
+            "r = 5",
+            "third = 1/3",
+            "f(x, y) : defined function",
+            "sin : built in function of 1 argument",
+            "pow : built in function of 2 arguments",
+            "mean : built in function of any number of arguments",
+        ] {
+            assert!(
+                lines.contains(&line),
+                "{line:?} should be listed in\n{definitions}"
+            );
+        }
+        let mut sorted = lines.clone();
+        sorted.sort_by_key(|line| {
+            line.split([' ', '('])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        });
+        assert_eq!(sorted, lines);
+    }
+}
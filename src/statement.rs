@@ -0,0 +1,107 @@
+use crate::*;
+
+/// one statement of a program, statements are separated by ";" or by newlines.
+#[derive(Clone, Debug)]
+pub enum Statement {
+    /// an expression whose value is the value of the statement, like "pi * r^2".
+    Expression(Ast),
+    /// a value bound to a name like "r = 5", the assigned value is the value of the statement.
+    Assignment {
+        identity: String,
+        identity_span: Option<Span>,
+        value: Ast,
+    },
+    /// a function definition like "f(x): x^2", which has no value. its body is validated when it is defined.
+    FunctionDefinition {
+        defined_function: DefinedFunction,
+        signature_span: Option<Span>,
+    },
+}
+
+/// this will parse a string into the statements of a program.
+pub fn parse_statements_str(input: &str) -> Result<Vec<Statement>, MathError> {
+    parse_statements(&parse_spanned_str(input)?)
+}
+
+/// this will parse a spanned token string into the statements of a program, skipping empty statements.
+/// the cases of a piecewise function are separated like statements, so they are kept with the definition they follow.
+pub fn parse_statements(expression: &[SpannedToken]) -> Result<Vec<Statement>, MathError> {
+    let mut statements: Vec<Vec<SpannedToken>> = vec![];
+    for statement in expression
+        .split(|spanned_token| spanned_token.token.is_statement_separator())
+        .filter(|statement| !statement.is_empty())
+    {
+        match statements.last_mut() {
+            Some(definition)
+                if is_function_definition(definition) && is_piecewise_case(statement) =>
+            {
+                definition.push(SpannedToken::from(Token::Operator(
+                    Operator::StatementSeparator,
+                )));
+                definition.extend_from_slice(statement);
+            }
+            _ => statements.push(statement.to_vec()),
+        }
+    }
+
+    statements
+        .iter()
+        .map(|statement| parse_statement(statement))
+        .collect()
+}
+
+fn parse_statement(statement: &[SpannedToken]) -> Result<Statement, MathError> {
+    if is_function_definition(statement) {
+        return Ok(Statement::FunctionDefinition {
+            defined_function: DefinedFunction::parse_spanned_unvalidated(statement.to_vec())?,
+            signature_span: statement[0].span,
+        });
+    }
+
+    match statement {
+        [SpannedToken {
+            token: Token::Identity(identity),
+            span: identity_span,
+        }, assignment, value @ ..]
+            if assignment.token.get_operator() == Some(&Operator::Assignment) =>
+        {
+            if value.is_empty() {
+                return Err(MathError::UnexpectedEnd {
+                    span: assignment.span.map(|span| Span::new(span.end, span.end)),
+                });
+            }
+            Ok(Statement::Assignment {
+                identity: identity.clone(),
+                identity_span: *identity_span,
+                value: parse_spanned_ast(value)?,
+            })
+        }
+        _ => match statement
+            .iter()
+            .find(|spanned_token| spanned_token.token.get_operator() == Some(&Operator::Assignment))
+        {
+            Some(assignment) => Err(MathError::InvalidDefinition {
+                span: assignment.span,
+                message: "a value can only be assigned to a name".to_string(),
+            }),
+            None => Ok(Statement::Expression(parse_spanned_ast(statement)?)),
+        },
+    }
+}
+
+/// a function definition is a name, its arguments in parentheses and then a function assignment.
+fn is_function_definition(statement: &[SpannedToken]) -> bool {
+    match statement {
+        [name, open_paren, rest @ ..]
+            if name.token.is_identity() && open_paren.token.is_open_paren() =>
+        {
+            rest.iter()
+                .position(|spanned_token| spanned_token.token.is_close_paren())
+                .and_then(|index| rest.get(index + 1))
+                .is_some_and(|spanned_token| {
+                    spanned_token.token.get_operator() == Some(&Operator::FunctionAssignment)
+                })
+        }
+        _ => false,
+    }
+}
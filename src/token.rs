@@ -1,49 +1,40 @@
+use crate::error::{MathError, Span};
+use crate::evaluator::{as_boolean, boolean_value, double_factorial, factorial};
+use std::fmt;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Token {
-    Number(f64),
+    Number(Number),
     Operator(Operator),
     Identity(String),
 }
 impl Token {
     pub fn get_num(&self) -> Option<f64> {
         if let Token::Number(num) = self {
-            Some(*num)
+            Some(num.value)
         } else {
             None
         }
     }
 
     pub fn is_argument_separator(&self) -> bool {
-        if let Self::Operator(Operator::ArgumentSeparator) = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, Self::Operator(Operator::ArgumentSeparator))
     }
 
     pub fn get_identity(&self) -> Option<&String> {
         if let Self::Identity(identity) = self {
-            Some(&identity)
+            Some(identity)
         } else {
             None
         }
     }
 
     pub fn is_identity(&self) -> bool {
-        if let Self::Identity(_) = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, Self::Identity(_))
     }
 
     pub fn is_num(&self) -> bool {
-        if let Self::Number(_) = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, Self::Number(_))
     }
 
     pub fn get_operator(&self) -> Option<&Operator> {
@@ -55,62 +46,132 @@ impl Token {
     }
 
     pub fn is_open_paren(&self) -> bool {
-        if let Self::Operator(operator) = self {
-            if let Operator::OpenParen = operator {
-                true
-            } else {
-                false
-            }
-        } else {
-            false
-        }
+        matches!(self, Self::Operator(Operator::OpenParen))
     }
 
     pub fn is_close_paren(&self) -> bool {
-        if let Self::Operator(operator) = self {
-            if let Operator::CloseParen = operator {
-                true
-            } else {
-                false
-            }
-        } else {
-            false
-        }
+        matches!(self, Self::Operator(Operator::CloseParen))
+    }
+
+    pub fn is_close_bracket(&self) -> bool {
+        matches!(self, Self::Operator(Operator::CloseBracket))
     }
 
     pub fn is_operator(&self) -> bool {
-        if let Self::Operator(_) = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, Self::Operator(_))
+    }
+
+    pub fn is_statement_separator(&self) -> bool {
+        matches!(self, Self::Operator(Operator::StatementSeparator))
     }
 
     pub fn is_argument_seperator(&self) -> bool {
-        if let Self::Operator(Operator::ArgumentSeparator) = self {
-            true
-        } else {
-            false
+        matches!(self, Self::Operator(Operator::ArgumentSeparator))
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(num) => write!(f, "{}", num.value),
+            Self::Operator(operator) => write!(f, "{}", operator.symbol()),
+            Self::Identity(identity) => write!(f, "{identity}"),
         }
     }
 }
 
+/// a number, along with the literal it was written as when it was parsed. the literal is a decimal without
+/// underscores like "12345678901234567890.123" or "1.5e-3", a hex, binary or octal literal is written in decimal.
+/// a backend more exact than f64 reads the literal rather than the nearest f64 to it.
+///
+/// comparing two numbers ignores their literals.
+#[derive(Clone, Debug)]
+pub struct Number {
+    pub value: f64,
+    pub literal: Option<String>,
+}
+impl Number {
+    pub fn new(value: f64, literal: &str) -> Self {
+        Self {
+            value,
+            literal: Some(literal.to_string()),
+        }
+    }
+}
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+impl From<f64> for Number {
+    fn from(value: f64) -> Self {
+        Self {
+            value,
+            literal: None,
+        }
+    }
+}
+
+/// a token along with the span of the input it was parsed from.
+/// tokens from the lexer always have a span, tokens converted from a bare `Token` do not.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Option<Span>,
+}
+impl SpannedToken {
+    /// this will get the text of the input the token was parsed from.
+    pub fn text<'a>(&self, input: &'a str) -> Option<&'a str> {
+        self.span.and_then(|span| input.get(span.start..span.end))
+    }
+}
+impl From<Token> for SpannedToken {
+    fn from(token: Token) -> Self {
+        Self { token, span: None }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Operator {
     Addition,
     Subtraction,
     Multiplication,
+    /// a multiplication implied by juxtaposition like "2x", only inserted by the implicit multiplication parse mode.
+    ImplicitMultiplication,
     Division,
     Modulus,
     Exponentiation,
     Factorial,
+    DoubleFactorial,
     OpenParen,
     CloseParen,
     ArgumentSeparator,
     FunctionAssignment,
+    Assignment,
+    /// a ";", or a newline that ends a statement.
+    StatementSeparator,
+    /// the "->" between the arguments and the body of a lambda like "x -> x^2".
+    Lambda,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+    /// a prefix "!", which is lexed as a factorial and only becomes a not where it starts an operand.
+    Not,
+    /// the "?" of a conditional like "x > 0 ? x : -x", the ":" before the otherwise branch is a function assignment.
+    Conditional,
+    /// the brackets of an interval literal like "[1.9, 2.1]".
+    OpenBracket,
+    CloseBracket,
+    /// the "±" of an interval around a value like "2 ± 0.1".
+    PlusMinus,
 }
 impl Operator {
-    pub fn apply_operation_2_operands(&self, num1: f64, num2: f64) -> Result<f64, ()> {
+    pub fn apply_operation_2_operands(&self, num1: f64, num2: f64) -> Result<f64, MathError> {
         match self {
             Self::Addition => Ok(num1 + num2),
             Self::Subtraction => Ok(num1 - num2),
@@ -118,18 +179,82 @@ impl Operator {
             Self::Division => Ok(num1 / num2),
             Self::Modulus => Ok(num1 % num2),
             Self::Exponentiation => Ok(num1.powf(num2)),
-            _ => Err(()),
+            Self::LessThan => Ok(boolean_value(num1 < num2)),
+            Self::LessThanOrEqual => Ok(boolean_value(num1 <= num2)),
+            Self::GreaterThan => Ok(boolean_value(num1 > num2)),
+            Self::GreaterThanOrEqual => Ok(boolean_value(num1 >= num2)),
+            Self::Equal => Ok(boolean_value(num1 == num2)),
+            Self::NotEqual => Ok(boolean_value(num1 != num2)),
+            Self::And => Ok(boolean_value(as_boolean(num1)? && as_boolean(num2)?)),
+            Self::Or => Ok(boolean_value(as_boolean(num1)? || as_boolean(num2)?)),
+            _ => Err(MathError::InvalidOperator {
+                span: None,
+                operator: *self,
+            }),
         }
     }
 
-    pub fn apply_operation_1_operand(&self, num1: f64) -> Result<f64, ()> {
+    pub fn apply_operation_1_operand(&self, num1: f64) -> Result<f64, MathError> {
         match self {
             Self::Addition => Ok(num1),
             Self::Subtraction => Ok(-num1),
-            _ => Err(()),
+            Self::Factorial => factorial(num1),
+            Self::DoubleFactorial => double_factorial(num1),
+            Self::Not => Ok(boolean_value(!as_boolean(num1)?)),
+            _ => Err(MathError::InvalidOperator {
+                span: None,
+                operator: *self,
+            }),
+        }
+    }
+
+    /// this will decide a logical operation from its left operand alone if it can, like "0 && x" which is false
+    /// whatever x is. the right operand of a logical operator is only evaluated if this gives none.
+    pub fn short_circuit(&self, num1: f64) -> Result<Option<f64>, MathError> {
+        match self {
+            Self::And if !as_boolean(num1)? => Ok(Some(boolean_value(false))),
+            Self::Or if as_boolean(num1)? => Ok(Some(boolean_value(true))),
+            _ => Ok(None),
         }
     }
 
+    pub fn is_binary_operator(&self) -> bool {
+        matches!(
+            self,
+            Self::Addition
+                | Self::Subtraction
+                | Self::Multiplication
+                | Self::Division
+                | Self::Modulus
+                | Self::Exponentiation
+        ) || self.is_boolean_operator() && *self != Self::Not
+    }
+
+    pub fn is_prefix_operator(&self) -> bool {
+        matches!(self, Self::Addition | Self::Subtraction | Self::Not)
+    }
+
+    pub fn is_comparison_operator(&self) -> bool {
+        matches!(
+            self,
+            Self::LessThan
+                | Self::LessThanOrEqual
+                | Self::GreaterThan
+                | Self::GreaterThanOrEqual
+                | Self::Equal
+                | Self::NotEqual
+        )
+    }
+
+    /// a comparison or a logical operator, which always gives a boolean.
+    pub fn is_boolean_operator(&self) -> bool {
+        self.is_comparison_operator() || matches!(self, Self::And | Self::Or | Self::Not)
+    }
+
+    pub fn is_postfix_operator(&self) -> bool {
+        matches!(self, Self::Factorial | Self::DoubleFactorial)
+    }
+
     pub fn get_inverse_operator(&self) -> Option<Self> {
         match self {
             Self::Addition => Some(Self::Subtraction),
@@ -140,11 +265,52 @@ impl Operator {
         }
     }
 
-    pub fn is_addition_or_subtraction(&self) -> bool {
-        if let Self::Addition | Self::Subtraction = self {
-            true
-        } else {
-            false
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Addition => "+",
+            Self::Subtraction => "-",
+            Self::Multiplication | Self::ImplicitMultiplication => "*",
+            Self::Division => "/",
+            Self::Modulus => "%",
+            Self::Exponentiation => "^",
+            Self::Factorial => "!",
+            Self::DoubleFactorial => "!!",
+            Self::OpenParen => "(",
+            Self::CloseParen => ")",
+            Self::ArgumentSeparator => ",",
+            Self::FunctionAssignment => ":",
+            Self::Assignment => "=",
+            Self::StatementSeparator => ";",
+            Self::Lambda => "->",
+            Self::LessThan => "<",
+            Self::LessThanOrEqual => "<=",
+            Self::GreaterThan => ">",
+            Self::GreaterThanOrEqual => ">=",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::And => "&&",
+            Self::Or => "||",
+            Self::Not => "!",
+            Self::Conditional => "?",
+            Self::OpenBracket => "[",
+            Self::CloseBracket => "]",
+            Self::PlusMinus => "±",
         }
     }
+
+    /// this will tell if a statement can not end with the operator, so a newline after it does not end the statement.
+    pub fn expects_operand(&self) -> bool {
+        !matches!(
+            self,
+            Self::CloseParen
+                | Self::CloseBracket
+                | Self::Factorial
+                | Self::DoubleFactorial
+                | Self::StatementSeparator
+        )
+    }
+
+    pub fn is_addition_or_subtraction(&self) -> bool {
+        matches!(self, Self::Addition | Self::Subtraction)
+    }
 }